@@ -0,0 +1,82 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::Extent3d,
+};
+
+use rusty_automata::{
+    RustyAutomataApp,
+    automata::{
+        AutomataField,
+        AutomataPlugin,
+        StepLimit,
+    },
+    neat::{
+        NeatField,
+        NeatPlugin,
+    },
+    readback::{
+        FieldSnapshot,
+        Readback,
+        ReadbackPlugin,
+    },
+    utils::setup_hooks,
+};
+
+
+// STEPS=1000 cargo run --example headless
+fn example_app() {
+    let steps = std::env::var("STEPS")
+        .ok()
+        .and_then(|steps| steps.parse().ok())
+        .unwrap_or(500);
+
+    App::new()
+        .add_plugins((
+            RustyAutomataApp::headless(),
+            AutomataPlugin,
+            NeatPlugin,
+            ReadbackPlugin,
+        ))
+        .insert_resource(StepLimit::new(steps))
+        .add_systems(Startup, setup)
+        .add_systems(Update, log_activity)
+        .run();
+}
+
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let field_size = Extent3d {
+        width: 256,
+        height: 256,
+        depth_or_array_layers: 1,
+    };
+
+    let mut readback = Readback::every(100);
+    readback.edges = false;
+    readback.uaf = false;
+    commands.insert_resource(readback);
+
+    commands.insert_resource(AutomataField::new(field_size, 25, &mut images));
+    commands.insert_resource(NeatField::new(field_size, &mut images));
+}
+
+
+fn log_activity(
+    mut snapshots: EventReader<FieldSnapshot>,
+) {
+    for snapshot in snapshots.iter() {
+        let values = snapshot.values();
+        let activity = values.iter().map(|value| value.abs()).sum::<f32>() / values.len().max(1) as f32;
+
+        info!("step {}: mean activity {activity:.4}", snapshot.step);
+    }
+}
+
+
+pub fn main() {
+    setup_hooks();
+    example_app();
+}
@@ -0,0 +1,188 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::Extent3d,
+};
+use bevy_egui::{
+    egui,
+    EguiContexts,
+};
+use rand::{
+    rngs::StdRng,
+    SeedableRng,
+};
+
+use rusty_automata::{
+    RustyAutomataApp,
+    automata::{
+        AutomataField,
+        AutomataPlugin,
+        EdgeInit,
+    },
+    evolution::{
+        EvolutionConfig,
+        EvolutionFile,
+        Genome,
+        Innovations,
+    },
+    hyperneat::{
+        HyperNeatField,
+        HyperNeatPlugin,
+        CPPN_INPUTS,
+        CPPN_OUTPUTS,
+    },
+    neat::{
+        NeatField,
+        NeatPlugin,
+    },
+    utils::setup_hooks,
+};
+
+
+fn example_app() {
+    App::new()
+        .add_plugins((
+            RustyAutomataApp::default(),
+            AutomataPlugin,
+            NeatPlugin,
+            HyperNeatPlugin,
+        ))
+        .init_resource::<CppnBreeder>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, hyperneat_ui)
+        .run();
+}
+
+
+// mutates a single cppn by hand, every change restarts the field
+#[derive(Resource)]
+struct CppnBreeder {
+    config: EvolutionConfig,
+    innovations: Innovations,
+    rng: StdRng,
+    seed: u64,
+}
+
+impl Default for CppnBreeder {
+    fn default() -> Self {
+        Self {
+            config: EvolutionConfig::default(),
+            innovations: Innovations::new(CPPN_INPUTS, CPPN_OUTPUTS),
+            rng: StdRng::seed_from_u64(0),
+            seed: 0,
+        }
+    }
+}
+
+impl CppnBreeder {
+    fn genome(&mut self) -> Genome {
+        self.rng = StdRng::seed_from_u64(self.seed);
+        Genome::minimal(
+            CPPN_INPUTS,
+            CPPN_OUTPUTS,
+            self.config.output_uaf,
+            &mut self.innovations,
+            &self.config.mutation,
+            &mut self.rng,
+        )
+    }
+
+    fn mutate(&mut self, genome: &mut Genome) {
+        self.innovations.next_generation();
+        genome.mutate(&mut self.innovations, &self.config.mutation, &mut self.rng);
+    }
+}
+
+
+fn setup(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    mut images: ResMut<Assets<Image>>,
+    mut breeder: ResMut<CppnBreeder>,
+) {
+    let window = windows.single();
+    let field_size = Extent3d {
+        width: window.resolution.width() as u32,
+        height: window.resolution.height() as u32,
+        depth_or_array_layers: 1,
+    };
+
+    let automata_field = AutomataField::new(field_size, 25, &mut images).with_edge_init(EdgeInit::Cppn);
+    let neat_field = NeatField::new(field_size, &mut images);
+
+    commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            custom_size: Some(Vec2::new(window.resolution.width(), window.resolution.height())),
+            ..default()
+        },
+        texture: automata_field.nodes.clone(),
+        ..default()
+    });
+
+    commands.insert_resource(automata_field);
+    commands.insert_resource(neat_field);
+    commands.insert_resource(HyperNeatField::new(breeder.genome()));
+}
+
+
+const CPPN_PATH: &str = "cppn.json";
+
+fn hyperneat_ui(
+    mut contexts: EguiContexts,
+    mut breeder: ResMut<CppnBreeder>,
+    hyperneat: Option<ResMut<HyperNeatField>>,
+) {
+    let Some(mut hyperneat) = hyperneat else {
+        return;
+    };
+
+    egui::Window::new("hyperneat").show(contexts.ctx_mut(), |ui| {
+        ui.label(format!(
+            "cppn: {} hidden nodes, {} of {} connections enabled",
+            hyperneat.genome.hidden_count(),
+            hyperneat.genome.enabled_connections().count(),
+            hyperneat.genome.connections.len(),
+        ));
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut breeder.seed).prefix("seed: "));
+
+            if ui.button("new genome").clicked() {
+                hyperneat.genome = breeder.genome();
+            }
+            if ui.button("mutate").clicked() {
+                breeder.mutate(&mut hyperneat.genome);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("save cppn.json").clicked() {
+                if let Err(err) = hyperneat.genome.save(CPPN_PATH) {
+                    error!("failed to save {CPPN_PATH}: {err}");
+                }
+            }
+            if ui.button("load cppn.json").clicked() {
+                match Genome::load(CPPN_PATH) {
+                    Ok(genome) => hyperneat.genome = genome,
+                    Err(err) => error!("failed to load {CPPN_PATH}: {err}"),
+                }
+            }
+        });
+
+        let mut weight_threshold = hyperneat.weight_threshold;
+        let mut uaf_scale = hyperneat.uaf_scale;
+        let threshold_changed = ui.add(egui::Slider::new(&mut weight_threshold, 0.0..=0.9).text("weight threshold")).changed();
+        let scale_changed = ui.add(egui::Slider::new(&mut uaf_scale, 0.0..=2.0).text("uaf scale")).changed();
+
+        // only touch the resource on edits, any change restarts the field
+        if threshold_changed || scale_changed {
+            hyperneat.weight_threshold = weight_threshold;
+            hyperneat.uaf_scale = uaf_scale;
+        }
+    });
+}
+
+
+pub fn main() {
+    setup_hooks();
+    example_app();
+}
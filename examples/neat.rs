@@ -3,10 +3,12 @@ use bevy::{
     reflect::TypeUuid,
     render::render_resource::{
         AsBindGroup,
-        Extent3d,
         ShaderRef,
     },
-    sprite::Material2d,
+    sprite::{
+        Material2d,
+        MaterialMesh2dBundle,
+    },
 };
 use num_format::{Locale, ToFormattedString};
 
@@ -16,10 +18,24 @@ use rusty_automata::{
         AutomataField,
         AutomataPlugin,
     },
+    checkpoint::CheckpointPlugin,
+    criticality::CriticalityPlugin,
+    experiment::{
+        Experiment,
+        ExperimentPlugin,
+    },
     neat::{
-        NeatField,
+        FieldInput,
+        InputMode,
+        InputRegion,
         NeatPlugin,
     },
+    readback::ReadbackPlugin,
+    trace::{
+        TraceMaterial,
+        TracePlugin,
+        Traces,
+    },
     utils::setup_hooks,
 };
 
@@ -29,35 +45,42 @@ fn example_app() {
             RustyAutomataApp::default(),
             AutomataPlugin::default(),
             NeatPlugin::default(),
+            ReadbackPlugin,
+            CheckpointPlugin,
+            CriticalityPlugin,
+            TracePlugin,
+            ExperimentPlugin,
         ))
         .add_systems(Startup, setup)
+        .add_systems(Update, (
+            setup_field.run_if(resource_added::<AutomataField>()),
+            drive_input,
+        ))
         .run();
 }
 
 
+// edit the config while the example runs to restart the field with it
 fn setup(
     mut commands: Commands,
-    windows: Query<&Window>,
-    mut images: ResMut<Assets<Image>>,
+    asset_server: Res<AssetServer>,
 ) {
-    // TODO: pull from config/UI
+    commands.insert_resource(Experiment::new(asset_server.load("experiments/neat.experiment.ron")));
+}
 
+// once the first field of the experiment exists, later fields reuse the sprite
+fn setup_field(
+    mut commands: Commands,
+    automata_field: Res<AutomataField>,
+    windows: Query<&Window>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut trace_materials: ResMut<Assets<TraceMaterial>>,
+    mut traces: ResMut<Traces>,
+    mut field_input: ResMut<FieldInput>,
+) {
     let window = windows.single();
-    let field_size = Extent3d {
-        width: window.resolution.width() as u32,
-        height: window.resolution.height() as u32,
-        depth_or_array_layers: 1,
-    };
-
-    // TODO: change to creation args struct
-    let edge_count: u32 = 25;
-
-    let automata_field = AutomataField::new(
-        field_size,
-        edge_count,
-        &mut images
-    );
-    let neat_field = NeatField::new(field_size, &mut images);
+    let field_size = automata_field.size();
+    let edge_count = automata_field.edge_count();
 
     commands.spawn(SpriteBundle {
         sprite: Sprite {
@@ -68,8 +91,26 @@ fn setup(
         ..default()
     });
 
-    commands.insert_resource(automata_field);
-    commands.insert_resource(neat_field);
+    // a small square left of center, driven by `drive_input`
+    field_input.add_region(InputRegion::new(
+        UVec2::new(field_size.width / 4, field_size.height / 2) - UVec2::splat(4),
+        UVec2::splat(8),
+        InputMode::Add,
+    ));
+
+    // a few nodes along the horizontal center line, drawn as stacked traces over the bottom of the field
+    let probe_count = 8;
+    for i in 0..probe_count {
+        traces.pin(UVec2::new(field_size.width * (2 * i + 1) / (2 * probe_count), field_size.height / 2));
+    }
+
+    let trace_size = Vec2::new(window.resolution.width(), window.resolution.height() * 0.3);
+    commands.spawn(MaterialMesh2dBundle {
+        mesh: meshes.add(Mesh::from(shape::Quad::new(trace_size))).into(),
+        material: trace_materials.add(TraceMaterial::new(&traces)),
+        transform: Transform::from_xyz(0.0, (trace_size.y - window.resolution.height()) * 0.5, 1.0),
+        ..default()
+    });
 
     println!("field_size: {:?}x{:?}", field_size.width, field_size.height);
     let parameters = (field_size.width * field_size.height * 8 + edge_count * 4) * field_size.depth_or_array_layers;
@@ -77,6 +118,19 @@ fn setup(
 }
 
 
+// slow oscillation pushing the input region up and down
+fn drive_input(
+    time: Res<Time>,
+    mut field_input: ResMut<FieldInput>,
+) {
+    let value = (time.elapsed_seconds() * std::f32::consts::TAU * 0.5).sin();
+
+    if let Some(region) = field_input.region_mut(0) {
+        region.fill(value);
+    }
+}
+
+
 // TODO(test): add visual remap layer via fragment shader
 #[derive(AsBindGroup, Clone, Debug, Default, Reflect, TypeUuid)]
 #[uuid = "ac2f08eb-5234-1262-5556-51571ea332d5"]
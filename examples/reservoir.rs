@@ -0,0 +1,161 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::Extent3d,
+};
+use bevy_egui::{
+    egui,
+    EguiContexts,
+};
+
+use rusty_automata::{
+    RustyAutomataApp,
+    automata::{
+        AutomataField,
+        AutomataPlugin,
+    },
+    neat::{
+        NeatField,
+        NeatPlugin,
+    },
+    reservoir::{
+        Reservoir,
+        ReservoirBenchmark,
+        ReservoirConfig,
+        ReservoirPlugin,
+    },
+    utils::setup_hooks,
+};
+
+
+fn example_app() {
+    App::new()
+        .add_plugins((
+            RustyAutomataApp::default(),
+            AutomataPlugin,
+            NeatPlugin,
+            ReservoirPlugin,
+        ))
+        .init_resource::<BenchmarkSettings>()
+        .add_systems(Startup, setup)
+        .add_systems(Update, reservoir_ui)
+        .run();
+}
+
+
+fn setup(
+    mut commands: Commands,
+    windows: Query<&Window>,
+    mut images: ResMut<Assets<Image>>,
+    mut reservoir: ResMut<Reservoir>,
+) {
+    let window = windows.single();
+    let field_size = Extent3d {
+        width: window.resolution.width() as u32,
+        height: window.resolution.height() as u32,
+        depth_or_array_layers: 1,
+    };
+
+    let automata_field = AutomataField::new(field_size, 25, &mut images);
+    let neat_field = NeatField::new(field_size, &mut images);
+
+    commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            custom_size: Some(Vec2::new(window.resolution.width(), window.resolution.height())),
+            ..default()
+        },
+        texture: automata_field.nodes.clone(),
+        ..default()
+    });
+
+    commands.insert_resource(automata_field);
+    commands.insert_resource(neat_field);
+
+    reservoir.config = ReservoirConfig::for_field(field_size);
+}
+
+
+#[derive(Resource)]
+struct BenchmarkSettings {
+    benchmark: ReservoirBenchmark,
+    len: usize,
+    seed: u64,
+}
+
+impl Default for BenchmarkSettings {
+    fn default() -> Self {
+        Self {
+            benchmark: ReservoirBenchmark::Narma10,
+            len: 2000,
+            seed: 0,
+        }
+    }
+}
+
+fn reservoir_ui(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<BenchmarkSettings>,
+    mut reservoir: ResMut<Reservoir>,
+) {
+    let settings = &mut *settings;
+
+    egui::Window::new("reservoir").show(contexts.ctx_mut(), |ui| {
+        egui::ComboBox::from_label("benchmark")
+            .selected_text(settings.benchmark.name())
+            .show_ui(ui, |ui| {
+                for benchmark in ReservoirBenchmark::ALL {
+                    ui.selectable_value(&mut settings.benchmark, benchmark, benchmark.name());
+                }
+            });
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut settings.len).clamp_range(200..=20000).prefix("steps: "));
+            ui.add(egui::DragValue::new(&mut settings.seed).prefix("seed: "));
+            ui.add(egui::DragValue::new(&mut reservoir.config.ridge).speed(1e-5).prefix("ridge: "));
+        });
+
+        match reservoir.progress() {
+            Some(progress) => {
+                ui.add(egui::ProgressBar::new(progress).show_percentage());
+            }
+            None => {
+                if ui.button("run").clicked() {
+                    reservoir.start_benchmark(settings.benchmark, settings.len, settings.seed);
+                }
+            }
+        }
+
+        let Some(report) = &reservoir.report else {
+            return;
+        };
+
+        ui.label(format!(
+            "nrmse train: {:.4} ({} steps)  test: {:.4} ({} steps)",
+            report.train_nrmse,
+            report.train_len,
+            report.test_nrmse,
+            report.test_len,
+        ));
+
+        let points = |values: &[f32]| -> egui::plot::PlotPoints {
+            values
+                .iter()
+                .enumerate()
+                .map(|(i, &value)| [i as f64, value as f64])
+                .collect()
+        };
+
+        egui::plot::Plot::new("reservoir_test_split")
+            .legend(egui::plot::Legend::default())
+            .height(240.0)
+            .show(ui, |plot_ui| {
+                plot_ui.line(egui::plot::Line::new(points(&report.targets)).name("target"));
+                plot_ui.line(egui::plot::Line::new(points(&report.predictions)).name("readout"));
+            });
+    });
+}
+
+
+pub fn main() {
+    setup_hooks();
+    example_app();
+}
@@ -1,48 +1,53 @@
 use bevy::{
     prelude::*,
-    reflect::TypeUuid,
-    render::{
-        render_asset::RenderAssets,
-        render_resource::{
-            AsBindGroup,
-            AsBindGroupShaderType,
-            Extent3d,
-            ShaderRef,
-            ShaderType,
-        },
-    },
-    sprite::{
-        Material2d,
-        Material2dPlugin,
-        MaterialMesh2dBundle
-    },
+    render::render_resource::Extent3d,
+    sprite::MaterialMesh2dBundle,
 };
-use bevy_inspector_egui::{
-    InspectorOptions,
-    inspector_options::std_options::NumberDisplay,
-    prelude::ReflectInspectorOptions,
-    quick::AssetInspectorPlugin,
+use bevy_egui::{
+    egui,
+    EguiContexts,
 };
+use bevy_inspector_egui::quick::ResourceInspectorPlugin;
+use bevy_pancam::PanCam;
 
 use rusty_automata::{
     RustyAutomataApp,
-    plot::PlotPlugin,
-    uaf::UafPlugin,
+    plot::{
+        curve_color,
+        legend_ui,
+        PlotCurve,
+        PlotMaterial,
+        PlotPlugin,
+        PlotView,
+        MAX_PLOT_CURVES,
+    },
+    uaf::{
+        FitMethod,
+        FitResult,
+        Uaf,
+        UafFit,
+        UafPreset,
+    },
     utils::setup_hooks,
 };
 
 
+const REFERENCE_COLOR: Color = Color::rgba(0.85, 0.85, 0.85, 0.8);
+
+
 fn example_app() {
     App::new()
         .add_plugins((
             RustyAutomataApp::default(),
             PlotPlugin,
-            UafPlugin,
-            Material2dPlugin::<UafMaterial>::default(),
         ))
-        .register_asset_reflect::<UafMaterial>()
-        .add_plugins(AssetInspectorPlugin::<UafMaterial>::default())
+        .register_type::<UafPlot>()
+        .init_resource::<UafPlot>()
+        .add_plugins(ResourceInspectorPlugin::<UafPlot>::default())
+        .init_resource::<FitSettings>()
         .add_systems(Startup, setup_screen)
+        .add_systems(PostStartup, disable_pancam)
+        .add_systems(Update, (fit_ui, update_plot, legend_window).chain())
         .run();
 }
 
@@ -50,7 +55,8 @@ fn example_app() {
 fn setup_screen(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut uaf_materials: ResMut<Assets<UafMaterial>>,
+    mut plot_materials: ResMut<Assets<PlotMaterial>>,
+    asset_server: Res<AssetServer>,
     windows: Query<&Window>,
 ) {
     let window = windows.single();
@@ -59,13 +65,15 @@ fn setup_screen(
         height: window.resolution.physical_height(),
         depth_or_array_layers: 1,
     };
+    let quad_size = Vec2::new(size.width as f32, size.height as f32);
+
+    let quad_handle = meshes.add(Mesh::from(shape::Quad::new(quad_size)));
 
-    let quad_handle = meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
-        size.width as f32,
-        size.height as f32,
-    ))));
+    let mut material = PlotMaterial::default();
+    material.fit_aspect(quad_size);
 
-    let material_handle = uaf_materials.add(UafMaterial::default());
+    let material_handle = plot_materials.add(material);
+    commands.insert_resource(UafPlotMaterial(material_handle.clone()));
 
     commands.spawn((
         MaterialMesh2dBundle {
@@ -77,6 +85,7 @@ fn setup_screen(
             },
             ..default()
         },
+        PlotView::new(quad_size, &asset_server),
     ));
 
     commands.spawn((
@@ -87,66 +96,229 @@ fn setup_screen(
 }
 
 
-// TODO: figure out why material asset handle ID is displayed instead of the material name
-#[derive(AsBindGroup, Clone, Debug, InspectorOptions, Reflect, TypeUuid)]
-#[reflect(Debug, Default, InspectorOptions)]
-#[uuid = "ac2f08eb-67fa-23f1-a908-51571ea332d5"]
-#[uniform(0, UafMaterialUniform)]
-struct UafMaterial {
-    #[inspector(min = -1.0, max = 1.0, display = NumberDisplay::Slider)]
-    a: f32,
-    #[inspector(min = -1.0, max = 1.0, display = NumberDisplay::Slider)]
-    b: f32,
-    #[inspector(min = -1.0, max = 1.0, display = NumberDisplay::Slider)]
-    c: f32,
-    #[inspector(min = -1.0, max = 1.0, display = NumberDisplay::Slider)]
-    d: f32,
-    #[inspector(min = -1.0, max = 1.0, display = NumberDisplay::Slider)]
-    e: f32,
+// dragging and scrolling pan and zoom the plotted interval instead of the camera
+fn disable_pancam(
+    mut pancams: Query<&mut PanCam>,
+) {
+    for mut pancam in &mut pancams {
+        pancam.enabled = false;
+    }
+}
+
+
+#[derive(Clone, Debug, Reflect)]
+struct UafCurve {
+    label: String,
+    uaf: Uaf,
+    color: Color,
+    visible: bool,
+}
+
+impl Default for UafCurve {
+    fn default() -> Self {
+        Self::preset(UafPreset::Sigmoid, 0)
+    }
+}
+
+impl UafCurve {
+    fn new(label: impl Into<String>, uaf: Uaf, color: Color) -> Self {
+        Self {
+            label: label.into(),
+            uaf,
+            color,
+            visible: true,
+        }
+    }
+
+    fn preset(preset: UafPreset, index: usize) -> Self {
+        Self::new(preset.name(), preset.uaf(), curve_color(index))
+    }
+}
+
+
+// curves beyond `MAX_PLOT_CURVES` (derivatives and the reference included) are not drawn
+#[derive(Resource, Clone, Debug, Reflect)]
+#[reflect(Resource)]
+struct UafPlot {
+    curves: Vec<UafCurve>,
+    show_derivatives: bool,
+    reference: Option<UafPreset>,
     animate: bool,
 }
 
-impl Default for UafMaterial {
+impl Default for UafPlot {
     fn default() -> Self {
-        // default to sigmoid - https://arxiv.org/pdf/2011.03842.pdf
         Self {
-            a: 1.01605291,
-            b: 0.492100,
-            c: 0.0,
-            d: 1.01605291,
-            e: 0.0,
+            curves: vec![UafCurve::default()],
+            show_derivatives: false,
+            reference: None,
             animate: false,
         }
     }
 }
 
-impl Material2d for UafMaterial {
-    fn fragment_shader() -> ShaderRef {
-        "shaders/uaf.wgsl".into()
+impl UafPlot {
+    fn set_first(&mut self, curve: UafCurve) {
+        match self.curves.first_mut() {
+            Some(first) => *first = curve,
+            None => self.curves.push(curve),
+        }
+    }
+
+    fn plot_curves(&self, time: f32) -> Vec<PlotCurve> {
+        // slow wobble of a, b, c and d
+        let offset = if self.animate {
+            Vec4::new(
+                2.0 * (time * 0.25).sin(),
+                0.8 * (time * 0.5 + 1.3).sin(),
+                (time * 0.5 + 2.1).sin(),
+                2.0 * (time * 0.25 + 3.7).sin(),
+            )
+        } else {
+            Vec4::ZERO
+        };
+
+        let mut curves = Vec::new();
+
+        if let Some(reference) = self.reference {
+            curves.push(PlotCurve::reference(reference, REFERENCE_COLOR).with_label(format!("{} (reference)", reference.name())));
+        }
+
+        for curve in self.curves.iter().filter(|curve| curve.visible) {
+            let uaf = Uaf::from_texel(curve.uaf.texel() + offset, curve.uaf.e);
+
+            curves.push(PlotCurve::uaf(&curve.label, uaf, curve.color));
+            if self.show_derivatives {
+                curves.push(PlotCurve::derivative(format!("d/dx {}", curve.label), uaf, curve.color.with_a(0.7)));
+            }
+        }
+
+        curves.truncate(MAX_PLOT_CURVES);
+        curves
+    }
+}
+
+
+#[derive(Resource)]
+struct UafPlotMaterial(Handle<PlotMaterial>);
+
+fn update_plot(
+    time: Res<Time>,
+    plot: Res<UafPlot>,
+    mut plot_materials: ResMut<Assets<PlotMaterial>>,
+    material_handle: Res<UafPlotMaterial>,
+) {
+    if !plot.is_changed() && !plot.animate {
+        return;
+    }
+
+    if let Some(material) = plot_materials.get_mut(&material_handle.0) {
+        material.curves = plot.plot_curves(time.elapsed_seconds());
     }
 }
 
-#[derive(Clone, Default, ShaderType)]
-struct UafMaterialUniform {
-    pub a: f32,
-    pub b: f32,
-    pub c: f32,
-    pub d: f32,
-    pub e: f32,
-    pub animate: f32,
+
+#[derive(Resource, Default)]
+struct FitSettings {
+    target: UafPreset,
+    method: FitMethod,
+    result: Option<FitResult>,
 }
 
-impl AsBindGroupShaderType<UafMaterialUniform> for UafMaterial {
-    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> UafMaterialUniform {
-        UafMaterialUniform {
-            a: self.a,
-            b: self.b,
-            c: self.c,
-            d: self.d,
-            e: self.e,
-            animate: if self.animate { 1.0 } else { 0.0 },
+// fits the first curve to the selected preset's reference function
+fn fit_ui(
+    mut contexts: EguiContexts,
+    mut settings: ResMut<FitSettings>,
+    mut plot: ResMut<UafPlot>,
+) {
+    let settings = &mut *settings;
+
+    egui::Window::new("fit").show(contexts.ctx_mut(), |ui| {
+        egui::ComboBox::from_label("fit to")
+            .selected_text(settings.target.name())
+            .show_ui(ui, |ui| {
+                for preset in UafPreset::ALL {
+                    ui.selectable_value(&mut settings.target, preset, preset.name());
+                }
+            });
+
+        egui::ComboBox::from_label("method")
+            .selected_text(settings.method.name())
+            .show_ui(ui, |ui| {
+                for method in FitMethod::ALL {
+                    ui.selectable_value(&mut settings.method, method, method.name());
+                }
+            });
+
+        ui.horizontal(|ui| {
+            if ui.button("fit").clicked() {
+                let target = settings.target;
+                let initial = plot.curves.first().map_or(Uaf::default(), |curve| curve.uaf);
+                let result = UafFit::default()
+                    .with_method(settings.method)
+                    .fit(initial, |x| target.reference(x));
+
+                plot.set_first(UafCurve::new(format!("fit {}", target.name()), result.uaf, curve_color(0)));
+                plot.reference = Some(target);
+                settings.result = Some(result);
+            }
+
+            if ui.button("preset").clicked() {
+                plot.set_first(UafCurve::preset(settings.target, 0));
+                settings.result = None;
+            }
+
+            if ui.button("all presets").clicked() {
+                plot.curves = UafPreset::ALL
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &preset)| UafCurve::preset(preset, i))
+                    .collect();
+                settings.result = None;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            let mut show_derivatives = plot.show_derivatives;
+            if ui.checkbox(&mut show_derivatives, "derivatives").changed() {
+                plot.show_derivatives = show_derivatives;
+            }
+
+            let mut show_reference = plot.reference.is_some();
+            if ui.checkbox(&mut show_reference, "reference").changed() {
+                plot.reference = show_reference.then_some(settings.target);
+            }
+        });
+
+        if let Some(result) = &settings.result {
+            ui.label(format!("rmse: {:.6} after {} iterations", result.error, result.iterations));
+            ui.label(format!(
+                "a: {:.6}  b: {:.6}  c: {:.6}  d: {:.6}  e: {:.6}",
+                result.uaf.a,
+                result.uaf.b,
+                result.uaf.c,
+                result.uaf.d,
+                result.uaf.e,
+            ));
         }
-    }
+    });
+}
+
+fn legend_window(
+    mut contexts: EguiContexts,
+    plot_materials: Res<Assets<PlotMaterial>>,
+    material_handle: Res<UafPlotMaterial>,
+) {
+    let Some(material) = plot_materials.get(&material_handle.0) else {
+        return;
+    };
+
+    egui::Window::new("legend")
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+        .resizable(false)
+        .show(contexts.ctx_mut(), |ui| {
+            legend_ui(ui, &material.legend());
+        });
 }
 
 
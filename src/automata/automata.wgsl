@@ -10,8 +10,15 @@ struct AutomataUniforms {
     seed: f32,
     width: u32,
     height: u32,
+    edge_init: u32,
+    plasticity_rate: f32,
+    plasticity_decay: f32,
 };
 
+const EDGE_INIT_RANDOM: u32 = 0u;
+const EDGE_INIT_KEEP: u32 = 1u;
+const EDGE_INIT_CPPN: u32 = 2u;
+
 
 // TODO: separate init and update shaders so read-only textures can be bound as readonly
 @group(0) @binding(0)
@@ -32,6 +39,13 @@ struct Edge {
     downregulation: f32,
 };
 
+// unused slot of an imported graph, see `EdgeState::EMPTY`
+fn is_empty_edge(
+    edge: Edge,
+) -> bool {
+    return edge.from_node_location.x < 0;
+}
+
 struct State {
     value: f32,
     derivative: f32,
@@ -138,6 +152,9 @@ fn pre_activation(
     var input_sum = current_state.value;
     for (var i = 0u; i < automata_uniforms.edge_count; i = i + 1u) {
         let edge = get_edge(location, i);
+        if (is_empty_edge(edge)) {
+            continue;
+        }
         let from_node = get_state(edge.from_node_location);
 
         input_sum += edge.weight * from_node.value;//(from_node.value - edge.downregulation);
@@ -157,11 +174,48 @@ fn pre_activation(
 }
 
 
+// hebbian update of the node's own edges, call with the next value before `set_next_state`
+fn update_edges(
+    location: vec2<i32>,
+    next_value: f32,
+) {
+    let rate = automata_uniforms.plasticity_rate;
+    let decay = automata_uniforms.plasticity_decay;
+    if (rate == 0.0 && decay == 0.0) {
+        return;
+    }
+
+    let max_weight = automata_uniforms.max_edge_weight;
+    for (var i = 0u; i < automata_uniforms.edge_count; i = i + 1u) {
+        let edge = get_edge(location, i);
+        if (is_empty_edge(edge)) {
+            continue;
+        }
+        let from_node = get_state(edge.from_node_location);
+
+        let weight = edge.weight + rate * from_node.value * next_value - decay * edge.weight;
+
+        set_edge(
+            location,
+            i,
+            Edge(
+                edge.from_node_location,
+                clamp(weight, -max_weight, max_weight),
+                edge.downregulation,
+            )
+        );
+    }
+}
+
+
 fn init_automata(
     location: vec2<i32>,
 ) {
     init_state(location);
-    init_edges(location);
+
+    if (automata_uniforms.edge_init == EDGE_INIT_RANDOM) {
+        init_edges(location);
+    }
 }
 
 fn init_state(
@@ -203,31 +257,41 @@ fn init_edges(
     //let ring_factor = simplex_2d(scaled_location * 100.0);
 
     for (var i = 0u; i < automata_uniforms.edge_count; i = i + 1u) {
-        // TODO: consider gaussian sampling with shaping function from above?
-        let xr = gaussian_rand(scaled_location - f32(i) * 0.07 + automata_uniforms.seed);
-        let yr = gaussian_rand(scaled_location - f32(i) * 0.03 + automata_uniforms.seed);
-
         let edge_weight = gaussian_rand(scaled_location + f32(i) * 0.01 + automata_uniforms.seed) * automata_uniforms.max_edge_weight;
 
-        let edge_offset = vec2<f32>(
-            xr,
-            yr,
-        ) * automata_uniforms.max_radius;
-
-        let field_size =  vec2<i32>(
-            i32(automata_uniforms.width),
-            i32(automata_uniforms.height),
-        );
-        let from_node_location = (location + vec2<i32>(edge_offset) + field_size) % field_size;
-
         set_edge(
             location,
             i,
             Edge(
-                from_node_location,
+                wrap_location(location + edge_offset(location, i)),
                 edge_weight,
                 0.0,
             )
         );
     }
 }
+
+// offset of the i-th edge's from node, the from node location wraps around the field
+fn edge_offset(
+    location: vec2<i32>,
+    index: u32,
+) -> vec2<i32> {
+    let scaled_location = vec2<f32>(location) / vec2<f32>(f32(automata_uniforms.width), f32(automata_uniforms.height));
+
+    // TODO: consider gaussian sampling with shaping function from above?
+    let xr = gaussian_rand(scaled_location - f32(index) * 0.07 + automata_uniforms.seed);
+    let yr = gaussian_rand(scaled_location - f32(index) * 0.03 + automata_uniforms.seed);
+
+    return vec2<i32>(vec2<f32>(xr, yr) * automata_uniforms.max_radius);
+}
+
+fn wrap_location(
+    location: vec2<i32>,
+) -> vec2<i32> {
+    let field_size = vec2<i32>(
+        i32(automata_uniforms.width),
+        i32(automata_uniforms.height),
+    );
+
+    return (location % field_size + field_size) % field_size;
+}
@@ -1,3 +1,12 @@
+use std::sync::{
+    mpsc::{
+        channel,
+        Receiver,
+        Sender,
+    },
+    Mutex,
+};
+
 use bevy::{
     asset::{
         load_internal_asset,
@@ -35,11 +44,18 @@ use bevy::{
             TextureViewDimension,
             UniformBuffer,
         },
+        settings::WgpuLimits,
+        texture::Volume,
         Render,
         RenderApp,
         RenderSet,
     },
 };
+use bevy_inspector_egui::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
 
 
 const AUTOMATA_SHADER_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 6712956732940);
@@ -61,12 +77,25 @@ impl Plugin for AutomataPlugin {
             ExtractResourcePlugin::<AutomataField>::default(),
         ));
 
+        let (sender, receiver) = channel();
+
+        app.register_type::<AutomataField>();
+        app.register_type::<EdgeInit>();
+
+        app.add_event::<StepLimitReached>();
+        app.init_resource::<FieldStep>();
+        app.init_resource::<StepLimit>();
+        app.insert_resource(StepReceiver(Mutex::new(receiver)));
+        app.add_systems(PreUpdate, receive_field_step);
+
         let render_app = app.sub_app_mut(RenderApp);
+        render_app.insert_resource(StepSender(Mutex::new(sender)));
         render_app.add_systems(
             Render,
             (
                 prepare_automata_uniforms.in_set(RenderSet::Prepare),
                 queue_automata_bind_group.in_set(RenderSet::Queue),
+                send_field_step.in_set(RenderSet::Cleanup),
             )
         );
     }
@@ -79,64 +108,410 @@ impl Plugin for AutomataPlugin {
 }
 
 
-// TODO: set max number of steps /w completion event
-#[derive(Resource, Clone, ExtractResource)]
+// the parameters are editable from the inspector, the `NeatPlugin` re-creates the field when they change
+//   max_radius, max_edge_weight and seed only shape the initial edges, so they restart the field as well
+//   edited sizes and edge counts only apply on restart, until then the textures keep their allocated layout
+#[derive(Resource, Clone, ExtractResource, Reflect, InspectorOptions)]
+#[reflect(Resource, InspectorOptions)]
 pub struct AutomataField {
+    #[reflect(ignore)]
     pub edges: Handle<Image>,
+    #[reflect(ignore)]
     pub nodes: Handle<Image>,
+    // one edges texture layer per edge
+    #[inspector(min = 1, max = 256)]
     edge_count: u32,
+    #[inspector(min = 0.0, max = 128.0)]
     max_radius: f32,
+    #[inspector(min = 0.0, max = 128.0)]
     max_edge_weight: f32,
     seed: f32,
+    #[inspector(min = 1, max = 8192)]
     width: u32,
+    #[inspector(min = 1, max = 8192)]
     height: u32,
+    // size of the edges texture, one layer per edge
+    #[reflect(ignore)]
+    allocated: Extent3d,
+    #[reflect(ignore)]
+    resume_step: Option<u64>,
+    edge_init: EdgeInit,
+    // applied every step, edits take effect without a restart
+    plasticity: Plasticity,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldLimitError {
+    Dimension {
+        width: u32,
+        height: u32,
+        max: u32,
+    },
+    Layers {
+        edge_count: u32,
+        max: u32,
+    },
+    // None when the size overflows
+    Bytes {
+        bytes: Option<u64>,
+        max: u64,
+    },
+}
+
+impl std::fmt::Display for FieldLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldLimitError::Dimension { width, height, max } => write!(f, "a {width}x{height} field exceeds the {max} texel texture limit"),
+            FieldLimitError::Layers { edge_count, max } => write!(f, "{edge_count} edges exceed the {max} texture layer limit"),
+            FieldLimitError::Bytes { bytes: Some(bytes), max } => write!(f, "{bytes} bytes of edges exceed the {max} byte buffer limit"),
+            FieldLimitError::Bytes { bytes: None, max } => write!(f, "the edges exceed the {max} byte buffer limit"),
+        }
+    }
+}
+
+impl std::error::Error for FieldLimitError {}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum EdgeInit {
+    // gaussian offsets and weights from `init_edges`
+    #[default]
+    Random,
+    // the edges texture already holds the connectivity, e.g. from an imported graph
+    Keep,
+    // weights of the `Random` edge locations are queried from a cppn, see `HyperNeatPlugin`
+    Cppn,
+}
+
+// hebbian edge plasticity, each step a weight moves by `rate * pre * post` and decays by `decay * weight`
+//   weights stay within max_edge_weight, the default of zero keeps the edges fixed
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Plasticity {
+    pub rate: f32,
+    pub decay: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutomataParameters {
+    pub edge_count: u32,
+    pub max_radius: f32,
+    pub max_edge_weight: f32,
+    pub seed: f32,
+}
+
+impl Default for AutomataParameters {
+    fn default() -> Self {
+        Self {
+            edge_count: 25,
+            max_radius: 15.0,
+            max_edge_weight: 16.0,
+            seed: 1.0,
+        }
+    }
+}
+
+
+// a default sized field with the default parameters, e.g. for `init_resource`
+impl FromWorld for AutomataField {
+    fn from_world(world: &mut World) -> Self {
+        let mut images = world.resource_mut::<Assets<Image>>();
+        Self::from_parameters(Self::DEFAULT_SIZE, AutomataParameters::default(), &mut images)
+    }
 }
 
-// TODO: use default implementation
 impl AutomataField {
-    // TODO: from_graph implementation
+    pub const DEFAULT_SIZE: Extent3d = Extent3d {
+        width: 256,
+        height: 256,
+        depth_or_array_layers: 1,
+    };
+
     pub fn new(
         field_size: Extent3d,
         edge_count: u32,
-        images: &mut ResMut<Assets<Image>>,
+        images: &mut Assets<Image>,
     ) -> Self {
-        let mut nodes = Image::new_fill(
+        Self::from_parameters(
             field_size,
-            TextureDimension::D2,
-            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-            TextureFormat::Rgba32Float,
-        );
-        nodes.texture_descriptor.usage = TextureUsages::COPY_DST | TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING;
-        let nodes = images.add(nodes);
+            AutomataParameters {
+                edge_count,
+                ..default()
+            },
+            images,
+        )
+    }
+
+    pub fn from_parameters(
+        field_size: Extent3d,
+        parameters: AutomataParameters,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let nodes = vec![0; field_size.volume() * TEXEL_SIZE];
+        let edges = vec![0; field_size.volume() * TEXEL_SIZE * parameters.edge_count as usize];
 
+        let mut field = Self::restore(field_size, parameters, nodes, edges, 0, images);
+        field.resume_step = None;
+        field
+    }
+
+    // edges as raw rgba32float texel data, node state and uaf parameters are still initialized
+    pub fn from_graph(
+        field_size: Extent3d,
+        parameters: AutomataParameters,
+        edges: Vec<u8>,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let nodes = vec![0; field_size.volume() * TEXEL_SIZE];
+
+        let mut field = Self::restore(field_size, parameters, nodes, edges, 0, images);
+        field.resume_step = None;
+        field.edge_init = EdgeInit::Keep;
+        field
+    }
+
+    // rebuilds a field from raw rgba32float texel data, skipping the init pass
+    pub fn restore(
+        field_size: Extent3d,
+        parameters: AutomataParameters,
+        nodes: Vec<u8>,
+        edges: Vec<u8>,
+        step: u64,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let nodes = images.add(storage_image(field_size, nodes));
 
         // 2D to assist cache locality
         let edges_size = Extent3d {
             width: field_size.width,
             height: field_size.height,
-            depth_or_array_layers: field_size.depth_or_array_layers * edge_count,
+            depth_or_array_layers: field_size.depth_or_array_layers * parameters.edge_count,
         };
-
-        let mut edges: Image = Image::new_fill(
-            edges_size,
-            TextureDimension::D2,
-            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-            TextureFormat::Rgba32Float,
-        );
-        edges.texture_descriptor.usage = TextureUsages::COPY_DST | TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING;
-        let edges = images.add(edges);
+        let edges = images.add(storage_image(edges_size, edges));
 
         Self {
             edges,
             nodes,
-            edge_count,
-            max_radius: 15.0,
-            max_edge_weight: 16.0,
-            seed: 1.0,
+            edge_count: parameters.edge_count,
+            max_radius: parameters.max_radius,
+            max_edge_weight: parameters.max_edge_weight,
+            seed: parameters.seed,
             width: field_size.width,
             height: field_size.height,
+            allocated: edges_size,
+            resume_step: Some(step),
+            edge_init: EdgeInit::Random,
+            plasticity: Plasticity::default(),
+        }
+    }
+
+    pub fn with_edge_init(mut self, edge_init: EdgeInit) -> Self {
+        self.edge_init = edge_init;
+        self
+    }
+
+    pub fn with_plasticity(mut self, plasticity: Plasticity) -> Self {
+        self.plasticity = plasticity;
+        self
+    }
+
+    // parameters of the allocated textures
+    pub fn parameters(&self) -> AutomataParameters {
+        AutomataParameters {
+            edge_count: self.edge_count(),
+            ..self.edited_parameters()
+        }
+    }
+
+    pub fn size(&self) -> Extent3d {
+        Extent3d {
+            depth_or_array_layers: 1,
+            ..self.allocated
+        }
+    }
+
+    // the parameters and size a restart would create, e.g. after an inspector edit
+    pub fn edited_parameters(&self) -> AutomataParameters {
+        AutomataParameters {
+            edge_count: self.edge_count,
+            max_radius: self.max_radius,
+            max_edge_weight: self.max_edge_weight,
+            seed: self.seed,
+        }
+    }
+
+    pub fn edited_size(&self) -> Extent3d {
+        Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
         }
     }
+
+    // drops edits of the size and edge count
+    pub fn revert_layout(&mut self) {
+        self.width = self.allocated.width;
+        self.height = self.allocated.height;
+        self.edge_count = self.allocated.depth_or_array_layers;
+    }
+
+    // the edges texture is the largest texture and is read back whole, so it has to fit a buffer
+    pub fn check_limits(
+        field_size: Extent3d,
+        edge_count: u32,
+        limits: &WgpuLimits,
+    ) -> Result<(), FieldLimitError> {
+        let dimension = limits.max_texture_dimension_2d;
+        if field_size.width > dimension || field_size.height > dimension {
+            return Err(FieldLimitError::Dimension {
+                width: field_size.width,
+                height: field_size.height,
+                max: dimension,
+            });
+        }
+
+        if edge_count > limits.max_texture_array_layers {
+            return Err(FieldLimitError::Layers {
+                edge_count,
+                max: limits.max_texture_array_layers,
+            });
+        }
+
+        let edges_bytes = (field_size.width as u64)
+            .checked_mul(field_size.height as u64)
+            .and_then(|texels| texels.checked_mul(edge_count as u64))
+            .and_then(|texels| texels.checked_mul(TEXEL_SIZE as u64));
+        match edges_bytes {
+            Some(bytes) if bytes <= limits.max_buffer_size => Ok(()),
+            bytes => Err(FieldLimitError::Bytes {
+                bytes,
+                max: limits.max_buffer_size,
+            }),
+        }
+    }
+
+    // Some when the textures already hold the state of that step and must not be initialized
+    pub fn resume_step(&self) -> Option<u64> {
+        self.resume_step
+    }
+
+    pub fn edge_init(&self) -> EdgeInit {
+        self.edge_init
+    }
+
+    pub fn plasticity(&self) -> Plasticity {
+        self.plasticity
+    }
+
+    pub fn edge_count(&self) -> u32 {
+        self.allocated.depth_or_array_layers
+    }
+
+    pub fn width(&self) -> u32 {
+        self.allocated.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.allocated.height
+    }
+}
+
+
+// latest step the render world simulated for the current field, None until it has been initialized
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FieldStep(pub Option<u64>);
+
+// sends `StepLimitReached` once per field when it reaches `max_steps`, the field keeps running
+#[derive(Resource, Clone, Debug, Default)]
+pub struct StepLimit {
+    pub max_steps: Option<u64>,
+    reached: Option<Handle<Image>>,
+}
+
+impl StepLimit {
+    pub fn new(max_steps: u64) -> Self {
+        Self {
+            max_steps: Some(max_steps),
+            reached: None,
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct StepLimitReached {
+    pub step: u64,
+}
+
+#[derive(Resource)]
+struct StepSender(Mutex<Sender<(Handle<Image>, Option<u64>)>>);
+
+#[derive(Resource)]
+struct StepReceiver(Mutex<Receiver<(Handle<Image>, Option<u64>)>>);
+
+fn send_field_step(
+    sender: Res<StepSender>,
+    automata: Option<Res<AutomataField>>,
+    pipeline: Option<Res<AutomataPipeline>>,
+) {
+    let (Some(automata), Some(pipeline)) = (automata, pipeline) else {
+        return;
+    };
+
+    let _ = sender.0.lock().unwrap().send((automata.nodes.clone(), pipeline.step));
+}
+
+fn receive_field_step(
+    receiver: Res<StepReceiver>,
+    automata: Option<Res<AutomataField>>,
+    mut field_step: ResMut<FieldStep>,
+    mut limit: ResMut<StepLimit>,
+    mut reached: EventWriter<StepLimitReached>,
+) {
+    let Some(automata) = automata else {
+        return;
+    };
+
+    // steps of a replaced field may still be in flight
+    let latest = receiver.0
+        .lock()
+        .unwrap()
+        .try_iter()
+        .filter(|(field, _)| *field == automata.nodes)
+        .last();
+
+    match latest {
+        Some((_, step)) => field_step.set_if_neq(FieldStep(step)),
+        None if automata.is_changed() => field_step.set_if_neq(FieldStep(None)),
+        None => {}
+    }
+
+    let Some(step) = field_step.0 else {
+        return;
+    };
+
+    let limit_reached = limit.max_steps.is_some_and(|max_steps| step >= max_steps);
+    if limit_reached && limit.reached.as_ref() != Some(&automata.nodes) {
+        limit.reached = Some(automata.nodes.clone());
+        reached.send(StepLimitReached { step });
+    }
+}
+
+
+pub(crate) const TEXEL_SIZE: usize = 16;
+
+// rgba32float texture usable by the compute shaders, readback and texture sampling
+pub(crate) fn storage_image(
+    size: Extent3d,
+    data: Vec<u8>,
+) -> Image {
+    let mut image = Image::new(
+        size,
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba32Float,
+    );
+    image.texture_descriptor.usage = TextureUsages::COPY_DST | TextureUsages::COPY_SRC | TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING;
+    image
 }
 
 
@@ -148,6 +523,9 @@ struct AutomataUniform {
     seed: f32,
     width: u32,
     height: u32,
+    edge_init: u32,
+    plasticity_rate: f32,
+    plasticity_decay: f32,
 }
 
 #[derive(Resource, Default)]
@@ -163,12 +541,15 @@ fn prepare_automata_uniforms(
 ) {
     let buffer = uniform_buffer.buffer.get_mut();
 
-    buffer.edge_count = automata.edge_count;
+    buffer.edge_count = automata.edge_count();
     buffer.max_radius = automata.max_radius;
     buffer.max_edge_weight = automata.max_edge_weight;
     buffer.seed = automata.seed;
-    buffer.width = automata.width;
-    buffer.height = automata.height;
+    buffer.width = automata.width();
+    buffer.height = automata.height();
+    buffer.edge_init = automata.edge_init as u32;
+    buffer.plasticity_rate = automata.plasticity.rate;
+    buffer.plasticity_decay = automata.plasticity.decay;
 
     uniform_buffer.buffer.write_buffer(&render_device, &render_queue);
 }
@@ -209,17 +590,21 @@ fn queue_automata_bind_group(
 
     commands.insert_resource(AutomataBindGroup(bind_group));
 
-    pipeline.width = automata.width;
-    pipeline.height = automata.height;
+    pipeline.width = automata.width();
+    pipeline.height = automata.height();
 }
 
 
-// TODO: internal step counter, validate step count in render world equals main world
+// TODO: validate step count in render world equals main world
 #[derive(Resource)]
 pub struct AutomataPipeline {
     pub bind_group_layout: BindGroupLayout,
     pub width: u32,
     pub height: u32,
+    // None until the field has been initialized, incremented once per update dispatch
+    pub step: Option<u64>,
+    // the field was restored from existing state, so the init pass is skipped
+    pub resumed: bool,
     // TODO: allow dynamic number of fields (workgroup depth)
 }
 
@@ -268,6 +653,62 @@ impl FromWorld for AutomataPipeline {
             bind_group_layout,
             width: 0,
             height: 0,
+            step: None,
+            resumed: false,
         }
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn size(width: u32, height: u32) -> Extent3d {
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    #[test]
+    fn default_field_fits_default_limits() {
+        let limits = WgpuLimits::default();
+        assert_eq!(AutomataField::check_limits(AutomataField::DEFAULT_SIZE, 25, &limits), Ok(()));
+    }
+
+    #[test]
+    fn oversized_fields_are_rejected() {
+        let limits = WgpuLimits::default();
+
+        assert!(matches!(
+            AutomataField::check_limits(size(limits.max_texture_dimension_2d + 1, 16), 1, &limits),
+            Err(FieldLimitError::Dimension { .. }),
+        ));
+        assert!(matches!(
+            AutomataField::check_limits(size(16, 16), limits.max_texture_array_layers + 1, &limits),
+            Err(FieldLimitError::Layers { .. }),
+        ));
+        // the largest inspector edit, about 256 GiB of edges
+        assert!(matches!(
+            AutomataField::check_limits(size(8192, 8192), 256, &limits),
+            Err(FieldLimitError::Bytes { bytes: Some(_), .. }),
+        ));
+    }
+
+    #[test]
+    fn byte_overflow_is_rejected() {
+        let limits = WgpuLimits {
+            max_texture_dimension_2d: u32::MAX,
+            max_texture_array_layers: u32::MAX,
+            max_buffer_size: u64::MAX,
+            ..WgpuLimits::default()
+        };
+
+        assert_eq!(
+            AutomataField::check_limits(size(u32::MAX, u32::MAX), u32::MAX, &limits),
+            Err(FieldLimitError::Bytes { bytes: None, max: u64::MAX }),
+        );
+    }
+}
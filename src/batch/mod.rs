@@ -0,0 +1,355 @@
+use std::{
+    collections::VecDeque,
+    fs::{
+        self,
+        File,
+    },
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        SystemTime,
+        UNIX_EPOCH,
+    },
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        render_resource::Extent3d,
+        renderer::RenderDevice,
+        settings::WgpuLimits,
+    },
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    automata::{
+        AutomataField,
+        AutomataParameters,
+        FieldLimitError,
+        FieldStep,
+        Plasticity,
+    },
+    evolution::{
+        Behaviour,
+        BehaviourRecorder,
+    },
+    neat::NeatField,
+    readback::{
+        FieldSnapshot,
+        Readback,
+        ReadbackPlugin,
+    },
+    uaf::Uaf,
+};
+
+
+// runs queued fields one after another in the same app, add after the `AutomataPlugin` and `NeatPlugin`
+//   usually headless, see `RustyAutomataApp::headless`
+#[derive(Default)]
+pub struct BatchPlugin;
+
+impl Plugin for BatchPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ReadbackPlugin>() {
+            app.add_plugins(ReadbackPlugin);
+        }
+
+        app.add_event::<FieldRunFinished>();
+        app.add_event::<BatchFinished>();
+        app.init_resource::<BatchQueue>();
+
+        app.add_systems(Update, run_batch);
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FieldRun {
+    pub label: String,
+    pub width: u32,
+    pub height: u32,
+    pub parameters: AutomataParameters,
+    pub init_uaf: Uaf,
+    #[serde(default)]
+    pub plasticity: Plasticity,
+    pub steps: u64,
+}
+
+impl Default for FieldRun {
+    fn default() -> Self {
+        Self {
+            label: "run".to_string(),
+            width: 256,
+            height: 256,
+            parameters: AutomataParameters::default(),
+            init_uaf: NeatField::DEFAULT_UAF,
+            plasticity: Plasticity::default(),
+            steps: 1000,
+        }
+    }
+}
+
+impl FieldRun {
+    pub fn field_size(&self) -> Extent3d {
+        Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    pub fn check_limits(&self, limits: &WgpuLimits) -> Result<(), FieldLimitError> {
+        AutomataField::check_limits(self.field_size(), self.parameters.edge_count, limits)
+    }
+}
+
+
+// a finished run with its behaviour and complete final state
+//   activity and spectrum are sampled every `BatchQueue::sample_interval` steps, avalanches every step
+#[derive(Event, Clone, Debug)]
+pub struct FieldRunFinished {
+    pub index: usize,
+    pub run: FieldRun,
+    pub behaviour: Behaviour,
+    pub snapshot: FieldSnapshot,
+}
+
+// sent once the queue has been drained
+#[derive(Event, Clone, Copy, Debug)]
+pub struct BatchFinished {
+    pub runs: usize,
+}
+
+
+#[derive(Resource, Clone, Debug)]
+pub struct BatchQueue {
+    pub runs: VecDeque<FieldRun>,
+    // the nodes are read back every step for the avalanche descriptors, see `BehaviourRecorder`
+    pub sample_interval: u64,
+    current: Option<ActiveRun>,
+    started: usize,
+    finished: bool,
+}
+
+impl Default for BatchQueue {
+    fn default() -> Self {
+        Self {
+            runs: VecDeque::new(),
+            sample_interval: 10,
+            current: None,
+            started: 0,
+            // an idle queue has nothing to report until runs are pushed
+            finished: true,
+        }
+    }
+}
+
+impl BatchQueue {
+    // sends `BatchFinished` once drained, right away when `runs` is empty
+    pub fn new(runs: impl IntoIterator<Item = FieldRun>) -> Self {
+        Self {
+            runs: runs.into_iter().collect(),
+            finished: false,
+            ..default()
+        }
+    }
+
+    pub fn with_sample_interval(mut self, sample_interval: u64) -> Self {
+        self.sample_interval = sample_interval.max(1);
+        self
+    }
+
+    pub fn push(&mut self, run: FieldRun) {
+        self.runs.push_back(run);
+        self.finished = false;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.current.is_some() || !self.runs.is_empty()
+    }
+
+    // runs started so far and the total, including the current one
+    pub fn progress(&self) -> (usize, usize) {
+        (self.started, self.started + self.runs.len())
+    }
+
+    pub fn current(&self) -> Option<&FieldRun> {
+        self.current.as_ref().map(|active| &active.run)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct ActiveRun {
+    index: usize,
+    run: FieldRun,
+    field: Handle<Image>,
+    recorder: BehaviourRecorder,
+    final_requested: bool,
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn run_batch(
+    mut commands: Commands,
+    mut queue: ResMut<BatchQueue>,
+    mut images: ResMut<Assets<Image>>,
+    mut textures: Query<&mut Handle<Image>>,
+    mut readback: ResMut<Readback>,
+    mut snapshots: EventReader<FieldSnapshot>,
+    mut finished_runs: EventWriter<FieldRunFinished>,
+    mut batch_finished: EventWriter<BatchFinished>,
+    automata: Option<Res<AutomataField>>,
+    field_step: Res<FieldStep>,
+    render_device: Option<Res<RenderDevice>>,
+) {
+    let queue = &mut *queue;
+
+    let Some(active) = &mut queue.current else {
+        snapshots.clear();
+
+        let Some(run) = queue.runs.pop_front() else {
+            if !queue.finished {
+                queue.finished = true;
+                batch_finished.send(BatchFinished {
+                    runs: queue.started,
+                });
+            }
+            return;
+        };
+
+        // a run the device cannot hold is dropped instead of panicking in the render world
+        let limits = render_device.map_or_else(WgpuLimits::default, |render_device| render_device.limits());
+        if let Err(err) = run.check_limits(&limits) {
+            error!("skipping run {}: {err}", run.label);
+            return;
+        }
+
+        let automata_field = AutomataField::from_parameters(run.field_size(), run.parameters, &mut images)
+            .with_plasticity(run.plasticity);
+        let neat_field = NeatField::new(run.field_size(), &mut images).with_uaf(run.init_uaf);
+
+        if let Some(automata) = &automata {
+            for mut texture in &mut textures {
+                if *texture == automata.nodes {
+                    *texture = automata_field.nodes.clone();
+                }
+            }
+        }
+
+        readback.interval = Some(1);
+        readback.nodes = true;
+        readback.edges = false;
+        readback.uaf = false;
+
+        info!("run {} ({}): {} steps", queue.started, run.label, run.steps);
+
+        queue.current = Some(ActiveRun {
+            index: queue.started,
+            run,
+            field: automata_field.nodes.clone(),
+            recorder: BehaviourRecorder::default().with_sample_interval(queue.sample_interval),
+            final_requested: false,
+        });
+        queue.started += 1;
+
+        commands.insert_resource(automata_field);
+        commands.insert_resource(neat_field);
+        return;
+    };
+
+    // the new field is live once its steps are reported
+    let live = automata.as_ref().is_some_and(|automata| automata.nodes == active.field);
+    if !live {
+        snapshots.clear();
+        return;
+    }
+
+    let mut finished = None;
+    for snapshot in snapshots.iter().filter(|snapshot| snapshot.field == active.field) {
+        if snapshot.step <= active.run.steps && !snapshot.nodes.is_empty() {
+            active.recorder.observe(snapshot.step, snapshot.width, snapshot.height, &snapshot.values());
+        }
+
+        if active.final_requested && snapshot.is_complete() && snapshot.step >= active.run.steps {
+            finished = Some(snapshot.clone());
+        }
+    }
+
+    if !active.final_requested && field_step.0.is_some_and(|step| step >= active.run.steps) {
+        active.final_requested = true;
+        readback.request();
+    }
+
+    if let Some(snapshot) = finished {
+        let active = queue.current.take().unwrap();
+
+        finished_runs.send(FieldRunFinished {
+            index: active.index,
+            behaviour: active.recorder.behaviour(),
+            run: active.run,
+            snapshot,
+        });
+    }
+}
+
+
+// written next to the results of a cli command, so runs can be traced back to their settings
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub command: String,
+    pub version: String,
+    pub arguments: Vec<String>,
+    pub config: serde_json::Value,
+    // unix seconds
+    pub started: u64,
+    pub finished: Option<u64>,
+    // relative to the manifest
+    pub outputs: Vec<PathBuf>,
+}
+
+impl Manifest {
+    pub fn new(command: impl Into<String>, config: &impl Serialize) -> Self {
+        Self {
+            command: command.into(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            arguments: std::env::args().collect(),
+            config: serde_json::to_value(config).unwrap_or_default(),
+            started: unix_seconds(),
+            ..default()
+        }
+    }
+
+    pub fn add_output(&mut self, path: impl Into<PathBuf>) {
+        self.outputs.push(path.into());
+    }
+
+    pub fn finish(&mut self) {
+        self.finished = Some(unix_seconds());
+    }
+
+    // `manifest.json` in the output directory
+    pub fn save(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        fs::create_dir_all(&dir)?;
+
+        let mut writer = BufWriter::new(File::create(dir.as_ref().join("manifest.json"))?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()
+    }
+}
+
+fn unix_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
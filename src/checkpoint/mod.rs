@@ -0,0 +1,496 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{
+        self,
+        BufReader,
+        BufWriter,
+        Read,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use bevy::{
+    prelude::*,
+    render::render_resource::Extent3d,
+};
+use bincode::Options;
+use flate2::{
+    read::ZlibDecoder,
+    write::ZlibEncoder,
+    Compression,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    automata::{
+        AutomataField,
+        AutomataParameters,
+        EdgeInit,
+        Plasticity,
+    },
+    neat::NeatField,
+    readback::{
+        FieldSnapshot,
+        Readback,
+        SnapshotRequests,
+    },
+    uaf::Uaf,
+};
+
+
+// file layout: magic, little endian u32 version, zlib compressed bincode `Checkpoint`
+pub const CHECKPOINT_MAGIC: [u8; 4] = *b"RACK";
+pub const CHECKPOINT_VERSION: u32 = 3;
+// upper bound on the decompressed bincode, a corrupt length fails to read instead of allocating without bound
+pub const MAX_CHECKPOINT_BYTES: u64 = 4 << 30;
+
+
+#[derive(Default)]
+pub struct CheckpointPlugin;
+
+impl Plugin for CheckpointPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FieldSnapshot>();
+        app.add_event::<SaveCheckpoint>();
+        app.add_event::<LoadCheckpoint>();
+
+        app.init_resource::<CheckpointSettings>();
+        app.init_resource::<PendingSaves>();
+
+        app.add_systems(
+            Update,
+            (
+                request_checkpoints,
+                save_checkpoints,
+                load_checkpoints,
+            ),
+        );
+    }
+}
+
+
+#[derive(Resource, Clone, Debug)]
+pub struct CheckpointSettings {
+    pub path: String,
+}
+
+impl Default for CheckpointSettings {
+    fn default() -> Self {
+        Self {
+            path: "field.rack".to_string(),
+        }
+    }
+}
+
+
+// saved once the next complete snapshot has been read back
+#[derive(Event, Clone, Debug)]
+pub struct SaveCheckpoint(pub PathBuf);
+
+#[derive(Event, Clone, Debug)]
+pub struct LoadCheckpoint(pub PathBuf);
+
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    Io(io::Error),
+    Encoding(bincode::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    Incomplete,
+    TooLarge,
+    SizeMismatch,
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::Io(err) => write!(f, "checkpoint io error: {err}"),
+            CheckpointError::Encoding(err) => write!(f, "checkpoint encoding error: {err}"),
+            CheckpointError::InvalidMagic => write!(f, "not a checkpoint file"),
+            CheckpointError::UnsupportedVersion(version) => write!(f, "unsupported checkpoint version {version}, expected {CHECKPOINT_VERSION}"),
+            CheckpointError::Incomplete => write!(f, "snapshot is missing nodes, edges or uaf activations"),
+            CheckpointError::TooLarge => write!(f, "checkpoint exceeds {MAX_CHECKPOINT_BYTES} bytes"),
+            CheckpointError::SizeMismatch => write!(f, "checkpoint texel counts do not match its field size"),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+impl From<io::Error> for CheckpointError {
+    fn from(err: io::Error) -> Self {
+        CheckpointError::Io(err)
+    }
+}
+
+impl From<bincode::Error> for CheckpointError {
+    fn from(err: bincode::Error) -> Self {
+        CheckpointError::Encoding(err)
+    }
+}
+
+
+// rgba32float texels in the same layout as the field textures
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub parameters: AutomataParameters,
+    pub width: u32,
+    pub height: u32,
+    pub step: u64,
+    pub nodes: Vec<[f32; 4]>,
+    pub edges: Vec<[f32; 4]>,
+    pub uaf_activations: Vec<[f32; 4]>,
+    pub init_uaf: Uaf,
+    pub edge_init: EdgeInit,
+    pub plasticity: Plasticity,
+}
+
+impl Checkpoint {
+    pub fn from_snapshot(
+        snapshot: &FieldSnapshot,
+        parameters: AutomataParameters,
+        init_uaf: Uaf,
+        edge_init: EdgeInit,
+        plasticity: Plasticity,
+    ) -> Result<Self, CheckpointError> {
+        if !snapshot.is_complete() || snapshot.edge_count != parameters.edge_count {
+            return Err(CheckpointError::Incomplete);
+        }
+
+        Ok(Self {
+            parameters,
+            width: snapshot.width,
+            height: snapshot.height,
+            step: snapshot.step,
+            nodes: snapshot.nodes.iter().map(|&node| node.into()).collect(),
+            edges: snapshot.edges.iter().map(|&edge| edge.into()).collect(),
+            uaf_activations: snapshot.uaf.iter().map(|uaf| uaf.to_array()).collect(),
+            init_uaf,
+            edge_init,
+            plasticity,
+        })
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> Result<(), CheckpointError> {
+        writer.write_all(&CHECKPOINT_MAGIC)?;
+        writer.write_all(&CHECKPOINT_VERSION.to_le_bytes())?;
+
+        let mut encoder = ZlibEncoder::new(writer, Compression::default());
+        bincode::serialize_into(&mut encoder, self)?;
+        encoder.finish()?.flush()?;
+
+        Ok(())
+    }
+
+    pub fn read(reader: impl Read) -> Result<Self, CheckpointError> {
+        Self::read_with_limit(reader, MAX_CHECKPOINT_BYTES)
+    }
+
+    fn read_with_limit(mut reader: impl Read, limit: u64) -> Result<Self, CheckpointError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != CHECKPOINT_MAGIC {
+            return Err(CheckpointError::InvalidMagic);
+        }
+
+        let mut version = [0; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != CHECKPOINT_VERSION {
+            return Err(CheckpointError::UnsupportedVersion(version));
+        }
+
+        // same fixint encoding as `bincode::serialize_into`
+        let checkpoint: Self = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(limit)
+            .deserialize_from(ZlibDecoder::new(reader))
+            .map_err(|err| match *err {
+                bincode::ErrorKind::SizeLimit => CheckpointError::TooLarge,
+                _ => CheckpointError::Encoding(err),
+            })?;
+
+        let texels = checkpoint.width
+            .checked_mul(checkpoint.height)
+            .map(|texels| texels as usize);
+        let edges = texels.and_then(|texels| texels.checked_mul(checkpoint.parameters.edge_count as usize));
+
+        let (Some(texels), Some(edges)) = (texels, edges) else {
+            return Err(CheckpointError::SizeMismatch);
+        };
+        if checkpoint.nodes.len() != texels
+            || checkpoint.edges.len() != edges
+            || checkpoint.uaf_activations.len() != texels
+        {
+            return Err(CheckpointError::SizeMismatch);
+        }
+
+        Ok(checkpoint)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheckpointError> {
+        self.write(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, CheckpointError> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    pub fn field_size(&self) -> Extent3d {
+        Extent3d {
+            width: self.width,
+            height: self.height,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    // the saved state as if it had just been read back, e.g. for exporting it
+    pub fn snapshot(&self) -> FieldSnapshot {
+        FieldSnapshot {
+            step: self.step,
+            width: self.width,
+            height: self.height,
+            edge_count: self.parameters.edge_count,
+            nodes: self.nodes.iter().map(|&node| node.into()).collect(),
+            edges: self.edges.iter().map(|&edge| edge.into()).collect(),
+            uaf: self.uaf_activations.iter().map(|&uaf| Vec4::from_array(uaf)).collect(),
+            ..default()
+        }
+    }
+
+    // the restored field resumes at `step` without running the init pass
+    pub fn restore(
+        &self,
+        images: &mut ResMut<Assets<Image>>,
+    ) -> (AutomataField, NeatField) {
+        let automata_field = AutomataField::restore(
+            self.field_size(),
+            self.parameters,
+            texel_bytes(&self.nodes),
+            texel_bytes(&self.edges),
+            self.step,
+            images,
+        )
+        .with_edge_init(self.edge_init)
+        .with_plasticity(self.plasticity);
+        let neat_field = NeatField::restore(
+            self.field_size(),
+            texel_bytes(&self.uaf_activations),
+            images,
+        ).with_uaf(self.init_uaf);
+
+        (automata_field, neat_field)
+    }
+}
+
+fn texel_bytes(texels: &[[f32; 4]]) -> Vec<u8> {
+    texels
+        .iter()
+        .flatten()
+        .flat_map(|channel| channel.to_ne_bytes())
+        .collect()
+}
+
+
+#[derive(Resource)]
+struct PendingSaves(SnapshotRequests<PathBuf>);
+
+impl Default for PendingSaves {
+    fn default() -> Self {
+        Self(SnapshotRequests::complete())
+    }
+}
+
+fn request_checkpoints(
+    mut requests: EventReader<SaveCheckpoint>,
+    mut pending: ResMut<PendingSaves>,
+    mut readback: Option<ResMut<Readback>>,
+    automata: Option<Res<AutomataField>>,
+) {
+    pending.0.update(
+        requests.iter().map(|request| (request.0.clone(), 1)),
+        automata.as_deref(),
+        readback.as_deref_mut(),
+        "saving checkpoints",
+    );
+}
+
+fn save_checkpoints(
+    mut pending: ResMut<PendingSaves>,
+    mut snapshots: EventReader<FieldSnapshot>,
+    automata: Option<Res<AutomataField>>,
+    neat: Option<Res<NeatField>>,
+) {
+    let (Some(automata), false) = (automata, pending.0.is_empty()) else {
+        snapshots.clear();
+        return;
+    };
+    let init_uaf = neat.map_or(NeatField::DEFAULT_UAF, |neat| neat.init_uaf);
+
+    for (path, snapshots) in pending.0.receive(snapshots.iter()) {
+        let result = Checkpoint::from_snapshot(
+            &snapshots[0],
+            automata.parameters(),
+            init_uaf,
+            automata.edge_init(),
+            automata.plasticity(),
+        )
+            .and_then(|checkpoint| checkpoint.save(&path));
+
+        match result {
+            Ok(()) => info!("saved checkpoint at step {} to {}", snapshots[0].step, path.display()),
+            Err(err) => error!("failed to save checkpoint to {}: {err}", path.display()),
+        }
+    }
+}
+
+fn load_checkpoints(
+    mut commands: Commands,
+    mut requests: EventReader<LoadCheckpoint>,
+    mut images: ResMut<Assets<Image>>,
+    mut textures: Query<&mut Handle<Image>>,
+    automata: Option<Res<AutomataField>>,
+) {
+    let Some(request) = requests.iter().last() else {
+        return;
+    };
+
+    let checkpoint = match Checkpoint::load(&request.0) {
+        Ok(checkpoint) => checkpoint,
+        Err(err) => {
+            error!("failed to load checkpoint from {}: {err}", request.0.display());
+            return;
+        }
+    };
+
+    let (automata_field, neat_field) = checkpoint.restore(&mut images);
+
+    // keep sprites that displayed the previous field pointed at the restored one
+    if let Some(automata) = automata {
+        for mut texture in &mut textures {
+            if *texture == automata.nodes {
+                *texture = automata_field.nodes.clone();
+            }
+        }
+    }
+
+    info!("loaded checkpoint at step {} from {}", checkpoint.step, request.0.display());
+
+    commands.insert_resource(automata_field);
+    commands.insert_resource(neat_field);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn checkpoint(width: u32, height: u32, edge_count: u32) -> Checkpoint {
+        let texels = (width * height) as usize;
+
+        Checkpoint {
+            parameters: AutomataParameters {
+                edge_count,
+                ..default()
+            },
+            width,
+            height,
+            step: 7,
+            nodes: vec![[0.5, 0.0, 0.0, 1.0]; texels],
+            edges: vec![[1.0, -1.0, 0.25, 0.0]; texels * edge_count as usize],
+            uaf_activations: vec![[0.0; 4]; texels],
+            init_uaf: NeatField::DEFAULT_UAF,
+            edge_init: EdgeInit::Keep,
+            plasticity: Plasticity {
+                rate: 0.01,
+                decay: 0.001,
+            },
+        }
+    }
+
+    fn encode(checkpoint: &Checkpoint) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        checkpoint.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    // magic and version followed by an arbitrary compressed payload
+    fn with_payload(payload: &[u8]) -> Vec<u8> {
+        let mut bytes = CHECKPOINT_MAGIC.to_vec();
+        bytes.extend(CHECKPOINT_VERSION.to_le_bytes());
+
+        let mut encoder = ZlibEncoder::new(&mut bytes, Compression::default());
+        encoder.write_all(payload).unwrap();
+        encoder.finish().unwrap();
+
+        bytes
+    }
+
+    #[test]
+    fn round_trip() {
+        let read = Checkpoint::read(encode(&checkpoint(3, 2, 4)).as_slice()).unwrap();
+
+        assert_eq!((read.width, read.height, read.step), (3, 2, 7));
+        assert_eq!(read.edges.len(), 24);
+        assert_eq!(read.edges[5], [1.0, -1.0, 0.25, 0.0]);
+        assert_eq!(read.edge_init, EdgeInit::Keep);
+        assert_eq!(read.plasticity, Plasticity { rate: 0.01, decay: 0.001 });
+    }
+
+    #[test]
+    fn rejects_wrong_magic_and_version() {
+        let mut bytes = encode(&checkpoint(1, 1, 1));
+        bytes[0] = b'X';
+        assert!(matches!(Checkpoint::read(bytes.as_slice()), Err(CheckpointError::InvalidMagic)));
+
+        let mut bytes = encode(&checkpoint(1, 1, 1));
+        bytes[4..8].copy_from_slice(&(CHECKPOINT_VERSION + 1).to_le_bytes());
+        assert!(matches!(Checkpoint::read(bytes.as_slice()), Err(CheckpointError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn huge_length_prefix_fails_without_allocating() {
+        // parameters, width, height and step, then a node count far above the limit
+        let mut payload = bincode::serialize(&AutomataParameters::default()).unwrap();
+        payload.extend(1u32.to_le_bytes());
+        payload.extend(1u32.to_le_bytes());
+        payload.extend(0u64.to_le_bytes());
+        payload.extend(u64::MAX.to_le_bytes());
+
+        assert!(Checkpoint::read(with_payload(&payload).as_slice()).is_err());
+    }
+
+    #[test]
+    fn exceeding_the_limit_is_too_large() {
+        let bytes = encode(&checkpoint(4, 4, 2));
+
+        assert!(matches!(Checkpoint::read_with_limit(bytes.as_slice(), 256), Err(CheckpointError::TooLarge)));
+    }
+
+    #[test]
+    fn overflowing_size_is_a_mismatch() {
+        let mut oversized = checkpoint(1, 1, 1);
+        oversized.width = u32::MAX;
+        oversized.height = 2;
+
+        assert!(matches!(Checkpoint::read(encode(&oversized).as_slice()), Err(CheckpointError::SizeMismatch)));
+    }
+
+    #[test]
+    fn wrong_texel_count_is_a_mismatch() {
+        let mut truncated = checkpoint(2, 2, 2);
+        truncated.edges.pop();
+
+        assert!(matches!(Checkpoint::read(encode(&truncated).as_slice()), Err(CheckpointError::SizeMismatch)));
+    }
+}
@@ -0,0 +1,793 @@
+use std::{
+    collections::{
+        HashMap,
+        HashSet,
+    },
+    fmt,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use bevy::{
+    prelude::*,
+    render::render_resource::Extent3d,
+};
+use rand::{
+    rngs::StdRng,
+    Rng,
+    SeedableRng,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    automata::{
+        AutomataField,
+        AutomataParameters,
+    },
+    readback::EdgeState,
+};
+
+
+#[derive(Event, Clone, Debug)]
+pub struct ImportConnectome {
+    pub path: PathBuf,
+    pub mapping: NodeMapping,
+}
+
+
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    Xml(roxmltree::Error),
+    Json(serde_json::Error),
+    UnsupportedFormat(PathBuf),
+    Parse(String),
+    UnknownNode(String),
+    MissingPosition(String),
+    TooManyNodes {
+        nodes: usize,
+        capacity: usize,
+    },
+    // (node id, incoming edge count) of every node with more than `edge_count` incoming edges
+    TooManyEdges {
+        edge_count: u32,
+        nodes: Vec<(String, usize)>,
+    },
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(err) => write!(f, "graph io error: {err}"),
+            ImportError::Xml(err) => write!(f, "graphml error: {err}"),
+            ImportError::Json(err) => write!(f, "json graph error: {err}"),
+            ImportError::UnsupportedFormat(path) => write!(f, "unsupported graph format: {}", path.display()),
+            ImportError::Parse(message) => write!(f, "graph parse error: {message}"),
+            ImportError::UnknownNode(id) => write!(f, "edge references unknown node {id}"),
+            ImportError::MissingPosition(id) => write!(f, "node {id} has no position attribute"),
+            ImportError::TooManyNodes { nodes, capacity } => write!(f, "{nodes} nodes do not fit in a field of {capacity}"),
+            ImportError::TooManyEdges { edge_count, nodes } => write!(
+                f,
+                "{} nodes exceed {edge_count} incoming edges: {}",
+                nodes.len(),
+                nodes
+                    .iter()
+                    .map(|(id, count)| format!("{id} ({count})"))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<io::Error> for ImportError {
+    fn from(err: io::Error) -> Self {
+        ImportError::Io(err)
+    }
+}
+
+impl From<roxmltree::Error> for ImportError {
+    fn from(err: roxmltree::Error) -> Self {
+        ImportError::Xml(err)
+    }
+}
+
+impl From<serde_json::Error> for ImportError {
+    fn from(err: serde_json::Error) -> Self {
+        ImportError::Json(err)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedNode {
+    pub id: String,
+    pub attributes: HashMap<String, String>,
+}
+
+// `source` feeds into `target`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportedEdge {
+    pub source: String,
+    pub target: String,
+    pub weight: f32,
+}
+
+// undirected graphs are imported with an edge in each direction
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportedGraph {
+    pub nodes: Vec<ImportedNode>,
+    pub edges: Vec<ImportedEdge>,
+}
+
+
+// how arbitrary node ids are placed onto field locations
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum NodeMapping {
+    // in order of appearance
+    #[default]
+    RowMajor,
+    // Fruchterman-Reingold layout scaled onto the field, O(n^2) per iteration
+    Spring {
+        iterations: u32,
+        seed: u64,
+    },
+    // integer positions inside the field are used as is, anything else is scaled to fit
+    Attribute {
+        x: String,
+        y: String,
+    },
+}
+
+#[derive(Deserialize)]
+struct JsonGraph {
+    // written by `networkx.node_link_data`, graphs without it are directed
+    #[serde(default = "default_directed")]
+    directed: bool,
+    nodes: Vec<JsonNode>,
+    // `links` as written by `networkx.node_link_data`
+    #[serde(alias = "links")]
+    edges: Vec<JsonEdge>,
+}
+
+#[derive(Deserialize)]
+struct JsonNode {
+    id: serde_json::Value,
+    #[serde(flatten)]
+    attributes: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct JsonEdge {
+    source: serde_json::Value,
+    target: serde_json::Value,
+    #[serde(default = "default_weight")]
+    weight: f32,
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+fn default_directed() -> bool {
+    true
+}
+
+fn json_string(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
+
+
+impl ImportedGraph {
+    // format is picked from the extension: .graphml, .csv or .json
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ImportError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_lowercase);
+
+        match extension.as_deref() {
+            Some("graphml") => Self::from_graphml(&text),
+            Some("csv") => Self::from_edge_list(&text),
+            Some("json") => Self::from_json(&text),
+            _ => Err(ImportError::UnsupportedFormat(path.to_path_buf())),
+        }
+    }
+
+    pub fn from_graphml(text: &str) -> Result<Self, ImportError> {
+        let document = roxmltree::Document::parse(text)?;
+
+        // data keys refer to `<key>` ids, store attributes by their `attr.name`
+        let key_names: HashMap<&str, &str> = document
+            .descendants()
+            .filter(|node| node.has_tag_name("key"))
+            .filter_map(|key| Some((key.attribute("id")?, key.attribute("attr.name").or(key.attribute("id"))?)))
+            .collect();
+
+        let data = |element: roxmltree::Node| -> HashMap<String, String> {
+            element
+                .children()
+                .filter(|child| child.has_tag_name("data"))
+                .filter_map(|child| {
+                    let key = child.attribute("key")?;
+                    let name = key_names.get(key).copied().unwrap_or(key);
+                    Some((name.to_string(), child.text().unwrap_or_default().trim().to_string()))
+                })
+                .collect()
+        };
+
+        let nodes = document
+            .descendants()
+            .filter(|element| element.has_tag_name("node"))
+            .map(|element| {
+                let id = element
+                    .attribute("id")
+                    .ok_or_else(|| ImportError::Parse("node without id".to_string()))?;
+
+                Ok(ImportedNode {
+                    id: id.to_string(),
+                    attributes: data(element),
+                })
+            })
+            .collect::<Result<Vec<_>, ImportError>>()?;
+
+        let mut edges = Vec::new();
+        for element in document.descendants().filter(|element| element.has_tag_name("edge")) {
+            let (Some(source), Some(target)) = (element.attribute("source"), element.attribute("target")) else {
+                return Err(ImportError::Parse("edge without source or target".to_string()));
+            };
+
+            let weight = match data(element).get("weight") {
+                Some(weight) => parse_float(weight)?,
+                None => 1.0,
+            };
+
+            // an edge's own `directed` overrides the `edgedefault` of its graph, which is directed when missing
+            let directed = match element.attribute("directed") {
+                Some(directed) => directed == "true",
+                None => element
+                    .ancestors()
+                    .find(|ancestor| ancestor.has_tag_name("graph"))
+                    .and_then(|graph| graph.attribute("edgedefault"))
+                    != Some("undirected"),
+            };
+
+            push_edge(&mut edges, source.to_string(), target.to_string(), weight, directed);
+        }
+
+        Ok(Self {
+            nodes,
+            edges,
+        })
+    }
+
+    // directed `source,target[,weight]` rows, a header naming those columns is optional
+    //   fields may be double quoted, with `""` for a quote inside them
+    pub fn from_edge_list(text: &str) -> Result<Self, ImportError> {
+        let mut lines = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .peekable();
+
+        let mut columns = (0, 1, Some(2));
+        if let Some(header) = lines.peek() {
+            let names = split_csv_line(header);
+            let position = |name: &str| names.iter().position(|column| column.eq_ignore_ascii_case(name));
+
+            if let (Some(source), Some(target)) = (position("source"), position("target")) {
+                columns = (source, target, position("weight"));
+                lines.next();
+            }
+        }
+
+        let mut graph = Self::default();
+        let mut known = HashSet::new();
+
+        for line in lines {
+            let fields = split_csv_line(line);
+            let field = |index: usize| {
+                fields
+                    .get(index)
+                    .cloned()
+                    .ok_or_else(|| ImportError::Parse(format!("missing column {index} in `{line}`")))
+            };
+
+            let source = field(columns.0)?;
+            let target = field(columns.1)?;
+            let weight = match columns.2.and_then(|column| fields.get(column)) {
+                Some(weight) => parse_float(weight)?,
+                None => 1.0,
+            };
+
+            // nodes only exist through their edges, keep them in order of appearance
+            for id in [&source, &target] {
+                if known.insert(id.clone()) {
+                    graph.nodes.push(ImportedNode {
+                        id: id.clone(),
+                        attributes: HashMap::new(),
+                    });
+                }
+            }
+
+            graph.edges.push(ImportedEdge {
+                source,
+                target,
+                weight,
+            });
+        }
+
+        Ok(graph)
+    }
+
+    // `{"directed": .., "nodes": [{"id": .., ...attributes}], "edges" | "links": [{"source": .., "target": .., "weight": ..}]}`
+    pub fn from_json(text: &str) -> Result<Self, ImportError> {
+        let graph: JsonGraph = serde_json::from_str(text)?;
+
+        let mut edges = Vec::new();
+        for edge in &graph.edges {
+            push_edge(&mut edges, json_string(&edge.source), json_string(&edge.target), edge.weight, graph.directed);
+        }
+
+        Ok(Self {
+            nodes: graph.nodes
+                .iter()
+                .map(|node| ImportedNode {
+                    id: json_string(&node.id),
+                    attributes: node.attributes
+                        .iter()
+                        .map(|(key, value)| (key.clone(), json_string(value)))
+                        .collect(),
+                })
+                .collect(),
+            edges,
+        })
+    }
+
+    pub fn layout(
+        &self,
+        field_size: Extent3d,
+        edge_count: u32,
+        mapping: &NodeMapping,
+    ) -> Result<GraphLayout, ImportError> {
+        let capacity = (field_size.width * field_size.height) as usize;
+        if self.nodes.len() > capacity {
+            return Err(ImportError::TooManyNodes {
+                nodes: self.nodes.len(),
+                capacity,
+            });
+        }
+
+        let index: HashMap<&str, usize> = self.nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| (node.id.as_str(), i))
+            .collect();
+
+        let edges = self.edges
+            .iter()
+            .map(|edge| {
+                let node = |id: &str| index.get(id).copied().ok_or_else(|| ImportError::UnknownNode(id.to_string()));
+                Ok((node(&edge.source)?, node(&edge.target)?, edge.weight))
+            })
+            .collect::<Result<Vec<_>, ImportError>>()?;
+
+        let mut incoming = vec![0; self.nodes.len()];
+        for &(_, target, _) in &edges {
+            incoming[target] += 1;
+        }
+
+        let overflowing: Vec<(String, usize)> = incoming
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > edge_count as usize)
+            .map(|(i, &count)| (self.nodes[i].id.clone(), count))
+            .collect();
+        if !overflowing.is_empty() {
+            return Err(ImportError::TooManyEdges {
+                edge_count,
+                nodes: overflowing,
+            });
+        }
+
+        let positions = match mapping {
+            NodeMapping::RowMajor => (0..self.nodes.len())
+                .map(|i| Vec2::new((i as u32 % field_size.width) as f32, (i as u32 / field_size.width) as f32))
+                .collect(),
+            NodeMapping::Spring { iterations, seed } => spring_layout(self.nodes.len(), &edges, *iterations, *seed),
+            NodeMapping::Attribute { x, y } => self.nodes
+                .iter()
+                .map(|node| {
+                    let coordinate = |key: &String| {
+                        let value = node.attributes
+                            .get(key)
+                            .ok_or_else(|| ImportError::MissingPosition(node.id.clone()))?;
+                        parse_float(value)
+                    };
+
+                    Ok(Vec2::new(coordinate(x)?, coordinate(y)?))
+                })
+                .collect::<Result<Vec<_>, ImportError>>()?,
+        };
+
+        let locations = place_on_grid(&positions, field_size);
+
+        Ok(GraphLayout {
+            field_size,
+            edge_count,
+            locations: self.nodes
+                .iter()
+                .zip(&locations)
+                .map(|(node, &location)| (node.id.clone(), location))
+                .collect(),
+            edges: edges
+                .iter()
+                .map(|&(source, target, weight)| (locations[source], locations[target], weight))
+                .collect(),
+        })
+    }
+}
+
+// undirected edges feed both nodes, so each direction takes an incoming edge slot
+fn push_edge(
+    edges: &mut Vec<ImportedEdge>,
+    source: String,
+    target: String,
+    weight: f32,
+    directed: bool,
+) {
+    let reverse = (!directed && source != target).then(|| ImportedEdge {
+        source: target.clone(),
+        target: source.clone(),
+        weight,
+    });
+
+    edges.push(ImportedEdge {
+        source,
+        target,
+        weight,
+    });
+    edges.extend(reverse);
+}
+
+// comma separated, trimmed fields
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+
+    fields
+}
+
+fn parse_float(value: &str) -> Result<f32, ImportError> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| ImportError::Parse(format!("`{value}` is not a number")))
+}
+
+
+// node placements and (source, target, weight) edges on the field grid
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphLayout {
+    pub field_size: Extent3d,
+    pub edge_count: u32,
+    pub locations: Vec<(String, UVec2)>,
+    pub edges: Vec<(UVec2, UVec2, f32)>,
+}
+
+impl GraphLayout {
+    // unused edge slots are `EdgeState::EMPTY`
+    pub fn edge_texels(&self) -> Vec<[f32; 4]> {
+        let (width, height) = (self.field_size.width, self.field_size.height);
+
+        let mut texels: Vec<[f32; 4]> = vec![EdgeState::EMPTY.into(); (width * height * self.edge_count) as usize];
+
+        let mut slots = vec![0; (width * height) as usize];
+        for &(source, target, weight) in &self.edges {
+            let index = (target.y * width + target.x) as usize;
+            let layer = slots[index] * (width * height) as usize;
+            slots[index] += 1;
+
+            texels[layer + index] = [source.x as f32, source.y as f32, weight, 0.0];
+        }
+
+        texels
+    }
+
+    pub fn into_field(
+        &self,
+        parameters: AutomataParameters,
+        images: &mut ResMut<Assets<Image>>,
+    ) -> AutomataField {
+        let edges = self.edge_texels()
+            .iter()
+            .flatten()
+            .flat_map(|channel| channel.to_ne_bytes())
+            .collect();
+
+        AutomataField::from_graph(
+            self.field_size,
+            AutomataParameters {
+                edge_count: self.edge_count,
+                ..parameters
+            },
+            edges,
+            images,
+        )
+    }
+}
+
+
+fn spring_layout(
+    node_count: usize,
+    edges: &[(usize, usize, f32)],
+    iterations: u32,
+    seed: u64,
+) -> Vec<Vec2> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut positions: Vec<Vec2> = (0..node_count)
+        .map(|_| Vec2::new(rng.gen(), rng.gen()))
+        .collect();
+
+    let k = (1.0 / node_count.max(1) as f32).sqrt();
+    let mut temperature = 0.1;
+    let cooling = temperature / (iterations.max(1) as f32 + 1.0);
+
+    for _ in 0..iterations {
+        let mut displacement = vec![Vec2::ZERO; node_count];
+
+        for i in 0..node_count {
+            for j in (i + 1)..node_count {
+                let delta = positions[i] - positions[j];
+                let distance = delta.length().max(0.01);
+                let force = delta / distance * (k * k / distance);
+
+                displacement[i] += force;
+                displacement[j] -= force;
+            }
+        }
+
+        for &(source, target, _) in edges {
+            let delta = positions[source] - positions[target];
+            let distance = delta.length().max(0.01);
+            let force = delta / distance * (distance * distance / k);
+
+            displacement[source] -= force;
+            displacement[target] += force;
+        }
+
+        for (position, displacement) in positions.iter_mut().zip(displacement) {
+            let length = displacement.length().max(0.01);
+            *position += displacement / length * length.min(temperature);
+        }
+
+        temperature -= cooling;
+    }
+
+    positions
+}
+
+// rounds positions onto free cells, moving collisions to the nearest free cell
+fn place_on_grid(
+    positions: &[Vec2],
+    field_size: Extent3d,
+) -> Vec<UVec2> {
+    let size = Vec2::new(field_size.width as f32, field_size.height as f32);
+
+    let on_grid = positions.iter().all(|position| {
+        position.fract() == Vec2::ZERO && position.cmpge(Vec2::ZERO).all() && position.cmplt(size).all()
+    });
+
+    let scaled: Vec<Vec2> = if on_grid {
+        positions.to_vec()
+    } else {
+        let min = positions.iter().fold(Vec2::splat(f32::MAX), |min, &position| min.min(position));
+        let max = positions.iter().fold(Vec2::splat(f32::MIN), |max, &position| max.max(position));
+        let extent = (max - min).max(Vec2::splat(f32::EPSILON));
+
+        positions
+            .iter()
+            .map(|&position| (position - min) / extent * (size - 1.0))
+            .collect()
+    };
+
+    let width = field_size.width as i32;
+    let height = field_size.height as i32;
+    let mut occupied = vec![false; (width * height) as usize];
+
+    scaled
+        .iter()
+        .map(|position| {
+            let cell = position.round().as_ivec2().clamp(IVec2::ZERO, IVec2::new(width - 1, height - 1));
+
+            // square rings of increasing radius until a free cell turns up
+            let free = (0..width.max(height))
+                .flat_map(|radius| {
+                    (-radius..=radius).flat_map(move |dy| {
+                        (-radius..=radius)
+                            .filter(move |dx| dx.abs() == radius || dy.abs() == radius)
+                            .map(move |dx| cell + IVec2::new(dx, dy))
+                    })
+                })
+                .find(|candidate| {
+                    candidate.cmpge(IVec2::ZERO).all()
+                        && candidate.x < width
+                        && candidate.y < height
+                        && !occupied[(candidate.y * width + candidate.x) as usize]
+                })
+                .expect("layout checks that every node fits in the field");
+
+            occupied[(free.y * width + free.x) as usize] = true;
+            free.as_uvec2()
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(graph: &ImportedGraph) -> Vec<(&str, &str, f32)> {
+        graph.edges
+            .iter()
+            .map(|edge| (edge.source.as_str(), edge.target.as_str(), edge.weight))
+            .collect()
+    }
+
+    fn field_size(width: u32, height: u32) -> Extent3d {
+        Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        }
+    }
+
+    #[test]
+    fn graphml_reads_attributes_and_weights() {
+        let graph = ImportedGraph::from_graphml(r#"
+            <graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+              <key id="d0" for="node" attr.name="x" attr.type="double"/>
+              <key id="d1" for="edge" attr.name="weight" attr.type="double"/>
+              <graph edgedefault="directed">
+                <node id="a"><data key="d0">2.0</data></node>
+                <node id="b"/>
+                <edge source="a" target="b"><data key="d1">-0.5</data></edge>
+                <edge source="b" target="a"/>
+              </graph>
+            </graphml>
+        "#).unwrap();
+
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(graph.nodes[0].attributes["x"], "2.0");
+        assert_eq!(pairs(&graph), vec![("a", "b", -0.5), ("b", "a", 1.0)]);
+    }
+
+    #[test]
+    fn graphml_undirected_edges_go_both_ways() {
+        let graph = ImportedGraph::from_graphml(r#"
+            <graphml>
+              <graph edgedefault="undirected">
+                <node id="a"/><node id="b"/><node id="c"/>
+                <edge source="a" target="b"/>
+                <edge source="b" target="c" directed="true"/>
+                <edge source="c" target="c"/>
+              </graph>
+            </graphml>
+        "#).unwrap();
+
+        assert_eq!(pairs(&graph), vec![("a", "b", 1.0), ("b", "a", 1.0), ("b", "c", 1.0), ("c", "c", 1.0)]);
+    }
+
+    #[test]
+    fn edge_list_reorders_columns_from_the_header() {
+        let graph = ImportedGraph::from_edge_list("# exported by hand\nweight,target,source\n0.25, b, a\n\n-1,c,b\n").unwrap();
+
+        let ids: Vec<&str> = graph.nodes.iter().map(|node| node.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "b", "c"]);
+        assert_eq!(pairs(&graph), vec![("a", "b", 0.25), ("b", "c", -1.0)]);
+    }
+
+    #[test]
+    fn edge_list_quoted_fields_keep_commas_and_quotes() {
+        let graph = ImportedGraph::from_edge_list("\"a, b\",\"say \"\"hi\"\"\",2\n").unwrap();
+
+        assert_eq!(pairs(&graph), vec![("a, b", "say \"hi\"", 2.0)]);
+        assert!(matches!(ImportedGraph::from_edge_list("a,b,heavy"), Err(ImportError::Parse(_))));
+    }
+
+    #[test]
+    fn json_node_link_data() {
+        let directed = ImportedGraph::from_json(r#"{
+            "directed": true,
+            "nodes": [{"id": 0, "x": 1.5}, {"id": "one"}],
+            "links": [{"source": 0, "target": "one", "weight": 3}]
+        }"#).unwrap();
+
+        assert_eq!(directed.nodes[0].id, "0");
+        assert_eq!(directed.nodes[0].attributes["x"], "1.5");
+        assert_eq!(pairs(&directed), vec![("0", "one", 3.0)]);
+
+        let undirected = ImportedGraph::from_json(r#"{
+            "directed": false,
+            "nodes": [{"id": "a"}, {"id": "b"}],
+            "edges": [{"source": "a", "target": "b"}]
+        }"#).unwrap();
+
+        assert_eq!(pairs(&undirected), vec![("a", "b", 1.0), ("b", "a", 1.0)]);
+    }
+
+    #[test]
+    fn layout_rejects_too_many_incoming_edges() {
+        // a star into `hub`, undirected so every leaf also gets one incoming edge
+        let graph = ImportedGraph::from_json(r#"{
+            "directed": false,
+            "nodes": [{"id": "hub"}, {"id": "a"}, {"id": "b"}, {"id": "c"}],
+            "links": [{"source": "a", "target": "hub"}, {"source": "b", "target": "hub"}, {"source": "c", "target": "hub"}]
+        }"#).unwrap();
+
+        match graph.layout(field_size(4, 4), 2, &NodeMapping::RowMajor) {
+            Err(ImportError::TooManyEdges { edge_count: 2, nodes }) => assert_eq!(nodes, vec![("hub".to_string(), 3)]),
+            other => panic!("expected TooManyEdges, got {other:?}"),
+        }
+
+        let layout = graph.layout(field_size(4, 4), 3, &NodeMapping::RowMajor).unwrap();
+        assert_eq!(layout.edges.len(), 6);
+        assert_eq!(layout.locations[1], ("a".to_string(), UVec2::new(1, 0)));
+    }
+
+    #[test]
+    fn unused_edge_slots_are_empty() {
+        let graph = ImportedGraph::from_edge_list("a,b,0.5
+").unwrap();
+        let layout = graph.layout(field_size(2, 1), 2, &NodeMapping::RowMajor).unwrap();
+
+        let edges: Vec<EdgeState> = layout.edge_texels().into_iter().map(EdgeState::from).collect();
+        let used: Vec<usize> = (0..edges.len()).filter(|&i| !edges[i].is_empty()).collect();
+
+        // `b` at index 1 takes its first slot from `a` at (0, 0), everything else is unused
+        assert_eq!(used, vec![1]);
+        assert_eq!(edges[1].from_node_location, IVec2::ZERO);
+        assert_eq!(edges[1].weight, 0.5);
+    }
+
+    #[test]
+    fn layout_rejects_graphs_larger_than_the_field() {
+        let graph = ImportedGraph::from_edge_list("a,b\nc,d\ne,f\n").unwrap();
+
+        assert!(matches!(
+            graph.layout(field_size(2, 2), 1, &NodeMapping::RowMajor),
+            Err(ImportError::TooManyNodes { nodes: 6, capacity: 4 }),
+        ));
+    }
+}
@@ -0,0 +1,464 @@
+use std::{
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use bevy::prelude::*;
+
+use crate::{
+    automata::AutomataField,
+    readback::{
+        FieldSnapshot,
+        Readback,
+        SnapshotRequests,
+    },
+};
+
+mod import;
+pub use import::*;
+
+
+#[derive(Default)]
+pub struct ConnectomePlugin;
+
+impl Plugin for ConnectomePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FieldSnapshot>();
+        app.add_event::<ExportConnectome>();
+        app.add_event::<ImportConnectome>();
+
+        app.init_resource::<PendingConnectomeExports>();
+
+        app.add_systems(
+            Update,
+            (
+                request_connectome_exports,
+                export_connectomes,
+                import_connectomes,
+            ),
+        );
+    }
+}
+
+
+// format is picked from the file extension, see `ConnectomeFormat::from_path`
+#[derive(Event, Clone, Debug)]
+pub struct ExportConnectome(pub PathBuf);
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectomeFormat {
+    GraphMl,
+    Dot,
+    EdgeList,
+}
+
+impl ConnectomeFormat {
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "graphml" => Some(ConnectomeFormat::GraphMl),
+            "dot" | "gv" => Some(ConnectomeFormat::Dot),
+            "csv" => Some(ConnectomeFormat::EdgeList),
+            _ => None,
+        }
+    }
+}
+
+
+// node ids are row-major field indices, `y * width + x`
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectomeNode {
+    pub id: usize,
+    pub location: UVec2,
+    pub value: Option<f32>,
+    pub uaf: Option<Vec4>,
+}
+
+// `source` feeds into `target`, matching `Edge.from_node_location` in automata.wgsl
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectomeEdge {
+    pub source: usize,
+    pub target: usize,
+    pub edge_index: u32,
+    pub weight: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Connectome {
+    pub width: u32,
+    pub height: u32,
+    pub edge_count: u32,
+    pub nodes: Vec<ConnectomeNode>,
+    pub edges: Vec<ConnectomeEdge>,
+}
+
+impl Connectome {
+    pub fn from_snapshot(snapshot: &FieldSnapshot) -> Self {
+        let (width, height) = (snapshot.width, snapshot.height);
+
+        let nodes = (0..height)
+            .flat_map(|y| (0..width).map(move |x| UVec2::new(x, y)))
+            .enumerate()
+            .map(|(id, location)| ConnectomeNode {
+                id,
+                location,
+                value: snapshot.node(location).map(|node| node.value),
+                uaf: snapshot.uaf(location).copied(),
+            })
+            .collect();
+
+        let field_size = IVec2::new(width as i32, height as i32);
+        let edges = snapshot.edges
+            .iter()
+            .enumerate()
+            // unused and zero weight slots feed nothing into the node
+            .filter(|(_, edge)| !edge.is_empty() && edge.weight != 0.0)
+            .map(|(i, edge)| {
+                let texels = (width * height) as usize;
+                let source = edge.from_node_location.rem_euclid(field_size);
+
+                ConnectomeEdge {
+                    source: (source.y * field_size.x + source.x) as usize,
+                    target: i % texels,
+                    edge_index: (i / texels) as u32,
+                    weight: edge.weight,
+                }
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            edge_count: snapshot.edge_count,
+            nodes,
+            edges,
+        }
+    }
+
+    pub fn write(&self, format: ConnectomeFormat, writer: impl Write) -> io::Result<()> {
+        match format {
+            ConnectomeFormat::GraphMl => self.write_graphml(writer),
+            ConnectomeFormat::Dot => self.write_dot(writer),
+            ConnectomeFormat::EdgeList => self.write_edge_list(writer),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let format = ConnectomeFormat::from_path(&path).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "connectome path must end in .graphml, .dot, .gv or .csv",
+        ))?;
+
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(format, &mut writer)?;
+        writer.flush()
+    }
+
+    pub fn write_graphml(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(writer, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+
+        for (id, domain, kind) in [
+            ("width", "graph", "int"),
+            ("height", "graph", "int"),
+            ("edge_count", "graph", "int"),
+            ("x", "node", "int"),
+            ("y", "node", "int"),
+            ("value", "node", "float"),
+            ("uaf_a", "node", "float"),
+            ("uaf_b", "node", "float"),
+            ("uaf_c", "node", "float"),
+            ("uaf_d", "node", "float"),
+            ("weight", "edge", "float"),
+            ("edge_index", "edge", "int"),
+        ] {
+            writeln!(writer, r#"  <key id="{id}" for="{domain}" attr.name="{id}" attr.type="{kind}"/>"#)?;
+        }
+
+        writeln!(writer, r#"  <graph id="field" edgedefault="directed">"#)?;
+        writeln!(writer, r#"    <data key="width">{}</data>"#, self.width)?;
+        writeln!(writer, r#"    <data key="height">{}</data>"#, self.height)?;
+        writeln!(writer, r#"    <data key="edge_count">{}</data>"#, self.edge_count)?;
+
+        for node in &self.nodes {
+            writeln!(writer, r#"    <node id="{}">"#, node.id)?;
+            writeln!(writer, r#"      <data key="x">{}</data>"#, node.location.x)?;
+            writeln!(writer, r#"      <data key="y">{}</data>"#, node.location.y)?;
+            if let Some(value) = node.value {
+                writeln!(writer, r#"      <data key="value">{value}</data>"#)?;
+            }
+            if let Some(uaf) = node.uaf {
+                for (key, parameter) in ["uaf_a", "uaf_b", "uaf_c", "uaf_d"].iter().zip(uaf.to_array()) {
+                    writeln!(writer, r#"      <data key="{key}">{parameter}</data>"#)?;
+                }
+            }
+            writeln!(writer, "    </node>")?;
+        }
+
+        for edge in &self.edges {
+            writeln!(writer, r#"    <edge source="{}" target="{}">"#, edge.source, edge.target)?;
+            writeln!(writer, r#"      <data key="weight">{}</data>"#, edge.weight)?;
+            writeln!(writer, r#"      <data key="edge_index">{}</data>"#, edge.edge_index)?;
+            writeln!(writer, "    </edge>")?;
+        }
+
+        writeln!(writer, "  </graph>")?;
+        writeln!(writer, "</graphml>")
+    }
+
+    // positions are pinned (`neato -n`) to the field layout, y flipped so row 0 is on top
+    pub fn write_dot(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "digraph field {{")?;
+        writeln!(writer, "  node [shape=point];")?;
+
+        for node in &self.nodes {
+            write!(
+                writer,
+                r#"  {} [pos="{},{}!""#,
+                node.id,
+                node.location.x,
+                self.height.saturating_sub(1) - node.location.y,
+            )?;
+            if let Some(value) = node.value {
+                write!(writer, ", value={value}")?;
+            }
+            if let Some(uaf) = node.uaf {
+                write!(writer, ", uaf_a={}, uaf_b={}, uaf_c={}, uaf_d={}", uaf.x, uaf.y, uaf.z, uaf.w)?;
+            }
+            writeln!(writer, "];")?;
+        }
+
+        // `weight` is reserved by graphviz for non-negative integer layout weights, the signed weight goes in `w`
+        for edge in &self.edges {
+            let color = if edge.weight < 0.0 { "blue" } else { "red" };
+            writeln!(
+                writer,
+                "  {} -> {} [w={}, edge_index={}, color={color}, penwidth={}];",
+                edge.source,
+                edge.target,
+                edge.weight,
+                edge.edge_index,
+                edge.weight.abs().clamp(0.1, 4.0),
+            )?;
+        }
+
+        writeln!(writer, "}}")
+    }
+
+    // loads with `networkx.from_pandas_edgelist(pandas.read_csv(path), edge_attr=True, create_using=networkx.DiGraph)`
+    pub fn write_edge_list(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "source,target,weight,edge_index,source_x,source_y,target_x,target_y")?;
+
+        for edge in &self.edges {
+            let source = self.nodes[edge.source].location;
+            let target = self.nodes[edge.target].location;
+
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{}",
+                edge.source,
+                edge.target,
+                edge.weight,
+                edge.edge_index,
+                source.x,
+                source.y,
+                target.x,
+                target.y,
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+
+#[derive(Resource)]
+struct PendingConnectomeExports(SnapshotRequests<PathBuf>);
+
+impl Default for PendingConnectomeExports {
+    fn default() -> Self {
+        Self(SnapshotRequests::complete())
+    }
+}
+
+fn request_connectome_exports(
+    mut requests: EventReader<ExportConnectome>,
+    mut pending: ResMut<PendingConnectomeExports>,
+    mut readback: Option<ResMut<Readback>>,
+    automata: Option<Res<AutomataField>>,
+) {
+    pending.0.update(
+        requests.iter().map(|request| (request.0.clone(), 1)),
+        automata.as_deref(),
+        readback.as_deref_mut(),
+        "exporting connectomes",
+    );
+}
+
+fn export_connectomes(
+    mut pending: ResMut<PendingConnectomeExports>,
+    mut snapshots: EventReader<FieldSnapshot>,
+) {
+    if pending.0.is_empty() {
+        snapshots.clear();
+        return;
+    }
+
+    for (path, snapshots) in pending.0.receive(snapshots.iter()) {
+        let connectome = Connectome::from_snapshot(&snapshots[0]);
+
+        match connectome.save(&path) {
+            Ok(()) => info!("exported {} edges to {}", connectome.edges.len(), path.display()),
+            Err(err) => error!("failed to export connectome to {}: {err}", path.display()),
+        }
+    }
+}
+
+
+// replaces the current field, keeping its size and parameters
+fn import_connectomes(
+    mut commands: Commands,
+    mut requests: EventReader<ImportConnectome>,
+    mut images: ResMut<Assets<Image>>,
+    mut textures: Query<&mut Handle<Image>>,
+    automata: Option<Res<AutomataField>>,
+) {
+    let Some(request) = requests.iter().last() else {
+        return;
+    };
+
+    let Some(automata) = automata else {
+        error!("importing a connectome requires an AutomataField to size it");
+        return;
+    };
+
+    let parameters = automata.parameters();
+    let layout = ImportedGraph::load(&request.path)
+        .and_then(|graph| graph.layout(automata.size(), parameters.edge_count, &request.mapping));
+
+    let layout = match layout {
+        Ok(layout) => layout,
+        Err(err) => {
+            error!("failed to import connectome from {}: {err}", request.path.display());
+            return;
+        }
+    };
+
+    let automata_field = layout.into_field(parameters, &mut images);
+
+    for mut texture in &mut textures {
+        if *texture == automata.nodes {
+            *texture = automata_field.nodes.clone();
+        }
+    }
+
+    info!(
+        "imported {} nodes and {} edges from {}",
+        layout.locations.len(),
+        layout.edges.len(),
+        request.path.display(),
+    );
+
+    commands.insert_resource(automata_field);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::readback::{
+        EdgeState,
+        NodeState,
+    };
+
+    fn edge(x: i32, y: i32, weight: f32) -> EdgeState {
+        EdgeState {
+            from_node_location: IVec2::new(x, y),
+            weight,
+            downregulation: 0.0,
+        }
+    }
+
+    // two nodes feeding each other, the second layer holds an unused and a zero weight slot
+    fn connectome() -> Connectome {
+        Connectome::from_snapshot(&FieldSnapshot {
+            width: 2,
+            height: 1,
+            edge_count: 2,
+            nodes: vec![
+                NodeState { value: 0.5, ..default() },
+                NodeState { value: -1.0, ..default() },
+            ],
+            edges: vec![
+                edge(1, 0, 0.5),
+                edge(0, 0, -0.25),
+                EdgeState::EMPTY,
+                edge(1, 0, 0.0),
+            ],
+            ..default()
+        })
+    }
+
+    fn written(write: impl FnOnce(&Connectome, &mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut bytes = Vec::new();
+        write(&connectome(), &mut bytes).unwrap();
+        String::from_utf8(bytes).unwrap()
+    }
+
+    #[test]
+    fn skips_unused_and_zero_weight_slots() {
+        let connectome = connectome();
+
+        assert_eq!(connectome.nodes.len(), 2);
+        assert_eq!(connectome.edges, vec![
+            ConnectomeEdge { source: 1, target: 0, edge_index: 0, weight: 0.5 },
+            ConnectomeEdge { source: 0, target: 1, edge_index: 0, weight: -0.25 },
+        ]);
+    }
+
+    #[test]
+    fn graphml_reads_back() {
+        let graphml = written(|connectome, writer| connectome.write_graphml(writer));
+
+        assert!(graphml.contains(r#"<data key="edge_count">2</data>"#));
+        assert!(graphml.contains(r#"<data key="value">-1</data>"#));
+        assert!(!graphml.contains(r#"<data key="uaf_a">"#));
+
+        let graph = ImportedGraph::from_graphml(&graphml).unwrap();
+        let edges: Vec<_> = graph.edges.iter().map(|edge| (edge.source.as_str(), edge.target.as_str(), edge.weight)).collect();
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(edges, vec![("1", "0", 0.5), ("0", "1", -0.25)]);
+    }
+
+    #[test]
+    fn dot_pins_positions_and_colors_by_sign() {
+        let dot = written(|connectome, writer| connectome.write_dot(writer));
+
+        assert!(dot.starts_with("digraph field {\n"));
+        assert!(dot.contains(r#"  1 [pos="1,0!", value=-1];"#));
+        assert!(dot.contains("  1 -> 0 [w=0.5, edge_index=0, color=red, penwidth=0.5];"));
+        assert!(dot.contains("  0 -> 1 [w=-0.25, edge_index=0, color=blue, penwidth=0.25];"));
+        assert_eq!(dot.matches("->").count(), 2);
+    }
+
+    #[test]
+    fn edge_list_rows() {
+        let csv = written(|connectome, writer| connectome.write_edge_list(writer));
+
+        assert_eq!(csv, "\
+source,target,weight,edge_index,source_x,source_y,target_x,target_y
+1,0,0.5,0,1,0,0,0
+0,1,-0.25,0,0,0,1,0
+");
+    }
+}
@@ -0,0 +1,407 @@
+use std::{
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+};
+
+use bevy::prelude::*;
+
+use crate::readback::FieldSnapshot;
+
+
+#[derive(Default)]
+pub struct CriticalityPlugin;
+
+impl Plugin for CriticalityPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FieldSnapshot>();
+        app.init_resource::<Criticality>();
+        app.init_resource::<CriticalitySettings>();
+
+        app.add_systems(Update, observe_snapshots);
+    }
+}
+
+
+#[derive(Resource, Clone, Debug)]
+pub struct CriticalitySettings {
+    // where the editor saves the report
+    pub path: String,
+}
+
+impl Default for CriticalitySettings {
+    fn default() -> Self {
+        Self {
+            path: "criticality.csv".to_string(),
+        }
+    }
+}
+
+
+// expects node snapshots every step, e.g. `Readback::every(1)` with only the nodes read back
+fn observe_snapshots(
+    mut criticality: ResMut<Criticality>,
+    mut snapshots: EventReader<FieldSnapshot>,
+) {
+    for snapshot in snapshots.iter().filter(|snapshot| !snapshot.nodes.is_empty()) {
+        criticality.observe(snapshot.step, &snapshot.values());
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Avalanche {
+    pub start_step: u64,
+    pub size: u64,
+    pub duration: u32,
+}
+
+// a node fires when its |value| crosses the threshold upwards, nodes staying above it do not fire again
+//   an avalanche is a run of consecutive steps with at least one firing node
+//   firing needs the previous step, so the first step and the step after a gap only set the baseline
+#[derive(Resource, Clone, Debug)]
+pub struct Criticality {
+    pub threshold: f32,
+    pub enabled: bool,
+    pub avalanches: Vec<Avalanche>,
+    steps: u64,
+    last_step: Option<u64>,
+    above: Vec<bool>,
+    current: Option<Avalanche>,
+    previous_active: u64,
+    ancestors: u64,
+    descendants: u64,
+}
+
+impl Default for Criticality {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl Criticality {
+    pub fn new(threshold: f32) -> Self {
+        Self {
+            threshold,
+            enabled: true,
+            avalanches: Vec::new(),
+            steps: 0,
+            last_step: None,
+            above: Vec::new(),
+            current: None,
+            previous_active: 0,
+            ancestors: 0,
+            descendants: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self {
+            enabled: self.enabled,
+            ..Self::new(self.threshold)
+        };
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    pub fn observe(&mut self, step: u64, values: &[f32]) {
+        if !self.enabled {
+            return;
+        }
+
+        let contiguous = self.last_step.is_some_and(|last| step == last + 1) && self.above.len() == values.len();
+        if !contiguous {
+            self.close_avalanche();
+            self.previous_active = 0;
+            self.last_step = Some(step);
+            self.above = values.iter().map(|value| value.abs() > self.threshold).collect();
+            return;
+        }
+
+        let mut active = 0;
+        for (above, value) in self.above.iter_mut().zip(values) {
+            let now = value.abs() > self.threshold;
+            active += (now && !*above) as u64;
+            *above = now;
+        }
+
+        self.observe_active(step, active);
+    }
+
+    // `active` nodes fired at `step`
+    pub fn observe_active(&mut self, step: u64, active: u64) {
+        // a skipped step can't be attributed, so close any open avalanche
+        let contiguous = self.last_step.is_none_or(|last| step == last + 1);
+        if !contiguous {
+            self.close_avalanche();
+            self.previous_active = 0;
+        }
+        self.last_step = Some(step);
+        self.steps += 1;
+
+        if self.previous_active > 0 {
+            self.ancestors += self.previous_active;
+            self.descendants += active;
+        }
+        self.previous_active = active;
+
+        if active == 0 {
+            self.close_avalanche();
+            return;
+        }
+
+        let avalanche = self.current.get_or_insert(Avalanche {
+            start_step: step,
+            size: 0,
+            duration: 0,
+        });
+        avalanche.size += active;
+        avalanche.duration += 1;
+    }
+
+    fn close_avalanche(&mut self) {
+        if let Some(avalanche) = self.current.take() {
+            self.avalanches.push(avalanche);
+        }
+    }
+
+    // ratio estimator: descendants per ancestor over all consecutive active steps
+    pub fn branching_ratio(&self) -> Option<f32> {
+        if self.ancestors == 0 {
+            return None;
+        }
+
+        Some(self.descendants as f32 / self.ancestors as f32)
+    }
+
+    // finished avalanches followed by the open one up to the latest step
+    pub fn all_avalanches(&self) -> impl Iterator<Item = &Avalanche> + '_ {
+        self.avalanches.iter().chain(&self.current)
+    }
+
+    pub fn sizes(&self) -> impl Iterator<Item = u64> + '_ {
+        self.all_avalanches().map(|avalanche| avalanche.size)
+    }
+
+    pub fn durations(&self) -> impl Iterator<Item = u64> + '_ {
+        self.all_avalanches().map(|avalanche| avalanche.duration as u64)
+    }
+
+    pub fn report(&self) -> CriticalityReport {
+        let sizes: Vec<u64> = self.sizes().collect();
+        let durations: Vec<u64> = self.durations().collect();
+
+        CriticalityReport {
+            threshold: self.threshold,
+            steps: self.steps,
+            avalanche_count: sizes.len(),
+            branching_ratio: self.branching_ratio(),
+            size_fit: PowerLawFit::fit(&sizes, 1),
+            duration_fit: PowerLawFit::fit(&durations, 1),
+            size_distribution: histogram(&sizes),
+            duration_distribution: histogram(&durations),
+        }
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerLawFit {
+    pub exponent: f32,
+    pub x_min: u64,
+    pub samples: usize,
+}
+
+impl PowerLawFit {
+    // discrete maximum likelihood approximation (Clauset, Shalizi & Newman 2009, eq. 3.7)
+    pub fn fit(samples: &[u64], x_min: u64) -> Option<Self> {
+        let x_min = x_min.max(1);
+        let tail: Vec<f64> = samples
+            .iter()
+            .filter(|&&x| x >= x_min)
+            .map(|&x| x as f64)
+            .collect();
+
+        let log_sum: f64 = tail
+            .iter()
+            .map(|x| (x / (x_min as f64 - 0.5)).ln())
+            .sum();
+
+        if tail.len() < 2 || log_sum <= 0.0 {
+            return None;
+        }
+
+        Some(Self {
+            exponent: (1.0 + tail.len() as f64 / log_sum) as f32,
+            x_min,
+            samples: tail.len(),
+        })
+    }
+}
+
+
+// (value, count) pairs sorted by value
+pub fn histogram(samples: &[u64]) -> Vec<(u64, u64)> {
+    let mut sorted = samples.to_vec();
+    sorted.sort_unstable();
+
+    let mut bins: Vec<(u64, u64)> = Vec::new();
+    for x in sorted {
+        match bins.last_mut() {
+            Some((value, count)) if *value == x => *count += 1,
+            _ => bins.push((x, 1)),
+        }
+    }
+
+    bins
+}
+
+
+#[derive(Clone, Debug)]
+pub struct CriticalityReport {
+    pub threshold: f32,
+    pub steps: u64,
+    pub avalanche_count: usize,
+    pub branching_ratio: Option<f32>,
+    pub size_fit: Option<PowerLawFit>,
+    pub duration_fit: Option<PowerLawFit>,
+    pub size_distribution: Vec<(u64, u64)>,
+    pub duration_distribution: Vec<(u64, u64)>,
+}
+
+impl CriticalityReport {
+    pub fn summary(&self) -> Vec<(&'static str, String)> {
+        let optional = |value: Option<f32>| value.map_or("-".to_string(), |value| format!("{value:.4}"));
+
+        vec![
+            ("threshold", format!("{}", self.threshold)),
+            ("steps", format!("{}", self.steps)),
+            ("avalanches", format!("{}", self.avalanche_count)),
+            ("branching ratio", optional(self.branching_ratio)),
+            ("size exponent", optional(self.size_fit.map(|fit| fit.exponent))),
+            ("duration exponent", optional(self.duration_fit.map(|fit| fit.exponent))),
+        ]
+    }
+
+    // summary as `# key: value` comment lines followed by a `kind,value,count` table
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        for (key, value) in self.summary() {
+            writeln!(writer, "# {key}: {value}")?;
+        }
+
+        writeln!(writer, "kind,value,count")?;
+        for (value, count) in &self.size_distribution {
+            writeln!(writer, "size,{value},{count}")?;
+        }
+        for (value, count) in &self.duration_distribution {
+            writeln!(writer, "duration,{value},{count}")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut writer)?;
+        writer.flush()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn observed(active: &[u64]) -> Criticality {
+        let mut criticality = Criticality::default();
+        for (step, &active) in active.iter().enumerate() {
+            criticality.observe_active(step as u64 + 1, active);
+        }
+        criticality
+    }
+
+    #[test]
+    fn consecutive_active_steps_form_avalanches() {
+        let criticality = observed(&[0, 2, 3, 0, 1, 0]);
+
+        assert_eq!(criticality.avalanches, vec![
+            Avalanche { start_step: 2, size: 5, duration: 2 },
+            Avalanche { start_step: 5, size: 1, duration: 1 },
+        ]);
+    }
+
+    #[test]
+    fn skipped_steps_close_the_avalanche() {
+        let mut criticality = Criticality::default();
+        criticality.observe_active(1, 2);
+        criticality.observe_active(2, 2);
+        criticality.observe_active(4, 1);
+
+        assert_eq!(criticality.avalanches, vec![Avalanche { start_step: 1, size: 4, duration: 2 }]);
+        // the gap is not attributed to either side
+        assert_eq!(criticality.branching_ratio(), Some(1.0));
+    }
+
+    #[test]
+    fn report_includes_the_open_avalanche() {
+        let criticality = observed(&[1, 1, 1]);
+
+        assert!(criticality.avalanches.is_empty());
+        let report = criticality.report();
+        assert_eq!(report.avalanche_count, 1);
+        assert_eq!(report.size_distribution, vec![(3, 1)]);
+    }
+
+    #[test]
+    fn branching_ratio_is_descendants_per_ancestor() {
+        assert_eq!(observed(&[1, 0, 1, 0]).branching_ratio(), Some(0.0));
+        assert_eq!(observed(&[0, 0]).branching_ratio(), None);
+
+        // ancestors 1 + 2 + 4, descendants 2 + 4 + 0
+        let ratio = observed(&[1, 2, 4, 0]).branching_ratio().unwrap();
+        assert!((ratio - 6.0 / 7.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn nodes_fire_on_upward_crossings() {
+        let mut criticality = Criticality::new(0.5);
+
+        // the first step sets the baseline
+        criticality.observe(1, &[0.9, 0.0, 0.0]);
+        criticality.observe(2, &[0.9, -0.8, 0.0]);
+        criticality.observe(3, &[0.9, 0.8, 0.6]);
+        criticality.observe(4, &[0.9, 0.8, 0.6]);
+
+        assert_eq!(criticality.avalanches, vec![Avalanche { start_step: 2, size: 2, duration: 2 }]);
+        assert_eq!(criticality.steps(), 3);
+    }
+
+    #[test]
+    fn power_law_fit_recovers_the_exponent() {
+        // discrete power law with exponent 2.5 above 6, the approximation is biased for smaller x_min
+        let (alpha, x_min) = (2.5_f64, 6);
+        let n = 20_000;
+        let samples: Vec<u64> = (0..n)
+            .map(|i| {
+                let u = (i as f64 + 0.5) / n as f64;
+                ((x_min as f64 - 0.5) * (1.0 - u).powf(-1.0 / (alpha - 1.0)) + 0.5).floor() as u64
+            })
+            .collect();
+
+        let fit = PowerLawFit::fit(&samples, x_min).unwrap();
+        assert_eq!(fit.samples, n);
+        assert!((fit.exponent as f64 - alpha).abs() < 0.1, "exponent {}", fit.exponent);
+
+        let tail = PowerLawFit::fit(&samples, 20).unwrap();
+        assert!(tail.samples < n);
+
+        assert_eq!(PowerLawFit::fit(&[3], 1), None);
+        assert_eq!(PowerLawFit::fit(&[], 1), None);
+    }
+}
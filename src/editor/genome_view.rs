@@ -0,0 +1,392 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::{
+    evolution::{
+        EvolutionFile,
+        Genome,
+        NodeKind,
+    },
+    hyperneat::HyperNeatField,
+};
+
+
+const NODE_RADIUS: f32 = 7.0;
+const FORCE_ITERATIONS: usize = 200;
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GenomeLayout {
+    // columns by longest path from the inputs
+    #[default]
+    Layered,
+    // fruchterman-reingold from the layered positions, inputs and outputs stay in their columns
+    Force,
+}
+
+impl GenomeLayout {
+    pub const ALL: [GenomeLayout; 2] = [
+        GenomeLayout::Layered,
+        GenomeLayout::Force,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GenomeLayout::Layered => "layered",
+            GenomeLayout::Force => "force",
+        }
+    }
+
+    // node positions in the unit square
+    pub fn positions(&self, genome: &Genome) -> HashMap<u32, Vec2> {
+        match self {
+            GenomeLayout::Layered => layered_layout(genome),
+            GenomeLayout::Force => force_layout(genome),
+        }
+    }
+}
+
+
+// genomes shown by the editor's genome tab, next to the `HyperNeatField` cppn when there is one
+#[derive(Resource, Clone, Debug)]
+pub struct GenomeView {
+    pub genomes: Vec<(String, Genome)>,
+    pub selected: usize,
+    pub layout: GenomeLayout,
+    pub show_disabled: bool,
+    pub path: String,
+}
+
+impl Default for GenomeView {
+    fn default() -> Self {
+        Self {
+            genomes: Vec::new(),
+            selected: 0,
+            layout: GenomeLayout::default(),
+            show_disabled: true,
+            path: "genome.json".to_string(),
+        }
+    }
+}
+
+impl GenomeView {
+    // replaces a genome with the same name, so champions can be pushed every generation
+    //   returns the genome's index in `genomes`
+    pub fn push(&mut self, name: impl Into<String>, genome: Genome) -> usize {
+        let name = name.into();
+        match self.genomes.iter().position(|(existing, _)| *existing == name) {
+            Some(index) => {
+                self.genomes[index].1 = genome;
+                index
+            }
+            None => {
+                self.genomes.push((name, genome));
+                self.genomes.len() - 1
+            }
+        }
+    }
+}
+
+
+pub fn layered_layout(genome: &Genome) -> HashMap<u32, Vec2> {
+    // longest path over every connection, the genome is acyclic including disabled connections
+    let mut depth: HashMap<u32, usize> = genome.nodes.iter().map(|node| (node.id, 0)).collect();
+    for _ in 0..genome.nodes.len() {
+        let mut changed = false;
+        for connection in &genome.connections {
+            let candidate = depth.get(&connection.from).copied().unwrap_or(0) + 1;
+            if let Some(to) = depth.get_mut(&connection.to) {
+                if candidate > *to {
+                    *to = candidate;
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    let hidden_depth = genome.nodes
+        .iter()
+        .filter(|node| node.kind == NodeKind::Hidden)
+        .map(|node| depth[&node.id])
+        .max()
+        .unwrap_or(0);
+    let output_depth = hidden_depth + 1;
+
+    let mut layers: Vec<Vec<u32>> = vec![Vec::new(); output_depth + 1];
+    for node in &genome.nodes {
+        let layer = match node.kind {
+            NodeKind::Input => 0,
+            NodeKind::Hidden => depth[&node.id].clamp(1, hidden_depth.max(1)),
+            NodeKind::Output => output_depth,
+        };
+        layers[layer].push(node.id);
+    }
+
+    let mut positions = HashMap::with_capacity(genome.nodes.len());
+    for (layer, ids) in layers.iter().enumerate() {
+        for (i, &id) in ids.iter().enumerate() {
+            positions.insert(id, Vec2::new(
+                layer as f32 / output_depth as f32,
+                (i + 1) as f32 / (ids.len() + 1) as f32,
+            ));
+        }
+    }
+
+    positions
+}
+
+pub fn force_layout(genome: &Genome) -> HashMap<u32, Vec2> {
+    let mut positions = layered_layout(genome);
+    let ids: Vec<u32> = genome.nodes.iter().map(|node| node.id).collect();
+    let k = (1.0 / ids.len().max(1) as f32).sqrt();
+
+    for iteration in 0..FORCE_ITERATIONS {
+        let temperature = 0.1 * (1.0 - iteration as f32 / FORCE_ITERATIONS as f32);
+        let mut forces: HashMap<u32, Vec2> = ids.iter().map(|&id| (id, Vec2::ZERO)).collect();
+
+        for (i, &a) in ids.iter().enumerate() {
+            for &b in &ids[i + 1..] {
+                let delta = positions[&a] - positions[&b];
+                let distance = delta.length().max(1e-3);
+                let push = delta / distance * (k * k / distance);
+                *forces.get_mut(&a).unwrap() += push;
+                *forces.get_mut(&b).unwrap() -= push;
+            }
+        }
+
+        for connection in genome.enabled_connections() {
+            let (Some(&from), Some(&to)) = (positions.get(&connection.from), positions.get(&connection.to)) else {
+                continue;
+            };
+
+            let delta = to - from;
+            let distance = delta.length().max(1e-3);
+            let pull = delta / distance * (distance * distance / k);
+            *forces.get_mut(&connection.from).unwrap() += pull;
+            *forces.get_mut(&connection.to).unwrap() -= pull;
+        }
+
+        for node in &genome.nodes {
+            let force = forces[&node.id];
+            let step = force.normalize_or_zero() * force.length().min(temperature);
+            let position = positions.get_mut(&node.id).unwrap();
+
+            let step = match node.kind {
+                NodeKind::Hidden => step,
+                _ => Vec2::new(0.0, step.y),
+            };
+            *position = (*position + step).clamp(Vec2::splat(0.05), Vec2::splat(0.95));
+
+            // inputs and outputs keep the left and right edges
+            match node.kind {
+                NodeKind::Input => position.x = 0.0,
+                NodeKind::Output => position.x = 1.0,
+                NodeKind::Hidden => {}
+            }
+        }
+    }
+
+    positions
+}
+
+
+pub(crate) fn genome_ui(
+    ui: &mut egui::Ui,
+    world: &mut World,
+) {
+    let cppn = world.get_resource::<HyperNeatField>().map(|hyperneat| hyperneat.genome.clone());
+
+    let Some(mut view) = world.get_resource_mut::<GenomeView>() else {
+        return;
+    };
+
+    let mut sources: Vec<(&str, &Genome)> = cppn
+        .as_ref()
+        .map(|genome| ("hyperneat cppn", genome))
+        .into_iter()
+        .collect();
+    sources.extend(view.genomes.iter().map(|(name, genome)| (name.as_str(), genome)));
+
+    let mut selected = view.selected.min(sources.len().saturating_sub(1));
+    let mut layout = view.layout;
+    let mut show_disabled = view.show_disabled;
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_label("genome")
+            .selected_text(sources.get(selected).map_or("none", |(name, _)| *name))
+            .show_ui(ui, |ui| {
+                for (i, (name, _)) in sources.iter().enumerate() {
+                    ui.selectable_value(&mut selected, i, *name);
+                }
+            });
+
+        egui::ComboBox::from_label("layout")
+            .selected_text(layout.name())
+            .show_ui(ui, |ui| {
+                for option in GenomeLayout::ALL {
+                    ui.selectable_value(&mut layout, option, option.name());
+                }
+            });
+
+        ui.checkbox(&mut show_disabled, "disabled connections");
+    });
+
+    let genome = sources.get(selected).map(|(_, genome)| (*genome).clone());
+    let has_cppn = cppn.is_some();
+
+    view.selected = selected;
+    view.layout = layout;
+    view.show_disabled = show_disabled;
+
+    ui.horizontal(|ui| {
+        ui.label("path");
+        ui.text_edit_singleline(&mut view.path);
+
+        if ui.button("load").clicked() {
+            let path = view.path.clone();
+            match Genome::load(&path) {
+                Ok(genome) => {
+                    view.selected = view.push(path, genome) + has_cppn as usize;
+                }
+                Err(err) => error!("failed to load genome {path}: {err}"),
+            }
+        }
+
+        if let Some(genome) = &genome {
+            if ui.button("save").clicked() {
+                if let Err(err) = genome.save(&view.path) {
+                    error!("failed to save genome {}: {err}", view.path);
+                }
+            }
+        }
+    });
+
+    let Some(genome) = genome else {
+        ui.label("add genomes to the GenomeView resource or load one");
+        return;
+    };
+
+    ui.label(format!(
+        "{} inputs, {} hidden, {} outputs, {} of {} connections enabled",
+        genome.inputs,
+        genome.hidden_count(),
+        genome.outputs,
+        genome.enabled_connections().count(),
+        genome.connections.len(),
+    ));
+
+    draw_genome(ui, &genome, layout, show_disabled);
+}
+
+fn draw_genome(
+    ui: &mut egui::Ui,
+    genome: &Genome,
+    layout: GenomeLayout,
+    show_disabled: bool,
+) {
+    let (response, painter) = ui.allocate_painter(ui.available_size(), egui::Sense::hover());
+    let rect = response.rect.shrink(NODE_RADIUS * 3.0);
+
+    let positions: HashMap<u32, egui::Pos2> = layout
+        .positions(genome)
+        .into_iter()
+        .map(|(id, position)| (id, rect.lerp_inside(egui::vec2(position.x, position.y))))
+        .collect();
+
+    let max_weight = genome.connections
+        .iter()
+        .map(|connection| connection.weight.abs())
+        .fold(1e-3, f32::max);
+
+    for connection in &genome.connections {
+        let (Some(&from), Some(&to)) = (positions.get(&connection.from), positions.get(&connection.to)) else {
+            continue;
+        };
+
+        let direction = (to - from).normalized();
+        let start = from + direction * NODE_RADIUS;
+        let end = to - direction * NODE_RADIUS;
+
+        if !connection.enabled {
+            if show_disabled {
+                painter.extend(egui::Shape::dashed_line(
+                    &[start, end],
+                    egui::Stroke::new(1.0, egui::Color32::from_gray(110)),
+                    4.0,
+                    4.0,
+                ));
+            }
+            continue;
+        }
+
+        // blue excitatory, red inhibitory, stronger connections are wider and more opaque
+        let magnitude = connection.weight.abs() / max_weight;
+        let color = match connection.weight >= 0.0 {
+            true => egui::Color32::from_rgb(80, 150, 255),
+            false => egui::Color32::from_rgb(255, 90, 80),
+        };
+        let stroke = egui::Stroke::new(
+            0.5 + 3.0 * magnitude,
+            color.gamma_multiply(0.25 + 0.75 * magnitude),
+        );
+
+        painter.arrow(start, end - start, stroke);
+    }
+
+    let pointer = response.hover_pos();
+    let mut hovered = None;
+
+    for node in &genome.nodes {
+        let Some(&position) = positions.get(&node.id) else {
+            continue;
+        };
+
+        let fill = match node.kind {
+            NodeKind::Input => egui::Color32::from_rgb(120, 200, 140),
+            NodeKind::Hidden => egui::Color32::from_rgb(230, 200, 90),
+            NodeKind::Output => egui::Color32::from_rgb(190, 130, 230),
+        };
+
+        painter.circle(position, NODE_RADIUS, fill, egui::Stroke::new(1.0, egui::Color32::BLACK));
+        painter.text(
+            position + egui::vec2(0.0, NODE_RADIUS + 1.0),
+            egui::Align2::CENTER_TOP,
+            node.id.to_string(),
+            egui::FontId::monospace(9.0),
+            ui.visuals().text_color(),
+        );
+
+        if node.kind != NodeKind::Input && pointer.is_some_and(|pointer| pointer.distance(position) <= NODE_RADIUS) {
+            hovered = Some(node);
+        }
+    }
+
+    if let Some(node) = hovered {
+        let uaf = node.uaf;
+        egui::show_tooltip_at_pointer(ui.ctx(), egui::Id::new("genome_uaf_tooltip"), |ui| {
+            ui.label(format!(
+                "node {}: a {:.3}, b {:.3}, c {:.3}, d {:.3}, e {:.3}",
+                node.id, uaf.a, uaf.b, uaf.c, uaf.d, uaf.e,
+            ));
+
+            egui::plot::Plot::new("genome_uaf_curve")
+                .width(220.0)
+                .height(140.0)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .show(ui, |plot_ui| {
+                    plot_ui.line(egui::plot::Line::new(egui::plot::PlotPoints::from_explicit_callback(
+                        move |x| uaf.eval(x as f32) as f64,
+                        -4.0..=4.0,
+                        128,
+                    )));
+                });
+        });
+    }
+}
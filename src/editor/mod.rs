@@ -40,6 +40,28 @@ use egui_dock::{
     Tree
 };
 
+mod genome_view;
+pub use genome_view::*;
+
+use crate::{
+    checkpoint::{
+        CheckpointSettings,
+        LoadCheckpoint,
+        SaveCheckpoint,
+    },
+    criticality::{
+        Criticality,
+        CriticalityReport,
+        CriticalitySettings,
+    },
+    trace::{
+        TraceChannel,
+        Traces,
+        MAX_TRACE_PROBES,
+        MAX_TRACE_WINDOW,
+    },
+};
+
 
 // TODO: toggle UI with F1 key
 // TODO: move UI system to core as a plugin, expose AutomataReflect to filter UI (expect proper type registration still), draw fps over UI, or move it into clip-rect space?
@@ -55,6 +77,7 @@ impl Plugin for EditorPlugin {
         ));
 
         app.init_resource::<EguiWantsFocus>();
+        app.init_resource::<GenomeView>();
         app.insert_resource(UiState::new());
 
         app.add_systems(Update, esc_close);
@@ -180,21 +203,29 @@ struct UiState {
     viewport_rect: egui::Rect,
     selected_entities: SelectedEntities,
     selection: InspectorSelection,
+    criticality_report: Option<CachedReport>,
     enabled: bool,
 }
 
+// the report is only rebuilt when the observed steps, avalanches or threshold change
+struct CachedReport {
+    key: (u64, usize, u32),
+    report: CriticalityReport,
+}
+
 impl UiState {
     pub fn new() -> Self {
         let mut tree = Tree::new(vec![EguiWindow::GameView]);
         let [game, _inspector] = tree.split_right(NodeIndex::root(), 0.75, vec![EguiWindow::Inspector]);
         let [game, _hierarchy] = tree.split_left(game, 0.2, vec![EguiWindow::Hierarchy]);
-        let [_game, _bottom] = tree.split_below(game, 0.8, vec![EguiWindow::Resources, EguiWindow::Assets]);
+        let [_game, _bottom] = tree.split_below(game, 0.8, vec![EguiWindow::Resources, EguiWindow::Assets, EguiWindow::Criticality, EguiWindow::Traces, EguiWindow::Checkpoint, EguiWindow::Genome]);
 
         Self {
             tree,
             selected_entities: SelectedEntities::default(),
             selection: InspectorSelection::Entities,
             viewport_rect: egui::Rect::NOTHING,
+            criticality_report: None,
             enabled: false,
         }
     }
@@ -209,6 +240,7 @@ impl UiState {
             viewport_rect: &mut self.viewport_rect,
             selected_entities: &mut self.selected_entities,
             selection: &mut self.selection,
+            criticality_report: &mut self.criticality_report,
         };
         DockArea::new(&mut self.tree)
             .style(Style::from_egui(ctx.style().as_ref()))
@@ -223,6 +255,10 @@ enum EguiWindow {
     Resources,
     Assets,
     Inspector,
+    Criticality,
+    Traces,
+    Checkpoint,
+    Genome,
 }
 
 struct TabViewer<'a> {
@@ -230,6 +266,7 @@ struct TabViewer<'a> {
     selected_entities: &'a mut SelectedEntities,
     selection: &'a mut InspectorSelection,
     viewport_rect: &'a mut egui::Rect,
+    criticality_report: &'a mut Option<CachedReport>,
 }
 
 // TODO: redo the UI to be more CA focused (only select CA types tagged with AutomataReflect?)
@@ -251,6 +288,10 @@ impl egui_dock::TabViewer for TabViewer<'_> {
             }
             EguiWindow::Resources => select_resource(ui, &type_registry, self.selection),
             EguiWindow::Assets => select_asset(ui, &type_registry, self.world, self.selection),
+            EguiWindow::Criticality => criticality_ui(ui, self.world, self.criticality_report),
+            EguiWindow::Traces => traces_ui(ui, self.world),
+            EguiWindow::Checkpoint => checkpoint_ui(ui, self.world),
+            EguiWindow::Genome => genome_ui(ui, self.world),
             EguiWindow::Inspector => match *self.selection {
                 InspectorSelection::Entities => match self.selected_entities.as_slice() {
                     &[entity] => ui_for_entity_with_children(self.world, entity, ui),
@@ -289,6 +330,199 @@ impl egui_dock::TabViewer for TabViewer<'_> {
     }
 }
 
+fn criticality_ui(
+    ui: &mut egui::Ui,
+    world: &mut World,
+    cache: &mut Option<CachedReport>,
+) {
+    let Some(criticality) = world.get_resource::<Criticality>() else {
+        ui.label("add the CriticalityPlugin to analyze avalanches");
+        return;
+    };
+    let was_path = world.get_resource::<CriticalitySettings>().map_or_else(default, |settings| settings.path.clone());
+
+    let key = (criticality.steps(), criticality.avalanches.len(), criticality.threshold.to_bits());
+    if cache.as_ref().is_none_or(|cached| cached.key != key) {
+        *cache = Some(CachedReport {
+            key,
+            report: criticality.report(),
+        });
+    }
+    let report = &cache.as_ref().unwrap().report;
+
+    // only take the resource mutably on an edit, so it isn't marked changed every frame
+    let (was_enabled, was_threshold) = (criticality.enabled, criticality.threshold);
+    let mut enabled = was_enabled;
+    let mut threshold = was_threshold;
+    let mut path = was_path.clone();
+    let mut reset = false;
+
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut enabled, "enabled");
+        ui.add(egui::DragValue::new(&mut threshold).speed(0.01).prefix("threshold: "));
+
+        reset = ui.button("reset").clicked();
+    });
+
+    ui.horizontal(|ui| {
+        ui.label("path");
+        ui.text_edit_singleline(&mut path);
+
+        if ui.button("save report").clicked() {
+            if let Err(err) = report.save(&path) {
+                error!("failed to save criticality report to {path}: {err}");
+            }
+        }
+    });
+
+    egui::Grid::new("criticality_summary").show(ui, |ui| {
+        for (key, value) in report.summary() {
+            ui.label(key);
+            ui.label(value);
+            ui.end_row();
+        }
+    });
+
+    // log-log distributions, a power law shows up as a straight line
+    let log_points = |distribution: &[(u64, u64)]| -> egui::plot::PlotPoints {
+        distribution
+            .iter()
+            .map(|&(value, count)| [(value as f64).log10(), (count as f64).log10()])
+            .collect()
+    };
+
+    egui::plot::Plot::new("criticality_distributions")
+        .legend(egui::plot::Legend::default())
+        .show(ui, |plot_ui| {
+            plot_ui.points(egui::plot::Points::new(log_points(&report.size_distribution)).name("size").radius(2.0));
+            plot_ui.points(egui::plot::Points::new(log_points(&report.duration_distribution)).name("duration").radius(2.0));
+        });
+
+    if path != was_path {
+        if let Some(mut settings) = world.get_resource_mut::<CriticalitySettings>() {
+            settings.path = path;
+        }
+    }
+
+    if enabled != was_enabled || threshold != was_threshold || reset {
+        let mut criticality = world.resource_mut::<Criticality>();
+        criticality.enabled = enabled;
+        criticality.threshold = threshold;
+
+        if reset {
+            criticality.reset();
+        }
+    }
+}
+
+fn traces_ui(
+    ui: &mut egui::Ui,
+    world: &mut World,
+) {
+    let Some(traces) = world.get_resource::<Traces>() else {
+        ui.label("add the TracePlugin to record node activity");
+        return;
+    };
+
+    // only take the resource mutably on an edit, so it isn't marked changed every frame
+    let (was_channel, was_gain, was_window) = (traces.channel, traces.gain, traces.window);
+    let was_probes = traces.probes.clone();
+    let mut channel = was_channel;
+    let mut gain = was_gain;
+    let mut window = was_window;
+    let mut probes = was_probes.clone();
+    let mut clear = false;
+
+    ui.horizontal(|ui| {
+        egui::ComboBox::from_label("channel")
+            .selected_text(channel.name())
+            .show_ui(ui, |ui| {
+                for option in TraceChannel::ALL {
+                    ui.selectable_value(&mut channel, option, option.name());
+                }
+            });
+
+        ui.add(egui::DragValue::new(&mut gain).speed(0.01).prefix("gain: "));
+        ui.add(egui::DragValue::new(&mut window).clamp_range(2..=MAX_TRACE_WINDOW).prefix("window: "));
+
+        clear = ui.button("clear").clicked();
+    });
+
+    // moving a probe starts a new recording
+    let mut unpinned = None;
+    for (i, probe) in probes.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            ui.label(format!("{i}"));
+            ui.add(egui::DragValue::new(&mut probe.x).prefix("x: "));
+            ui.add(egui::DragValue::new(&mut probe.y).prefix("y: "));
+
+            if ui.button("unpin").clicked() {
+                unpinned = Some(*probe);
+            }
+        });
+    }
+
+    let mut pinned = None;
+    ui.add_enabled_ui(probes.len() < MAX_TRACE_PROBES, |ui| {
+        if ui.button("pin").clicked() {
+            pinned = Some(probes.last().map_or(UVec2::ZERO, |probe| *probe + UVec2::X));
+        }
+    });
+
+    let edited = channel != was_channel
+        || gain != was_gain
+        || window != was_window
+        || probes != was_probes
+        || clear
+        || unpinned.is_some()
+        || pinned.is_some();
+    if !edited {
+        return;
+    }
+
+    let mut traces = world.resource_mut::<Traces>();
+    traces.channel = channel;
+    traces.gain = gain;
+    traces.window = window;
+    traces.probes = probes;
+
+    if clear {
+        traces.clear();
+    }
+    if let Some(probe) = unpinned {
+        traces.unpin(probe);
+    }
+    if let Some(probe) = pinned {
+        traces.pin(probe);
+    }
+}
+
+fn checkpoint_ui(
+    ui: &mut egui::Ui,
+    world: &mut World,
+) {
+    let Some(mut settings) = world.get_resource_mut::<CheckpointSettings>() else {
+        ui.label("add the CheckpointPlugin to save and load fields");
+        return;
+    };
+
+    ui.horizontal(|ui| {
+        ui.label("path");
+        ui.text_edit_singleline(&mut settings.path);
+    });
+    let path = std::path::PathBuf::from(&settings.path);
+
+    ui.horizontal(|ui| {
+        if ui.button("save").clicked() {
+            world.send_event(SaveCheckpoint(path.clone()));
+        }
+
+        if ui.button("load").clicked() {
+            world.send_event(LoadCheckpoint(path));
+        }
+    });
+}
+
 fn select_resource(
     ui: &mut egui::Ui,
     type_registry: &TypeRegistry,
@@ -0,0 +1,476 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::Extent3d,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    neat::{
+        FieldInput,
+        InputMode,
+        InputRegion,
+        RegionId,
+    },
+    readback::{
+        FieldOutput,
+        OutputPlugin,
+        OutputRegion,
+        OutputSample,
+    },
+};
+
+mod tasks;
+pub use tasks::*;
+
+
+// frames to wait for the output of an observation before the episode is abandoned
+const ACTION_TIMEOUT: u32 = 120;
+
+
+// runs episodes of an `Environment` in closed loop with the field, add after the `NeatPlugin`
+#[derive(Default)]
+pub struct EnvironmentPlugin;
+
+impl Plugin for EnvironmentPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<OutputPlugin>() {
+            app.add_plugins(OutputPlugin);
+        }
+
+        app.add_event::<EvaluationReport>();
+        app.init_resource::<EpisodeRunner>();
+
+        app.add_systems(Update, run_episodes);
+    }
+}
+
+
+// a gym-style task, observations are written into field input regions and actions decoded from output regions
+//   observations and actions are expected to lie in -1..1
+pub trait Environment: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn observation_size(&self) -> usize;
+
+    fn action_size(&self) -> usize;
+
+    // starts a new episode, the same seed gives the same episode
+    fn reset(&mut self, seed: u64);
+
+    fn observe(&self) -> Vec<f32>;
+
+    fn act(&mut self, action: &[f32]);
+
+    // reward of the last action
+    fn reward(&self) -> f32;
+
+    fn done(&self) -> bool;
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnvironmentKind {
+    #[default]
+    Xor,
+    PoleBalancing,
+    MountainCar,
+    Foraging,
+}
+
+impl EnvironmentKind {
+    pub const ALL: [EnvironmentKind; 4] = [
+        EnvironmentKind::Xor,
+        EnvironmentKind::PoleBalancing,
+        EnvironmentKind::MountainCar,
+        EnvironmentKind::Foraging,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnvironmentKind::Xor => "xor",
+            EnvironmentKind::PoleBalancing => "pole balancing",
+            EnvironmentKind::MountainCar => "mountain car",
+            EnvironmentKind::Foraging => "foraging",
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn Environment> {
+        match self {
+            EnvironmentKind::Xor => Box::<Xor>::default(),
+            EnvironmentKind::PoleBalancing => Box::<PoleBalancing>::default(),
+            EnvironmentKind::MountainCar => Box::<MountainCar>::default(),
+            EnvironmentKind::Foraging => Box::<Foraging>::default(),
+        }
+    }
+}
+
+
+// one square input region per observation down the left of the field, one output region per action down the right
+//   an action is the mean value of its region's nodes
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EpisodeConfig {
+    pub episodes: usize,
+    pub seed: u64,
+    // field steps between an observation and the action read from the field
+    pub steps_per_action: u32,
+    pub region_size: u32,
+    pub input_mode: InputMode,
+    pub input_scale: f32,
+    pub input_column: u32,
+    pub output_column: u32,
+    pub top: u32,
+    pub spacing: u32,
+}
+
+impl Default for EpisodeConfig {
+    fn default() -> Self {
+        Self {
+            episodes: 4,
+            seed: 0,
+            steps_per_action: 4,
+            region_size: 4,
+            input_mode: InputMode::Add,
+            input_scale: 1.0,
+            input_column: 64,
+            output_column: 96,
+            top: 64,
+            spacing: 16,
+        }
+    }
+}
+
+impl EpisodeConfig {
+    // inputs a little left of the field's center and outputs a little right, within reach of the edges
+    //   small fields clamp to their top and right edge, regions past the field read and write nothing
+    pub fn for_field(field_size: Extent3d) -> Self {
+        let center = field_size.width / 2;
+        let region_size = Self::default().region_size;
+
+        Self {
+            input_column: center.saturating_sub(16),
+            output_column: (center + 16).min(field_size.width.saturating_sub(region_size)),
+            top: (field_size.height / 2).saturating_sub(32),
+            ..default()
+        }
+    }
+
+    fn region_origin(&self, column: u32, index: usize) -> UVec2 {
+        UVec2::new(column, self.top + index as u32 * self.spacing)
+    }
+
+    pub fn input_region(&self, index: usize) -> InputRegion {
+        InputRegion::new(self.region_origin(self.input_column, index), UVec2::splat(self.region_size), self.input_mode)
+    }
+
+    pub fn output_region(&self, index: usize) -> OutputRegion {
+        OutputRegion::new(self.region_origin(self.output_column, index), UVec2::splat(self.region_size))
+    }
+}
+
+
+// returns and lengths of each episode, the fitness is the mean return
+#[derive(Event, Clone, Debug, Default, PartialEq)]
+pub struct EvaluationReport {
+    pub environment: String,
+    pub returns: Vec<f32>,
+    pub lengths: Vec<usize>,
+    // an episode was abandoned because the field's output never arrived
+    pub incomplete: bool,
+}
+
+impl EvaluationReport {
+    pub fn fitness(&self) -> f32 {
+        match self.returns.is_empty() {
+            true => f32::NAN,
+            false => self.returns.iter().sum::<f32>() / self.returns.len() as f32,
+        }
+    }
+}
+
+
+// field input and output regions owned by an evaluation, by id so other owners may add or remove theirs
+#[derive(Clone, Debug, Default)]
+struct RegionBlock {
+    inputs: Vec<RegionId>,
+    outputs: Vec<RegionId>,
+}
+
+impl RegionBlock {
+    fn add(
+        config: &EpisodeConfig,
+        environment: &dyn Environment,
+        field_input: &mut FieldInput,
+        field_output: &mut FieldOutput,
+    ) -> Option<Self> {
+        let mut block = Self::default();
+
+        for i in 0..environment.observation_size() {
+            if let Some(index) = field_input.add_region(config.input_region(i)) {
+                block.inputs.push(field_input.regions[index].id);
+            }
+        }
+        for i in 0..environment.action_size() {
+            if let Some(index) = field_output.add_region(config.output_region(i)) {
+                block.outputs.push(field_output.regions[index].id);
+            }
+        }
+
+        if block.inputs.len() < environment.observation_size() || block.outputs.len() < environment.action_size() {
+            block.release(field_input, field_output);
+            return None;
+        }
+
+        Some(block)
+    }
+
+    fn release(
+        &self,
+        field_input: &mut FieldInput,
+        field_output: &mut FieldOutput,
+    ) {
+        for &id in &self.inputs {
+            field_input.remove_id(id);
+        }
+        for &id in &self.outputs {
+            field_output.remove_id(id);
+        }
+    }
+}
+
+struct Evaluation {
+    environment: Box<dyn Environment>,
+    config: EpisodeConfig,
+    report: EvaluationReport,
+    regions: Option<RegionBlock>,
+    episode: usize,
+    episode_return: f32,
+    episode_length: usize,
+    // tags the current observation in the field input
+    sequence: u64,
+    // output samples seen for the current observation
+    responses: u32,
+    waited: u32,
+}
+
+impl Evaluation {
+    fn observe(
+        &self,
+        regions: &RegionBlock,
+        field_input: &mut FieldInput,
+    ) {
+        for (&id, observation) in regions.inputs.iter().zip(self.environment.observe()) {
+            if let Some(region) = field_input.index_of(id).and_then(|index| field_input.region_mut(index)) {
+                region.fill(observation * self.config.input_scale);
+            }
+        }
+
+        field_input.sequence = Some(self.sequence);
+    }
+
+    // the action once the field has run `steps_per_action` steps on the current observation
+    fn response(
+        &mut self,
+        regions: &RegionBlock,
+        field_output: &FieldOutput,
+        samples: &mut EventReader<OutputSample>,
+    ) -> Option<Vec<f32>> {
+        let mut action = None;
+
+        for sample in samples.iter().filter(|sample| sample.sequence == Some(self.sequence)) {
+            self.responses += 1;
+
+            if action.is_none() && self.responses >= self.config.steps_per_action.max(1) {
+                action = Some(
+                    regions.outputs
+                        .iter()
+                        .map(|&id| field_output.index_of(id).and_then(|index| sample.mean(index)).unwrap_or(0.0))
+                        .collect(),
+                );
+            }
+        }
+
+        action
+    }
+}
+
+// evaluates the current field on an environment, the field state carries over between episodes
+//   only one evaluation or reservoir run should drive the field input at a time
+#[derive(Resource, Default)]
+pub struct EpisodeRunner {
+    pub report: Option<EvaluationReport>,
+    evaluation: Option<Evaluation>,
+    evaluations: u64,
+    // regions of a replaced evaluation, released on the next update
+    release: Option<RegionBlock>,
+}
+
+impl EpisodeRunner {
+    // replaces any evaluation in progress
+    pub fn evaluate(&mut self, mut environment: Box<dyn Environment>, config: EpisodeConfig) {
+        environment.reset(config.seed);
+
+        if let Some(regions) = self.evaluation.take().and_then(|evaluation| evaluation.regions) {
+            self.release = Some(regions);
+        }
+
+        self.evaluations += 1;
+        self.evaluation = Some(Evaluation {
+            report: EvaluationReport {
+                environment: environment.name().to_string(),
+                ..default()
+            },
+            environment,
+            config,
+            regions: None,
+            episode: 0,
+            episode_return: 0.0,
+            episode_length: 0,
+            sequence: self.evaluations << 32,
+            responses: 0,
+            waited: 0,
+        });
+    }
+
+    pub fn evaluate_kind(&mut self, kind: EnvironmentKind, config: EpisodeConfig) {
+        self.evaluate(kind.build(), config);
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.evaluation.is_some()
+    }
+
+    // fraction of the episodes finished
+    pub fn progress(&self) -> Option<f32> {
+        self.evaluation
+            .as_ref()
+            .map(|evaluation| evaluation.episode as f32 / evaluation.config.episodes.max(1) as f32)
+    }
+}
+
+
+fn run_episodes(
+    mut runner: ResMut<EpisodeRunner>,
+    mut field_input: ResMut<FieldInput>,
+    mut field_output: ResMut<FieldOutput>,
+    mut samples: EventReader<OutputSample>,
+    mut reports: EventWriter<EvaluationReport>,
+) {
+    let runner = &mut *runner;
+
+    if let Some(regions) = runner.release.take() {
+        regions.release(&mut field_input, &mut field_output);
+    }
+
+    let Some(evaluation) = runner.evaluation.as_mut() else {
+        samples.clear();
+        return;
+    };
+
+    let regions = match &evaluation.regions {
+        Some(regions) => regions.clone(),
+        None => {
+            let Some(regions) = RegionBlock::add(&evaluation.config, evaluation.environment.as_ref(), &mut field_input, &mut field_output) else {
+                error!("not enough free field input or output regions for {}", evaluation.report.environment);
+                runner.evaluation = None;
+                return;
+            };
+
+            evaluation.observe(&regions, &mut field_input);
+            evaluation.regions = Some(regions);
+            samples.clear();
+            return;
+        }
+    };
+
+    match evaluation.response(&regions, &field_output, &mut samples) {
+        Some(action) => {
+            evaluation.waited = 0;
+            evaluation.environment.act(&action);
+            evaluation.episode_return += evaluation.environment.reward();
+            evaluation.episode_length += 1;
+        }
+        None => {
+            evaluation.waited += 1;
+            if evaluation.waited < ACTION_TIMEOUT {
+                return;
+            }
+
+            warn!("no field output for {} after {ACTION_TIMEOUT} frames", evaluation.report.environment);
+            evaluation.report.incomplete = true;
+            evaluation.episode = evaluation.config.episodes;
+        }
+    }
+
+    if evaluation.environment.done() || evaluation.report.incomplete {
+        evaluation.report.returns.push(evaluation.episode_return);
+        evaluation.report.lengths.push(evaluation.episode_length);
+        evaluation.episode += 1;
+        evaluation.episode_return = 0.0;
+        evaluation.episode_length = 0;
+
+        if evaluation.episode >= evaluation.config.episodes {
+            let Some(evaluation) = runner.evaluation.take() else {
+                return;
+            };
+            regions.release(&mut field_input, &mut field_output);
+            field_input.sequence = None;
+
+            info!("{} fitness: {:.4}", evaluation.report.environment, evaluation.report.fitness());
+
+            reports.send(evaluation.report.clone());
+            runner.report = Some(evaluation.report);
+            return;
+        }
+
+        evaluation.environment.reset(evaluation.config.seed + evaluation.episode as u64);
+    }
+
+    evaluation.sequence += 1;
+    evaluation.responses = 0;
+    evaluation.observe(&regions, &mut field_input);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_fields_keep_regions_in_bounds() {
+        for (width, height) in [(1, 1), (8, 8), (24, 40), (256, 256)] {
+            let config = EpisodeConfig::for_field(Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            });
+
+            assert!(config.top <= height / 2);
+            assert!(config.output_column + config.region_size <= width.max(config.region_size));
+        }
+    }
+
+    #[test]
+    fn released_regions_survive_removals_by_other_owners() {
+        let config = EpisodeConfig::default();
+        let mut field_input = FieldInput::default();
+        let mut field_output = FieldOutput::default();
+
+        // the reservoir's regions come first, the evaluation's after them
+        field_input.add_region(InputRegion::new(UVec2::ZERO, UVec2::ONE, InputMode::Add));
+        field_output.add_region(OutputRegion::new(UVec2::ZERO, UVec2::ONE));
+        let block = RegionBlock::add(&config, &Xor::default(), &mut field_input, &mut field_output).unwrap();
+        let kept_input = field_input.add_region(InputRegion::new(UVec2::ONE, UVec2::ONE, InputMode::Clamp)).unwrap();
+        let kept_input = field_input.regions[kept_input].id;
+
+        field_input.remove_region(0);
+        field_output.remove_region(0);
+        block.release(&mut field_input, &mut field_output);
+
+        assert_eq!(field_input.regions.len(), 1);
+        assert_eq!(field_input.regions[0].id, kept_input);
+        assert!(field_output.regions.is_empty());
+    }
+}
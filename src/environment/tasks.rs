@@ -0,0 +1,385 @@
+use std::f32::consts::PI;
+
+use rand::{
+    rngs::StdRng,
+    seq::SliceRandom,
+    Rng,
+    SeedableRng,
+};
+
+use super::Environment;
+
+
+// the four xor cases in a seeded order, observations and actions are -1 for false and 1 for true
+#[derive(Clone, Debug)]
+pub struct Xor {
+    cases: Vec<(bool, bool)>,
+    index: usize,
+    reward: f32,
+}
+
+impl Default for Xor {
+    fn default() -> Self {
+        Self {
+            cases: vec![(false, false), (false, true), (true, false), (true, true)],
+            index: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+impl Environment for Xor {
+    fn name(&self) -> &'static str {
+        "xor"
+    }
+
+    fn observation_size(&self) -> usize {
+        2
+    }
+
+    fn action_size(&self) -> usize {
+        1
+    }
+
+    fn reset(&mut self, seed: u64) {
+        self.cases.shuffle(&mut StdRng::seed_from_u64(seed));
+        self.index = 0;
+        self.reward = 0.0;
+    }
+
+    fn observe(&self) -> Vec<f32> {
+        let sign = |bit: bool| if bit { 1.0 } else { -1.0 };
+
+        match self.cases.get(self.index) {
+            Some(&(a, b)) => vec![sign(a), sign(b)],
+            None => vec![0.0, 0.0],
+        }
+    }
+
+    // 1 for the exact answer, 0 for the opposite one
+    fn act(&mut self, action: &[f32]) {
+        let Some(&(a, b)) = self.cases.get(self.index) else {
+            return;
+        };
+
+        let target = if a != b { 1.0 } else { -1.0 };
+        let output = action.first().copied().unwrap_or(0.0).clamp(-1.0, 1.0);
+
+        self.reward = 1.0 - (output - target).powi(2) / 4.0;
+        self.index += 1;
+    }
+
+    fn reward(&self) -> f32 {
+        self.reward
+    }
+
+    fn done(&self) -> bool {
+        self.index >= self.cases.len()
+    }
+}
+
+
+// cart-pole with the classic constants and a continuous force, +1 for every step the pole stays up
+#[derive(Clone, Debug)]
+pub struct PoleBalancing {
+    pub max_steps: usize,
+    x: f32,
+    x_dot: f32,
+    theta: f32,
+    theta_dot: f32,
+    steps: usize,
+    reward: f32,
+}
+
+impl Default for PoleBalancing {
+    fn default() -> Self {
+        Self {
+            max_steps: 500,
+            x: 0.0,
+            x_dot: 0.0,
+            theta: 0.0,
+            theta_dot: 0.0,
+            steps: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+impl PoleBalancing {
+    const GRAVITY: f32 = 9.8;
+    const CART_MASS: f32 = 1.0;
+    const POLE_MASS: f32 = 0.1;
+    // half the pole's length
+    const POLE_LENGTH: f32 = 0.5;
+    const FORCE: f32 = 10.0;
+    const TAU: f32 = 0.02;
+    const X_LIMIT: f32 = 2.4;
+    const THETA_LIMIT: f32 = 12.0 * PI / 180.0;
+
+    fn failed(&self) -> bool {
+        self.x.abs() > Self::X_LIMIT || self.theta.abs() > Self::THETA_LIMIT
+    }
+}
+
+impl Environment for PoleBalancing {
+    fn name(&self) -> &'static str {
+        "pole balancing"
+    }
+
+    fn observation_size(&self) -> usize {
+        4
+    }
+
+    fn action_size(&self) -> usize {
+        1
+    }
+
+    fn reset(&mut self, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        *self = Self {
+            max_steps: self.max_steps,
+            x: rng.gen_range(-0.05..0.05),
+            x_dot: rng.gen_range(-0.05..0.05),
+            theta: rng.gen_range(-0.05..0.05),
+            theta_dot: rng.gen_range(-0.05..0.05),
+            ..Self::default()
+        };
+    }
+
+    fn observe(&self) -> Vec<f32> {
+        vec![
+            self.x / Self::X_LIMIT,
+            (self.x_dot / 3.0).clamp(-1.0, 1.0),
+            self.theta / Self::THETA_LIMIT,
+            (self.theta_dot / 3.0).clamp(-1.0, 1.0),
+        ]
+    }
+
+    // euler integration of the cart-pole equations of motion
+    fn act(&mut self, action: &[f32]) {
+        let force = action.first().copied().unwrap_or(0.0).clamp(-1.0, 1.0) * Self::FORCE;
+
+        let total_mass = Self::CART_MASS + Self::POLE_MASS;
+        let pole_moment = Self::POLE_MASS * Self::POLE_LENGTH;
+        let (sin, cos) = self.theta.sin_cos();
+
+        let temp = (force + pole_moment * self.theta_dot.powi(2) * sin) / total_mass;
+        let theta_acc = (Self::GRAVITY * sin - cos * temp)
+            / (Self::POLE_LENGTH * (4.0 / 3.0 - Self::POLE_MASS * cos.powi(2) / total_mass));
+        let x_acc = temp - pole_moment * theta_acc * cos / total_mass;
+
+        self.x += Self::TAU * self.x_dot;
+        self.x_dot += Self::TAU * x_acc;
+        self.theta += Self::TAU * self.theta_dot;
+        self.theta_dot += Self::TAU * theta_acc;
+
+        self.steps += 1;
+        self.reward = if self.failed() { 0.0 } else { 1.0 };
+    }
+
+    fn reward(&self) -> f32 {
+        self.reward
+    }
+
+    fn done(&self) -> bool {
+        self.failed() || self.steps >= self.max_steps
+    }
+}
+
+
+// an underpowered car in a valley has to swing back and forth to reach the flag, -1 per step until it does
+#[derive(Clone, Debug)]
+pub struct MountainCar {
+    pub max_steps: usize,
+    position: f32,
+    velocity: f32,
+    steps: usize,
+    reward: f32,
+}
+
+impl Default for MountainCar {
+    fn default() -> Self {
+        Self {
+            max_steps: 500,
+            position: -0.5,
+            velocity: 0.0,
+            steps: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+impl MountainCar {
+    const MIN_POSITION: f32 = -1.2;
+    const MAX_POSITION: f32 = 0.6;
+    const MAX_VELOCITY: f32 = 0.07;
+    const GOAL: f32 = 0.5;
+    const POWER: f32 = 0.0015;
+
+    fn reached_goal(&self) -> bool {
+        self.position >= Self::GOAL
+    }
+}
+
+impl Environment for MountainCar {
+    fn name(&self) -> &'static str {
+        "mountain car"
+    }
+
+    fn observation_size(&self) -> usize {
+        2
+    }
+
+    fn action_size(&self) -> usize {
+        1
+    }
+
+    fn reset(&mut self, seed: u64) {
+        *self = Self {
+            max_steps: self.max_steps,
+            position: StdRng::seed_from_u64(seed).gen_range(-0.6..-0.4),
+            ..Self::default()
+        };
+    }
+
+    fn observe(&self) -> Vec<f32> {
+        let range = Self::MAX_POSITION - Self::MIN_POSITION;
+
+        vec![
+            (self.position - Self::MIN_POSITION) / range * 2.0 - 1.0,
+            self.velocity / Self::MAX_VELOCITY,
+        ]
+    }
+
+    fn act(&mut self, action: &[f32]) {
+        let force = action.first().copied().unwrap_or(0.0).clamp(-1.0, 1.0);
+
+        self.velocity += force * Self::POWER - 0.0025 * (3.0 * self.position).cos();
+        self.velocity = self.velocity.clamp(-Self::MAX_VELOCITY, Self::MAX_VELOCITY);
+        self.position = (self.position + self.velocity).clamp(Self::MIN_POSITION, Self::MAX_POSITION);
+
+        // the left wall is inelastic
+        if self.position <= Self::MIN_POSITION {
+            self.velocity = self.velocity.max(0.0);
+        }
+
+        self.steps += 1;
+        self.reward = if self.reached_goal() { 0.0 } else { -1.0 };
+    }
+
+    fn reward(&self) -> f32 {
+        self.reward
+    }
+
+    fn done(&self) -> bool {
+        self.reached_goal() || self.steps >= self.max_steps
+    }
+}
+
+
+// an agent moves through a square arena of -1..1 eating food, which respawns elsewhere once eaten
+//   observes its position and the offset to the nearest food, acts with a velocity
+#[derive(Clone, Debug)]
+pub struct Foraging {
+    pub max_steps: usize,
+    pub food_count: usize,
+    pub speed: f32,
+    pub eat_radius: f32,
+    agent: [f32; 2],
+    food: Vec<[f32; 2]>,
+    rng: StdRng,
+    steps: usize,
+    reward: f32,
+}
+
+impl Default for Foraging {
+    fn default() -> Self {
+        Self {
+            max_steps: 500,
+            food_count: 5,
+            speed: 0.04,
+            eat_radius: 0.1,
+            agent: [0.0, 0.0],
+            food: Vec::new(),
+            rng: StdRng::seed_from_u64(0),
+            steps: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+impl Foraging {
+    fn random_location(&mut self) -> [f32; 2] {
+        [self.rng.gen_range(-1.0..1.0), self.rng.gen_range(-1.0..1.0)]
+    }
+
+    fn nearest_food(&self) -> Option<usize> {
+        let distance = |food: &[f32; 2]| (food[0] - self.agent[0]).powi(2) + (food[1] - self.agent[1]).powi(2);
+
+        (0..self.food.len()).min_by(|&a, &b| distance(&self.food[a]).total_cmp(&distance(&self.food[b])))
+    }
+}
+
+impl Environment for Foraging {
+    fn name(&self) -> &'static str {
+        "foraging"
+    }
+
+    fn observation_size(&self) -> usize {
+        4
+    }
+
+    fn action_size(&self) -> usize {
+        2
+    }
+
+    fn reset(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+        self.agent = [0.0, 0.0];
+        self.food = (0..self.food_count).map(|_| self.random_location()).collect();
+        self.steps = 0;
+        self.reward = 0.0;
+    }
+
+    fn observe(&self) -> Vec<f32> {
+        let offset = self.nearest_food()
+            .map(|i| [self.food[i][0] - self.agent[0], self.food[i][1] - self.agent[1]])
+            .unwrap_or([0.0, 0.0]);
+
+        vec![
+            self.agent[0],
+            self.agent[1],
+            (offset[0] * 0.5).clamp(-1.0, 1.0),
+            (offset[1] * 0.5).clamp(-1.0, 1.0),
+        ]
+    }
+
+    fn act(&mut self, action: &[f32]) {
+        for (axis, position) in self.agent.iter_mut().enumerate() {
+            let velocity = action.get(axis).copied().unwrap_or(0.0).clamp(-1.0, 1.0);
+            *position = (*position + velocity * self.speed).clamp(-1.0, 1.0);
+        }
+
+        self.reward = 0.0;
+        if let Some(nearest) = self.nearest_food() {
+            let food = self.food[nearest];
+            let distance = ((food[0] - self.agent[0]).powi(2) + (food[1] - self.agent[1]).powi(2)).sqrt();
+
+            if distance < self.eat_radius {
+                self.food[nearest] = self.random_location();
+                self.reward = 1.0;
+            }
+        }
+
+        self.steps += 1;
+    }
+
+    fn reward(&self) -> f32 {
+        self.reward
+    }
+
+    fn done(&self) -> bool {
+        self.steps >= self.max_steps
+    }
+}
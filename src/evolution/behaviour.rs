@@ -0,0 +1,387 @@
+use std::f32::consts::TAU;
+
+use bevy::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    automata::AutomataField,
+    criticality::Criticality,
+    readback::FieldSnapshot,
+};
+
+
+// the field is block averaged down to this many cells per side before its spectrum is taken
+pub const SPECTRUM_RESOLUTION: usize = 32;
+// radial frequency bands of the spatial spectrum, dc excluded
+pub const SPECTRUM_BANDS: usize = 8;
+
+
+// records the behaviour of the running field from node snapshots, e.g. with `Readback::every(1)`
+#[derive(Default)]
+pub struct BehaviourPlugin;
+
+impl Plugin for BehaviourPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FieldSnapshot>();
+        app.init_resource::<BehaviourRecorder>();
+
+        app.add_systems(Update, record_behaviour);
+    }
+}
+
+fn record_behaviour(
+    mut recorder: ResMut<BehaviourRecorder>,
+    mut snapshots: EventReader<FieldSnapshot>,
+    automata: Option<Res<AutomataField>>,
+) {
+    let Some(automata) = automata else {
+        return;
+    };
+
+    // snapshots of a replaced field may still be in flight
+    let current = snapshots
+        .iter()
+        .filter(|snapshot| snapshot.field == automata.nodes && !snapshot.nodes.is_empty());
+
+    for snapshot in current {
+        recorder.observe(snapshot.step, snapshot.width, snapshot.height, &snapshot.values());
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Descriptor {
+    // mean absolute node value
+    #[default]
+    MeanActivity,
+    // standard deviation of the per step mean activity
+    ActivityVariability,
+    // power weighted mean band of the spatial spectrum, 0 for the coarsest band and 1 for the finest
+    SpectralCentroid,
+    AvalancheExponent,
+    BranchingRatio,
+}
+
+impl Descriptor {
+    pub const ALL: [Descriptor; 5] = [
+        Descriptor::MeanActivity,
+        Descriptor::ActivityVariability,
+        Descriptor::SpectralCentroid,
+        Descriptor::AvalancheExponent,
+        Descriptor::BranchingRatio,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Descriptor::MeanActivity => "mean activity",
+            Descriptor::ActivityVariability => "activity variability",
+            Descriptor::SpectralCentroid => "spectral centroid",
+            Descriptor::AvalancheExponent => "avalanche exponent",
+            Descriptor::BranchingRatio => "branching ratio",
+        }
+    }
+
+    // typical range, used for map-elites axes and to weigh the novelty distance
+    pub fn range(&self) -> (f32, f32) {
+        match self {
+            Descriptor::MeanActivity => (0.0, 1.0),
+            Descriptor::ActivityVariability => (0.0, 0.5),
+            Descriptor::SpectralCentroid => (0.0, 1.0),
+            Descriptor::AvalancheExponent => (1.0, 4.0),
+            Descriptor::BranchingRatio => (0.0, 2.0),
+        }
+    }
+
+    // avalanches are only seen in snapshots of consecutive steps, see `Criticality`
+    pub fn needs_every_step(&self) -> bool {
+        matches!(self, Descriptor::AvalancheExponent | Descriptor::BranchingRatio)
+    }
+}
+
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Behaviour {
+    pub mean_activity: f32,
+    pub activity_variability: f32,
+    // fraction of the spectral power in each radial band, coarse to fine
+    pub spectrum: Vec<f32>,
+    // None without enough avalanches to fit
+    pub avalanche_exponent: Option<f32>,
+    pub branching_ratio: Option<f32>,
+}
+
+impl Behaviour {
+    pub fn descriptor(&self, descriptor: Descriptor) -> Option<f32> {
+        match descriptor {
+            Descriptor::MeanActivity => Some(self.mean_activity),
+            Descriptor::ActivityVariability => Some(self.activity_variability),
+            Descriptor::SpectralCentroid => self.spectral_centroid(),
+            Descriptor::AvalancheExponent => self.avalanche_exponent,
+            Descriptor::BranchingRatio => self.branching_ratio,
+        }
+    }
+
+    pub fn spectral_centroid(&self) -> Option<f32> {
+        let total: f32 = self.spectrum.iter().sum();
+        if total <= 0.0 || self.spectrum.len() < 2 {
+            return None;
+        }
+
+        let centroid: f32 = self.spectrum
+            .iter()
+            .enumerate()
+            .map(|(band, power)| band as f32 * power)
+            .sum();
+
+        Some(centroid / total / (self.spectrum.len() - 1) as f32)
+    }
+
+    // point in behaviour space for novelty search, every component scaled to about 0..1
+    //   missing descriptors sit at the bottom of their range
+    pub fn vector(&self) -> Vec<f32> {
+        let scaled = |descriptor: Descriptor| {
+            let (min, max) = descriptor.range();
+            (self.descriptor(descriptor).unwrap_or(min) - min) / (max - min)
+        };
+
+        [
+            scaled(Descriptor::MeanActivity),
+            scaled(Descriptor::ActivityVariability),
+            scaled(Descriptor::AvalancheExponent),
+            scaled(Descriptor::BranchingRatio),
+        ]
+        .into_iter()
+        .chain(self.spectrum.iter().copied())
+        .collect()
+    }
+
+    pub fn distance(&self, other: &Behaviour) -> f32 {
+        behaviour_distance(&self.vector(), &other.vector())
+    }
+}
+
+pub fn behaviour_distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b).powi(2))
+        .sum::<f32>()
+        .sqrt()
+}
+
+
+// accumulates the behaviour of one run of the field, reset between evaluations
+//   criticality looks at every observed step, activity and spectrum only at multiples of `sample_interval`
+#[derive(Resource, Clone, Debug)]
+pub struct BehaviourRecorder {
+    pub enabled: bool,
+    pub sample_interval: u64,
+    pub criticality: Criticality,
+    steps: u64,
+    activity_sum: f64,
+    activity_square_sum: f64,
+    spectrum_sum: Vec<f64>,
+    spectra: u64,
+}
+
+impl Default for BehaviourRecorder {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_interval: 1,
+            criticality: Criticality::default(),
+            steps: 0,
+            activity_sum: 0.0,
+            activity_square_sum: 0.0,
+            spectrum_sum: vec![0.0; SPECTRUM_BANDS],
+            spectra: 0,
+        }
+    }
+}
+
+impl BehaviourRecorder {
+    pub fn reset(&mut self) {
+        let mut criticality = self.criticality.clone();
+        criticality.reset();
+
+        *self = Self {
+            enabled: self.enabled,
+            sample_interval: self.sample_interval,
+            criticality,
+            ..default()
+        };
+    }
+
+    pub fn with_sample_interval(mut self, sample_interval: u64) -> Self {
+        self.sample_interval = sample_interval.max(1);
+        self
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    // row-major node values of a `width` × `height` field
+    pub fn observe(&mut self, step: u64, width: u32, height: u32, values: &[f32]) {
+        if !self.enabled || values.is_empty() {
+            return;
+        }
+
+        self.criticality.observe(step, values);
+
+        if !step.is_multiple_of(self.sample_interval.max(1)) {
+            return;
+        }
+
+        let activity = values.iter().map(|value| value.abs() as f64).sum::<f64>() / values.len() as f64;
+        self.activity_sum += activity;
+        self.activity_square_sum += activity * activity;
+        self.steps += 1;
+
+        let spectrum = radial_spectrum(values, width as usize, height as usize);
+        for (sum, power) in self.spectrum_sum.iter_mut().zip(spectrum) {
+            *sum += power as f64;
+        }
+        self.spectra += 1;
+    }
+
+    pub fn behaviour(&self) -> Behaviour {
+        let steps = self.steps.max(1) as f64;
+        let mean = self.activity_sum / steps;
+        let variance = (self.activity_square_sum / steps - mean * mean).max(0.0);
+
+        let total: f64 = self.spectrum_sum.iter().sum();
+        let spectrum = self.spectrum_sum
+            .iter()
+            .map(|&power| if total > 0.0 { (power / total) as f32 } else { 0.0 })
+            .collect();
+
+        let report = self.criticality.report();
+
+        Behaviour {
+            mean_activity: mean as f32,
+            activity_variability: variance.sqrt() as f32,
+            spectrum,
+            avalanche_exponent: report.size_fit.map(|fit| fit.exponent),
+            branching_ratio: report.branching_ratio,
+        }
+    }
+}
+
+
+// power of the block averaged field per radial frequency band, summing to 1 unless the field is flat
+//   structure finer than a block is averaged away
+pub fn radial_spectrum(values: &[f32], width: usize, height: usize) -> Vec<f32> {
+    const N: usize = SPECTRUM_RESOLUTION;
+
+    let mut bands = vec![0.0_f32; SPECTRUM_BANDS];
+    if width == 0 || height == 0 || values.len() < width * height {
+        return bands;
+    }
+
+    let mut grid = vec![0.0_f32; N * N];
+    let mut counts = vec![0_u32; N * N];
+    for y in 0..height {
+        for x in 0..width {
+            let cell = (y * N / height) * N + x * N / width;
+            grid[cell] += values[y * width + x];
+            counts[cell] += 1;
+        }
+    }
+    for (value, &count) in grid.iter_mut().zip(&counts) {
+        *value /= count.max(1) as f32;
+    }
+
+    let twiddles: Vec<(f32, f32)> = (0..N)
+        .map(|k| (TAU * k as f32 / N as f32).sin_cos())
+        .collect();
+
+    // separable dft, rows then columns
+    let mut rows = vec![(0.0_f32, 0.0_f32); N * N];
+    for y in 0..N {
+        for u in 0..N {
+            let (mut re, mut im) = (0.0, 0.0);
+            for x in 0..N {
+                let (sin, cos) = twiddles[(u * x) % N];
+                re += grid[y * N + x] * cos;
+                im -= grid[y * N + x] * sin;
+            }
+            rows[y * N + u] = (re, im);
+        }
+    }
+
+    let max_radius = (N / 2) as f32 * std::f32::consts::SQRT_2;
+    for v in 0..N {
+        for u in 0..N {
+            if u == 0 && v == 0 {
+                continue;
+            }
+
+            let (mut re, mut im) = (0.0, 0.0);
+            for y in 0..N {
+                let (sin, cos) = twiddles[(v * y) % N];
+                let (row_re, row_im) = rows[y * N + u];
+                re += row_re * cos + row_im * sin;
+                im += row_im * cos - row_re * sin;
+            }
+
+            // frequencies past nyquist are the negative ones
+            let fu = u.min(N - u) as f32;
+            let fv = v.min(N - v) as f32;
+            let band = ((fu.hypot(fv) / max_radius) * SPECTRUM_BANDS as f32) as usize;
+            bands[band.min(SPECTRUM_BANDS - 1)] += re * re + im * im;
+        }
+    }
+
+    let total: f32 = bands.iter().sum();
+    if total > 0.0 {
+        for band in &mut bands {
+            *band /= total;
+        }
+    }
+
+    bands
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 2x2 field where one node fires every other step
+    fn values(step: u64) -> [f32; 4] {
+        let firing = if step.is_multiple_of(2) { 0.9 } else { 0.1 };
+        [firing, 0.1, 0.2, 0.3]
+    }
+
+    #[test]
+    fn sparse_snapshots_have_no_criticality() {
+        let mut recorder = BehaviourRecorder::default().with_sample_interval(5);
+        for step in (5..=200).step_by(5) {
+            recorder.observe(step, 2, 2, &values(step));
+        }
+
+        let behaviour = recorder.behaviour();
+        assert_eq!(recorder.steps(), 40);
+        assert_eq!(behaviour.branching_ratio, None);
+        assert_eq!(behaviour.avalanche_exponent, None);
+        assert_eq!(recorder.criticality.avalanches.len(), 0);
+    }
+
+    #[test]
+    fn every_step_snapshots_are_subsampled_for_activity() {
+        let mut recorder = BehaviourRecorder::default().with_sample_interval(5);
+        for step in 1..=200 {
+            recorder.observe(step, 2, 2, &values(step));
+        }
+
+        let behaviour = recorder.behaviour();
+        assert_eq!(recorder.steps(), 40);
+        assert_eq!(recorder.criticality.steps(), 199);
+        // single step avalanches with nothing firing right after them
+        assert_eq!(behaviour.branching_ratio, Some(0.0));
+        assert_eq!(recorder.criticality.report().avalanche_count, 100);
+    }
+}
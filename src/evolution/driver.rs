@@ -0,0 +1,407 @@
+use bevy::prelude::*;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    automata::{
+        AutomataField,
+        FieldStep,
+    },
+    environment::{
+        EnvironmentKind,
+        EnvironmentPlugin,
+        EpisodeConfig,
+        EpisodeRunner,
+    },
+    hyperneat::{
+        cppn_population,
+        CppnProgram,
+        HyperNeatField,
+    },
+    readback::{
+        FieldSnapshot,
+        Readback,
+        ReadbackPlugin,
+    },
+};
+
+use super::{
+    Behaviour,
+    BehaviourRecorder,
+    Descriptor,
+    DescriptorAxis,
+    EvolutionConfig,
+    GenerationStats,
+    Genome,
+    MapElites,
+    NoveltyArchive,
+    NoveltyConfig,
+    Population,
+    RunHistory,
+};
+
+
+// evaluates an `EvolutionDriver` population one cppn at a time on the field, add after the `HyperNeatPlugin`
+#[derive(Default)]
+pub struct EvolutionPlugin;
+
+impl Plugin for EvolutionPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ReadbackPlugin>() {
+            app.add_plugins(ReadbackPlugin);
+        }
+        if !app.is_plugin_added::<EnvironmentPlugin>() {
+            app.add_plugins(EnvironmentPlugin);
+        }
+
+        app.add_event::<GenerationFinished>();
+        app.add_event::<EvolutionFinished>();
+
+        app.add_systems(Update, drive_evolution);
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SearchMode {
+    // selection by the objective
+    #[default]
+    Objective,
+    // selection by novelty of the behaviour, the objective is still recorded
+    Novelty,
+    // offspring of random map-elites elites
+    MapElites,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Objective {
+    // mean episode return
+    Environment(EnvironmentKind),
+    // maximizes a behaviour descriptor, genomes without it are the least fit
+    Descriptor(Descriptor),
+}
+
+impl Default for Objective {
+    fn default() -> Self {
+        Objective::Descriptor(Descriptor::ActivityVariability)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EvolutionSettings {
+    pub mode: SearchMode,
+    pub objective: Objective,
+    pub evolution: EvolutionConfig,
+    pub novelty: NoveltyConfig,
+    pub map_x: DescriptorAxis,
+    pub map_y: DescriptorAxis,
+    // steps the behaviour of each genome is recorded for
+    pub steps: u64,
+    // steps between the activity and spectrum samples, criticality descriptors read back every step
+    pub sample_interval: u64,
+    // stops after this many evaluated generations
+    pub generations: Option<u64>,
+    pub seed: u64,
+}
+
+impl EvolutionSettings {
+    // whether selection or the map looks at a descriptor that needs every step, novelty uses all of them
+    pub fn needs_every_step(&self) -> bool {
+        let objective = matches!(self.objective, Objective::Descriptor(descriptor) if descriptor.needs_every_step());
+        let map = self.mode == SearchMode::MapElites
+            && (self.map_x.descriptor.needs_every_step() || self.map_y.descriptor.needs_every_step());
+
+        objective || map || self.mode == SearchMode::Novelty
+    }
+
+    pub fn readback_interval(&self) -> u64 {
+        match self.needs_every_step() {
+            true => 1,
+            false => self.sample_interval.max(1),
+        }
+    }
+}
+
+impl Default for EvolutionSettings {
+    fn default() -> Self {
+        Self {
+            mode: SearchMode::default(),
+            objective: Objective::default(),
+            evolution: EvolutionConfig::default(),
+            novelty: NoveltyConfig::default(),
+            map_x: DescriptorAxis::new(Descriptor::MeanActivity, 16),
+            map_y: DescriptorAxis::new(Descriptor::SpectralCentroid, 16),
+            steps: 200,
+            sample_interval: 5,
+            generations: None,
+            seed: 0,
+        }
+    }
+}
+
+
+#[derive(Event, Clone, Debug)]
+pub struct GenerationFinished {
+    pub stats: GenerationStats,
+    pub archive: usize,
+    pub coverage: f32,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct EvolutionFinished {
+    pub generations: u64,
+}
+
+
+// a neat run over `HyperNeatField` cppns, the field is restarted for every genome
+#[derive(Resource, Clone, Debug)]
+pub struct EvolutionDriver {
+    pub settings: EvolutionSettings,
+    pub population: Population,
+    pub archive: NoveltyArchive,
+    pub map: MapElites<Genome>,
+    pub history: RunHistory,
+    behaviours: Vec<Option<Behaviour>>,
+    current: Option<GenomeEvaluation>,
+    finished: bool,
+}
+
+#[derive(Clone, Debug)]
+struct GenomeEvaluation {
+    index: usize,
+    previous_field: Handle<Image>,
+    field: Option<Handle<Image>>,
+    recorder: BehaviourRecorder,
+    recorded: bool,
+    episode_started: bool,
+}
+
+impl EvolutionDriver {
+    pub fn new(settings: EvolutionSettings) -> Self {
+        let population = cppn_population(settings.evolution.clone(), settings.seed);
+        Self::resume(settings, population, RunHistory::default())
+    }
+
+    // continues a saved population
+    pub fn resume(settings: EvolutionSettings, population: Population, history: RunHistory) -> Self {
+        Self {
+            archive: NoveltyArchive::new(settings.novelty.clone()),
+            map: MapElites::new(settings.map_x, settings.map_y),
+            behaviours: vec![None; population.len()],
+            population,
+            history,
+            settings,
+            current: None,
+            finished: false,
+        }
+    }
+
+    // keeps the novelty archive of a resumed run
+    pub fn with_archive(mut self, archive: NoveltyArchive) -> Self {
+        self.archive = archive;
+        self
+    }
+
+    // keeps the elites of a resumed run, a map over other descriptors starts empty
+    pub fn with_map(mut self, map: MapElites<Genome>) -> Self {
+        if map.x == self.map.x && map.y == self.map.y {
+            self.map = map;
+        } else {
+            warn!("saved map-elites axes differ from the settings, starting an empty map");
+        }
+        self
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    // genomes evaluated in the current generation
+    pub fn progress(&self) -> (usize, usize) {
+        let evaluated = self.population.fitness.iter().filter(|fitness| fitness.is_some()).count();
+        (evaluated, self.population.len())
+    }
+
+    fn finish_generation(&mut self) -> GenerationFinished {
+        let behaviours: Vec<Behaviour> = std::mem::take(&mut self.behaviours)
+            .into_iter()
+            .map(Option::unwrap_or_default)
+            .collect();
+
+        for ((genome, fitness), behaviour) in self.population.genomes.iter().zip(&self.population.fitness).zip(&behaviours) {
+            let fitness = fitness.filter(|fitness| fitness.is_finite()).unwrap_or(f32::MIN);
+            self.map.insert(genome.clone(), fitness, behaviour.clone());
+        }
+
+        let stats = self.history.record(&self.population).clone();
+
+        if self.settings.mode == SearchMode::Novelty {
+            let vectors: Vec<Vec<f32>> = behaviours.iter().map(Behaviour::vector).collect();
+            let scores = self.archive.score_generation(&vectors);
+
+            for (index, score) in scores.into_iter().enumerate() {
+                self.population.set_fitness(index, score);
+            }
+        }
+
+        GenerationFinished {
+            stats,
+            archive: self.archive.len(),
+            coverage: self.map.coverage(),
+        }
+    }
+
+    fn next_generation(&mut self) {
+        match self.settings.mode {
+            SearchMode::MapElites => self.population.evolve_from_elites(&self.map),
+            _ => self.population.evolve(),
+        }
+
+        self.behaviours = vec![None; self.population.len()];
+    }
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn drive_evolution(
+    mut commands: Commands,
+    driver: Option<ResMut<EvolutionDriver>>,
+    hyperneat: Option<ResMut<HyperNeatField>>,
+    automata: Option<Res<AutomataField>>,
+    field_step: Res<FieldStep>,
+    mut readback: ResMut<Readback>,
+    mut runner: ResMut<EpisodeRunner>,
+    mut snapshots: EventReader<FieldSnapshot>,
+    mut generations: EventWriter<GenerationFinished>,
+    mut finished: EventWriter<EvolutionFinished>,
+) {
+    let (Some(mut driver), Some(automata)) = (driver, automata) else {
+        snapshots.clear();
+        return;
+    };
+    let driver = &mut *driver;
+
+    if driver.finished {
+        snapshots.clear();
+        return;
+    }
+
+    let Some(evaluation) = &mut driver.current else {
+        snapshots.clear();
+
+        let Some(index) = driver.population.next_unevaluated() else {
+            // a run saved at its generation limit resumes with its last generation already recorded
+            let recorded = driver.history.last().is_some_and(|stats| stats.generation == driver.population.generation);
+            if recorded {
+                driver.next_generation();
+                return;
+            }
+
+            let generation = driver.finish_generation();
+            info!(
+                "generation {}: best {:?}, mean {:?}, {} species, archive {}, coverage {:.3}",
+                generation.stats.generation,
+                generation.stats.best_fitness,
+                generation.stats.mean_fitness,
+                generation.stats.species,
+                generation.archive,
+                generation.coverage,
+            );
+            generations.send(generation);
+
+            let evaluated = driver.history.len() as u64;
+            if driver.settings.generations.is_some_and(|limit| evaluated >= limit) {
+                driver.finished = true;
+                finished.send(EvolutionFinished {
+                    generations: evaluated,
+                });
+                return;
+            }
+
+            driver.next_generation();
+            return;
+        };
+
+        let genome = driver.population.genomes[index].clone();
+
+        // e.g. more hidden nodes than the shader evaluates, the least fit without a run
+        if let Err(err) = CppnProgram::compile(&genome) {
+            warn!("genome {index} is not evaluated: {err}");
+            driver.behaviours[index] = Some(Behaviour::default());
+            driver.population.set_fitness(index, f32::NAN);
+            return;
+        }
+
+        match hyperneat {
+            Some(mut hyperneat) => hyperneat.genome = genome,
+            None => commands.insert_resource(HyperNeatField::new(genome)),
+        }
+
+        readback.interval = Some(driver.settings.readback_interval());
+        readback.nodes = true;
+        readback.edges = false;
+        readback.uaf = false;
+
+        driver.current = Some(GenomeEvaluation {
+            index,
+            previous_field: automata.nodes.clone(),
+            field: None,
+            recorder: BehaviourRecorder::default().with_sample_interval(driver.settings.sample_interval),
+            recorded: false,
+            episode_started: false,
+        });
+        return;
+    };
+
+    // the substrate restarts with a new field for the genome
+    let field = match &evaluation.field {
+        Some(field) => field.clone(),
+        None if automata.nodes != evaluation.previous_field => {
+            evaluation.field = Some(automata.nodes.clone());
+            automata.nodes.clone()
+        }
+        None => {
+            snapshots.clear();
+            return;
+        }
+    };
+
+    let steps = driver.settings.steps;
+    for snapshot in snapshots.iter().filter(|snapshot| snapshot.field == field && !snapshot.nodes.is_empty()) {
+        if snapshot.step <= steps {
+            evaluation.recorder.observe(snapshot.step, snapshot.width, snapshot.height, &snapshot.values());
+        }
+        evaluation.recorded |= snapshot.step >= steps;
+    }
+
+    if let Objective::Environment(kind) = driver.settings.objective {
+        if !evaluation.episode_started && field_step.0.is_some() {
+            runner.report = None;
+            runner.evaluate_kind(kind, EpisodeConfig::for_field(automata.size()));
+            evaluation.episode_started = true;
+        }
+    }
+
+    if !evaluation.recorded {
+        return;
+    }
+
+    let behaviour = evaluation.recorder.behaviour();
+    let objective = match driver.settings.objective {
+        Objective::Environment(_) => {
+            if runner.is_running() {
+                return;
+            }
+            runner.report.as_ref().map_or(f32::NAN, |report| report.fitness())
+        }
+        Objective::Descriptor(descriptor) => behaviour.descriptor(descriptor).unwrap_or(f32::NAN),
+    };
+
+    let index = evaluation.index;
+    driver.behaviours[index] = Some(behaviour);
+    driver.population.set_fitness(index, objective);
+    driver.current = None;
+}
@@ -0,0 +1,391 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{
+        self,
+        BufReader,
+        BufWriter,
+        Read,
+        Write,
+    },
+    path::Path,
+};
+
+use bincode::Options;
+use flate2::{
+    read::ZlibDecoder,
+    write::ZlibEncoder,
+    Compression,
+};
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+
+use super::{
+    Genome,
+    MapElites,
+    NodeKind,
+    NoveltyArchive,
+    Population,
+    RunHistory,
+};
+
+
+// binary layout: magic, little endian u32 version, zlib compressed bincode
+pub const EVOLUTION_FORMAT_VERSION: u32 = 1;
+// upper bound on the decompressed bincode, files are shared so a corrupt length must not allocate without bound
+pub const MAX_EVOLUTION_FILE_BYTES: u64 = 256 << 20;
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EvolutionFormat {
+    // human readable, for sharing champions and inspecting runs
+    Json,
+    #[default]
+    Binary,
+}
+
+impl EvolutionFormat {
+    // json for a `.json` extension, binary otherwise
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().is_some_and(|extension| extension.eq_ignore_ascii_case("json")) {
+            true => EvolutionFormat::Json,
+            false => EvolutionFormat::Binary,
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub enum EvolutionFileError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Encoding(bincode::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for EvolutionFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EvolutionFileError::Io(err) => write!(f, "evolution file io error: {err}"),
+            EvolutionFileError::Json(err) => write!(f, "evolution file json error: {err}"),
+            EvolutionFileError::Encoding(err) => write!(f, "evolution file encoding error: {err}"),
+            EvolutionFileError::InvalidMagic => write!(f, "not an evolution file of this kind"),
+            EvolutionFileError::UnsupportedVersion(version) => write!(f, "unsupported evolution file version {version}, expected {EVOLUTION_FORMAT_VERSION}"),
+            EvolutionFileError::Invalid(reason) => write!(f, "invalid evolution file: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for EvolutionFileError {}
+
+impl From<io::Error> for EvolutionFileError {
+    fn from(err: io::Error) -> Self {
+        EvolutionFileError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for EvolutionFileError {
+    fn from(err: serde_json::Error) -> Self {
+        EvolutionFileError::Json(err)
+    }
+}
+
+impl From<bincode::Error> for EvolutionFileError {
+    fn from(err: bincode::Error) -> Self {
+        EvolutionFileError::Encoding(err)
+    }
+}
+
+
+// genomes, populations and run histories saved as json or compact binary
+pub trait EvolutionFile: Serialize + DeserializeOwned {
+    // tells the binary files apart
+    const MAGIC: [u8; 4];
+
+    // checks invariants the rest of the crate relies on after loading
+    fn validate(&self) -> Result<(), EvolutionFileError> {
+        Ok(())
+    }
+
+    fn write(&self, mut writer: impl Write, format: EvolutionFormat) -> Result<(), EvolutionFileError> {
+        match format {
+            EvolutionFormat::Json => {
+                serde_json::to_writer_pretty(&mut writer, self)?;
+                writer.flush()?;
+            }
+            EvolutionFormat::Binary => {
+                writer.write_all(&Self::MAGIC)?;
+                writer.write_all(&EVOLUTION_FORMAT_VERSION.to_le_bytes())?;
+
+                let mut encoder = ZlibEncoder::new(writer, Compression::default());
+                bincode::serialize_into(&mut encoder, self)?;
+                encoder.finish()?.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read(mut reader: impl Read, format: EvolutionFormat) -> Result<Self, EvolutionFileError> {
+        let value: Self = match format {
+            EvolutionFormat::Json => serde_json::from_reader(reader)?,
+            EvolutionFormat::Binary => {
+                let mut magic = [0; 4];
+                reader.read_exact(&mut magic)?;
+                if magic != Self::MAGIC {
+                    return Err(EvolutionFileError::InvalidMagic);
+                }
+
+                let mut version = [0; 4];
+                reader.read_exact(&mut version)?;
+                let version = u32::from_le_bytes(version);
+                if version != EVOLUTION_FORMAT_VERSION {
+                    return Err(EvolutionFileError::UnsupportedVersion(version));
+                }
+
+                // same fixint encoding as `bincode::serialize_into`
+                bincode::DefaultOptions::new()
+                    .with_fixint_encoding()
+                    .allow_trailing_bytes()
+                    .with_limit(MAX_EVOLUTION_FILE_BYTES)
+                    .deserialize_from(ZlibDecoder::new(reader))?
+            }
+        };
+
+        value.validate()?;
+        Ok(value)
+    }
+
+    fn to_json(&self) -> Result<String, EvolutionFileError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    fn from_json(text: &str) -> Result<Self, EvolutionFileError> {
+        let value: Self = serde_json::from_str(text)?;
+        value.validate()?;
+        Ok(value)
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>, EvolutionFileError> {
+        let mut bytes = Vec::new();
+        self.write(&mut bytes, EvolutionFormat::Binary)?;
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, EvolutionFileError> {
+        Self::read(bytes, EvolutionFormat::Binary)
+    }
+
+    // the format follows the extension, see `EvolutionFormat::from_path`
+    fn save(&self, path: impl AsRef<Path>) -> Result<(), EvolutionFileError> {
+        let format = EvolutionFormat::from_path(&path);
+        self.write(BufWriter::new(File::create(path)?), format)
+    }
+
+    fn load(path: impl AsRef<Path>) -> Result<Self, EvolutionFileError> {
+        let format = EvolutionFormat::from_path(&path);
+        Self::read(BufReader::new(File::open(path)?), format)
+    }
+}
+
+
+impl EvolutionFile for Genome {
+    const MAGIC: [u8; 4] = *b"RAGN";
+
+    fn validate(&self) -> Result<(), EvolutionFileError> {
+        let ids_sorted = self.nodes.windows(2).all(|pair| pair[0].id < pair[1].id);
+        let innovations_sorted = self.connections.windows(2).all(|pair| pair[0].innovation < pair[1].innovation);
+        if !ids_sorted || !innovations_sorted {
+            return Err(EvolutionFileError::Invalid("genes are not sorted"));
+        }
+
+        // `activate` and `CppnProgram::compile` expect ids 0.. to be the inputs followed by the outputs
+        let io = self.inputs + self.outputs;
+        let layout = self.nodes.len() >= io
+            && self.nodes.iter().enumerate().all(|(index, node)| {
+                let kind = match index {
+                    index if index < self.inputs => NodeKind::Input,
+                    index if index < io => NodeKind::Output,
+                    _ => NodeKind::Hidden,
+                };
+                node.kind == kind && (index >= io || node.id == index as u32)
+            });
+        if !layout {
+            return Err(EvolutionFileError::Invalid("input and output nodes are not the first node ids"));
+        }
+
+        let known = |id| self.node(id).is_some();
+        if !self.connections.iter().all(|connection| known(connection.from) && known(connection.to)) {
+            return Err(EvolutionFileError::Invalid("connection to a missing node"));
+        }
+
+        if self.hidden_order().is_none() {
+            return Err(EvolutionFileError::Invalid("genome has a cycle"));
+        }
+
+        Ok(())
+    }
+}
+
+impl EvolutionFile for Population {
+    const MAGIC: [u8; 4] = *b"RAPO";
+
+    fn validate(&self) -> Result<(), EvolutionFileError> {
+        if self.fitness.len() != self.genomes.len() {
+            return Err(EvolutionFileError::Invalid("fitness and genome counts differ"));
+        }
+
+        let members_valid = self.species
+            .iter()
+            .flat_map(|species| &species.members)
+            .all(|&member| member < self.genomes.len());
+        if !members_valid {
+            return Err(EvolutionFileError::Invalid("species member out of range"));
+        }
+
+        self.genomes.iter().try_for_each(Genome::validate)
+    }
+}
+
+impl EvolutionFile for RunHistory {
+    const MAGIC: [u8; 4] = *b"RAHI";
+
+    fn validate(&self) -> Result<(), EvolutionFileError> {
+        match &self.champion {
+            Some(champion) => champion.genome.validate(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl EvolutionFile for NoveltyArchive {
+    const MAGIC: [u8; 4] = *b"RANA";
+
+    fn validate(&self) -> Result<(), EvolutionFileError> {
+        if !self.behaviours.windows(2).all(|pair| pair[0].len() == pair[1].len()) {
+            return Err(EvolutionFileError::Invalid("archived behaviours differ in length"));
+        }
+
+        Ok(())
+    }
+}
+
+impl EvolutionFile for MapElites<Genome> {
+    const MAGIC: [u8; 4] = *b"RAME";
+
+    fn validate(&self) -> Result<(), EvolutionFileError> {
+        if self.x.bins.checked_mul(self.y.bins) != Some(self.cells.len()) {
+            return Err(EvolutionFileError::Invalid("cell count does not match the axes"));
+        }
+
+        self.elites().try_for_each(|elite| elite.solution.validate())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evolution::EvolutionConfig;
+
+    fn population() -> Population {
+        let config = EvolutionConfig {
+            population_size: 8,
+            ..EvolutionConfig::default()
+        };
+        let mut population = Population::new(config, 3, 2, 11);
+        for i in 0..population.len() {
+            population.set_fitness(i, i as f32 * 0.5);
+        }
+        population
+    }
+
+    #[test]
+    fn genome_round_trips_through_json_and_binary() {
+        let genome = population().genomes[3].clone();
+
+        assert_eq!(Genome::from_json(&genome.to_json().unwrap()).unwrap(), genome);
+        assert_eq!(Genome::from_bytes(&genome.to_bytes().unwrap()).unwrap(), genome);
+    }
+
+    #[test]
+    fn population_resumes_identically() {
+        let mut original = population();
+        let mut restored = Population::from_bytes(&original.to_bytes().unwrap()).unwrap();
+
+        assert_eq!(restored.genomes, original.genomes);
+        assert_eq!(restored.fitness, original.fitness);
+
+        // the rng state is saved, so the next generation matches
+        original.evolve();
+        restored.evolve();
+        assert_eq!(restored.genomes, original.genomes);
+
+        let json = Population::from_json(&original.to_json().unwrap()).unwrap();
+        assert_eq!(json.genomes, original.genomes);
+    }
+
+    #[test]
+    fn history_round_trips() {
+        let mut history = RunHistory::default();
+        history.record(&population());
+
+        assert_eq!(RunHistory::from_bytes(&history.to_bytes().unwrap()).unwrap(), history);
+        assert_eq!(RunHistory::from_json(&history.to_json().unwrap()).unwrap(), history);
+    }
+
+    #[test]
+    fn archive_and_map_round_trip() {
+        use crate::evolution::{
+            Behaviour,
+            Descriptor,
+            DescriptorAxis,
+            NoveltyConfig,
+        };
+
+        let mut archive = NoveltyArchive::new(NoveltyConfig::default());
+        archive.behaviours = vec![vec![0.1, 0.2], vec![0.3, 0.4]];
+        assert_eq!(NoveltyArchive::from_bytes(&archive.to_bytes().unwrap()).unwrap(), archive);
+
+        let axis = DescriptorAxis::new(Descriptor::MeanActivity, 4);
+        let mut map = MapElites::new(axis, axis);
+        let behaviour = Behaviour {
+            mean_activity: axis.center(1),
+            ..Behaviour::default()
+        };
+        assert!(map.insert(population().genomes[0].clone(), 1.0, behaviour));
+        assert_eq!(MapElites::<Genome>::from_json(&map.to_json().unwrap()).unwrap(), map);
+
+        map.cells.pop();
+        assert!(matches!(MapElites::<Genome>::from_bytes(&map.to_bytes().unwrap()), Err(EvolutionFileError::Invalid(_))));
+    }
+
+    #[test]
+    fn rejects_other_kinds_and_versions() {
+        let genome = population().genomes[0].clone();
+        let bytes = genome.to_bytes().unwrap();
+
+        assert!(matches!(Population::from_bytes(&bytes), Err(EvolutionFileError::InvalidMagic)));
+
+        let mut newer = bytes.clone();
+        newer[4..8].copy_from_slice(&(EVOLUTION_FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(Genome::from_bytes(&newer), Err(EvolutionFileError::UnsupportedVersion(_))));
+
+        assert!(Genome::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+    }
+
+    #[test]
+    fn rejects_genomes_with_misplaced_inputs_or_outputs() {
+        let mut genome = population().genomes[0].clone();
+        genome.nodes[0].kind = NodeKind::Output;
+        assert!(matches!(Genome::from_json(&genome.to_json().unwrap()), Err(EvolutionFileError::Invalid(_))));
+
+        let mut genome = population().genomes[0].clone();
+        genome.outputs += 1;
+        assert!(matches!(Genome::from_bytes(&genome.to_bytes().unwrap()), Err(EvolutionFileError::Invalid(_))));
+    }
+}
@@ -0,0 +1,639 @@
+use std::collections::HashMap;
+
+use rand::{
+    seq::SliceRandom,
+    Rng,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::uaf::{
+    Uaf,
+    UafPreset,
+};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NodeKind {
+    Input,
+    Hidden,
+    Output,
+}
+
+// every non-input node activates with its own uaf, inputs pass their value through
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeGene {
+    pub id: u32,
+    pub kind: NodeKind,
+    pub uaf: Uaf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionGene {
+    pub innovation: u64,
+    pub from: u32,
+    pub to: u32,
+    pub weight: f32,
+    pub enabled: bool,
+}
+
+
+// hands out historical markings, the same structural mutation within a generation gets the same marking
+//   node ids below `inputs + outputs` belong to the input and output nodes
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Innovations {
+    next_innovation: u64,
+    next_node: u32,
+    // cleared by `next_generation` before any offspring is made, so they need not be saved
+    #[serde(skip)]
+    connections: HashMap<(u32, u32), u64>,
+    #[serde(skip)]
+    splits: HashMap<u64, u32>,
+}
+
+impl Innovations {
+    pub fn new(inputs: usize, outputs: usize) -> Self {
+        Self {
+            next_node: (inputs + outputs) as u32,
+            ..Self::default()
+        }
+    }
+
+    pub fn next_innovation(&self) -> u64 {
+        self.next_innovation
+    }
+
+    pub fn next_node(&self) -> u32 {
+        self.next_node
+    }
+
+    fn connection(&mut self, from: u32, to: u32) -> u64 {
+        *self.connections.entry((from, to)).or_insert_with(|| {
+            self.next_innovation += 1;
+            self.next_innovation - 1
+        })
+    }
+
+    // id of the node splitting the connection
+    fn split(&mut self, innovation: u64) -> u32 {
+        *self.splits.entry(innovation).or_insert_with(|| {
+            self.next_node += 1;
+            self.next_node - 1
+        })
+    }
+
+    // markings are only shared within a generation
+    pub fn next_generation(&mut self) {
+        self.connections.clear();
+        self.splits.clear();
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MutationConfig {
+    pub weight_rate: f32,
+    pub weight_power: f32,
+    // chance a mutated weight is drawn anew instead of perturbed
+    pub weight_replace_rate: f32,
+    pub weight_range: f32,
+    pub uaf_rate: f32,
+    pub uaf_power: f32,
+    // chance a mutated uaf jumps to a random preset
+    pub uaf_preset_rate: f32,
+    pub add_connection_rate: f32,
+    pub add_node_rate: f32,
+    pub toggle_rate: f32,
+}
+
+impl Default for MutationConfig {
+    fn default() -> Self {
+        Self {
+            weight_rate: 0.8,
+            weight_power: 0.5,
+            weight_replace_rate: 0.1,
+            weight_range: 4.0,
+            uaf_rate: 0.2,
+            uaf_power: 0.2,
+            uaf_preset_rate: 0.05,
+            add_connection_rate: 0.1,
+            add_node_rate: 0.05,
+            toggle_rate: 0.01,
+        }
+    }
+}
+
+
+// compatibility distance coefficients
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DistanceConfig {
+    pub disjoint: f32,
+    pub weight: f32,
+    pub uaf: f32,
+}
+
+impl Default for DistanceConfig {
+    fn default() -> Self {
+        Self {
+            disjoint: 1.0,
+            weight: 0.4,
+            uaf: 0.2,
+        }
+    }
+}
+
+
+// a feed-forward neat genome, output nodes never feed other nodes
+//   connections are only added where they close no cycle, even through disabled ones, so toggling is always safe
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Genome {
+    pub inputs: usize,
+    pub outputs: usize,
+    // sorted by id, inputs first then outputs
+    pub nodes: Vec<NodeGene>,
+    // sorted by innovation
+    pub connections: Vec<ConnectionGene>,
+}
+
+impl Genome {
+    // every input connected to every output with a random weight
+    pub fn minimal(
+        inputs: usize,
+        outputs: usize,
+        output_uaf: Uaf,
+        innovations: &mut Innovations,
+        config: &MutationConfig,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let nodes = (0..inputs)
+            .map(|i| NodeGene {
+                id: i as u32,
+                kind: NodeKind::Input,
+                uaf: Uaf::IDENTITY,
+            })
+            .chain((0..outputs).map(|i| NodeGene {
+                id: (inputs + i) as u32,
+                kind: NodeKind::Output,
+                uaf: output_uaf,
+            }))
+            .collect();
+
+        let mut connections = Vec::with_capacity(inputs * outputs);
+        for from in 0..inputs as u32 {
+            for to in inputs as u32..(inputs + outputs) as u32 {
+                connections.push(ConnectionGene {
+                    innovation: innovations.connection(from, to),
+                    from,
+                    to,
+                    weight: rng.gen_range(-1.0..1.0) * config.weight_range,
+                    enabled: true,
+                });
+            }
+        }
+        connections.sort_by_key(|connection| connection.innovation);
+
+        Self {
+            inputs,
+            outputs,
+            nodes,
+            connections,
+        }
+    }
+
+    pub fn node(&self, id: u32) -> Option<&NodeGene> {
+        self.nodes
+            .binary_search_by_key(&id, |node| node.id)
+            .ok()
+            .map(|index| &self.nodes[index])
+    }
+
+    pub fn hidden_count(&self) -> usize {
+        self.nodes.iter().filter(|node| node.kind == NodeKind::Hidden).count()
+    }
+
+    pub fn enabled_connections(&self) -> impl Iterator<Item = &ConnectionGene> {
+        self.connections.iter().filter(|connection| connection.enabled)
+    }
+
+    // hidden nodes in an order where every node comes after the nodes feeding it, None on a cycle
+    pub fn hidden_order(&self) -> Option<Vec<u32>> {
+        let hidden: Vec<u32> = self.nodes
+            .iter()
+            .filter(|node| node.kind == NodeKind::Hidden)
+            .map(|node| node.id)
+            .collect();
+
+        let mut pending: HashMap<u32, usize> = hidden.iter().map(|&id| (id, 0)).collect();
+        for connection in self.enabled_connections() {
+            if pending.contains_key(&connection.from) {
+                if let Some(count) = pending.get_mut(&connection.to) {
+                    *count += 1;
+                }
+            }
+        }
+
+        let mut ready: Vec<u32> = hidden.iter().copied().filter(|id| pending[id] == 0).collect();
+        let mut order = Vec::with_capacity(hidden.len());
+
+        while let Some(id) = ready.pop() {
+            order.push(id);
+
+            for connection in self.enabled_connections().filter(|connection| connection.from == id) {
+                if let Some(count) = pending.get_mut(&connection.to) {
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(connection.to);
+                    }
+                }
+            }
+        }
+
+        (order.len() == hidden.len()).then_some(order)
+    }
+
+    // cpu evaluation, the gpu evaluates the same network in cppn.wgsl
+    pub fn activate(&self, inputs: &[f32]) -> Option<Vec<f32>> {
+        let mut values: HashMap<u32, f32> = (0..self.inputs)
+            .map(|i| (i as u32, inputs.get(i).copied().unwrap_or(0.0)))
+            .collect();
+
+        let outputs = (self.inputs..self.inputs + self.outputs).map(|id| id as u32);
+        for id in self.hidden_order()?.into_iter().chain(outputs) {
+            let sum: f32 = self.enabled_connections()
+                .filter(|connection| connection.to == id)
+                .map(|connection| values.get(&connection.from).copied().unwrap_or(0.0) * connection.weight)
+                .sum();
+
+            let uaf = self.node(id).map_or(Uaf::IDENTITY, |node| node.uaf);
+            values.insert(id, uaf.eval(sum));
+        }
+
+        Some(
+            (self.inputs..self.inputs + self.outputs)
+                .map(|id| values[&(id as u32)])
+                .collect()
+        )
+    }
+
+    pub fn mutate(
+        &mut self,
+        innovations: &mut Innovations,
+        config: &MutationConfig,
+        rng: &mut impl Rng,
+    ) {
+        if rng.gen::<f32>() < config.add_node_rate {
+            self.add_node(innovations, rng);
+        }
+        if rng.gen::<f32>() < config.add_connection_rate {
+            self.add_connection(innovations, config, rng);
+        }
+
+        for connection in &mut self.connections {
+            if rng.gen::<f32>() < config.weight_rate {
+                connection.weight = match rng.gen::<f32>() < config.weight_replace_rate {
+                    true => rng.gen_range(-1.0..1.0) * config.weight_range,
+                    false => connection.weight + rng.gen_range(-1.0..1.0) * config.weight_power,
+                }
+                .clamp(-config.weight_range, config.weight_range);
+            }
+
+            if rng.gen::<f32>() < config.toggle_rate {
+                connection.enabled = !connection.enabled;
+            }
+        }
+
+        for node in self.nodes.iter_mut().filter(|node| node.kind != NodeKind::Input) {
+            if rng.gen::<f32>() >= config.uaf_rate {
+                continue;
+            }
+
+            node.uaf = match rng.gen::<f32>() < config.uaf_preset_rate {
+                true => UafPreset::ALL.choose(rng).copied().unwrap_or_default().uaf(),
+                false => {
+                    let mut parameters = node.uaf.to_array();
+                    for parameter in &mut parameters {
+                        *parameter += rng.gen_range(-1.0..1.0) * config.uaf_power;
+                    }
+                    Uaf::from_array(parameters)
+                }
+            };
+        }
+
+    }
+
+    // splits an enabled connection, the incoming weight is 1 and the outgoing keeps the old weight
+    pub fn add_node(&mut self, innovations: &mut Innovations, rng: &mut impl Rng) -> Option<u32> {
+        let candidates: Vec<usize> = (0..self.connections.len())
+            .filter(|&i| self.connections[i].enabled)
+            .collect();
+        let &index = candidates.choose(rng)?;
+
+        let split = self.connections[index];
+        let id = innovations.split(split.innovation);
+        if self.node(id).is_some() {
+            return None;
+        }
+
+        self.connections[index].enabled = false;
+        self.insert_node(NodeGene {
+            id,
+            kind: NodeKind::Hidden,
+            uaf: Uaf::IDENTITY,
+        });
+        self.insert_connection(ConnectionGene {
+            innovation: innovations.connection(split.from, id),
+            from: split.from,
+            to: id,
+            weight: 1.0,
+            enabled: true,
+        });
+        self.insert_connection(ConnectionGene {
+            innovation: innovations.connection(id, split.to),
+            from: id,
+            to: split.to,
+            weight: split.weight,
+            enabled: true,
+        });
+
+        Some(id)
+    }
+
+    // connects two unconnected nodes without closing a cycle
+    pub fn add_connection(
+        &mut self,
+        innovations: &mut Innovations,
+        config: &MutationConfig,
+        rng: &mut impl Rng,
+    ) -> Option<u64> {
+        const ATTEMPTS: usize = 32;
+
+        for _ in 0..ATTEMPTS {
+            let from = *self.nodes.choose(rng)?;
+            let to = *self.nodes.choose(rng)?;
+
+            if from.kind == NodeKind::Output
+                || to.kind == NodeKind::Input
+                || from.id == to.id
+                || self.connections.iter().any(|connection| connection.from == from.id && connection.to == to.id)
+                || self.reaches(to.id, from.id)
+            {
+                continue;
+            }
+
+            let innovation = innovations.connection(from.id, to.id);
+            self.insert_connection(ConnectionGene {
+                innovation,
+                from: from.id,
+                to: to.id,
+                weight: rng.gen_range(-1.0..1.0) * config.weight_range,
+                enabled: true,
+            });
+
+            return Some(innovation);
+        }
+
+        None
+    }
+
+    // true when a path of connections leads from `from` to `to`, disabled connections count since they can be re-enabled
+    fn reaches(&self, from: u32, to: u32) -> bool {
+        let mut stack = vec![from];
+        let mut visited = vec![from];
+
+        while let Some(id) = stack.pop() {
+            if id == to {
+                return true;
+            }
+
+            for connection in self.connections.iter().filter(|connection| connection.from == id) {
+                if !visited.contains(&connection.to) {
+                    visited.push(connection.to);
+                    stack.push(connection.to);
+                }
+            }
+        }
+
+        false
+    }
+
+    fn insert_node(&mut self, node: NodeGene) {
+        let index = self.nodes.partition_point(|other| other.id < node.id);
+        self.nodes.insert(index, node);
+    }
+
+    fn insert_connection(&mut self, connection: ConnectionGene) {
+        let index = self.connections.partition_point(|other| other.innovation < connection.innovation);
+        self.connections.insert(index, connection);
+    }
+
+    // matching genes come from either parent, disjoint and excess genes from the fitter one
+    //   the child has the fitter parent's structure, so it stays acyclic
+    pub fn crossover(fitter: &Genome, other: &Genome, rng: &mut impl Rng) -> Genome {
+        let mut child = fitter.clone();
+
+        for connection in &mut child.connections {
+            let Ok(index) = other.connections.binary_search_by_key(&connection.innovation, |gene| gene.innovation) else {
+                continue;
+            };
+            let matching = &other.connections[index];
+
+            if rng.gen::<bool>() {
+                connection.weight = matching.weight;
+            }
+            // a gene disabled in either parent is usually disabled in the child
+            if !connection.enabled || !matching.enabled {
+                connection.enabled = rng.gen::<f32>() >= 0.75;
+            }
+        }
+
+        for node in &mut child.nodes {
+            if let Some(matching) = other.node(node.id) {
+                if rng.gen::<bool>() {
+                    node.uaf = matching.uaf;
+                }
+            }
+        }
+
+        child
+    }
+
+    // δ = c1 (disjoint + excess) / n + c2 mean weight difference + c3 mean uaf difference of matching genes
+    pub fn distance(&self, other: &Genome, config: &DistanceConfig) -> f32 {
+        let (mut i, mut j) = (0, 0);
+        let (mut disjoint, mut matching, mut weight_difference) = (0, 0, 0.0);
+
+        while i < self.connections.len() && j < other.connections.len() {
+            let (a, b) = (&self.connections[i], &other.connections[j]);
+
+            match a.innovation.cmp(&b.innovation) {
+                std::cmp::Ordering::Less => {
+                    disjoint += 1;
+                    i += 1;
+                }
+                std::cmp::Ordering::Greater => {
+                    disjoint += 1;
+                    j += 1;
+                }
+                std::cmp::Ordering::Equal => {
+                    matching += 1;
+                    weight_difference += (a.weight - b.weight).abs();
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        disjoint += self.connections.len() - i + other.connections.len() - j;
+
+        let (mut uaf_matching, mut uaf_difference) = (0, 0.0);
+        for node in self.nodes.iter().filter(|node| node.kind != NodeKind::Input) {
+            if let Some(other) = other.node(node.id) {
+                uaf_matching += 1;
+                uaf_difference += node.uaf
+                    .to_array()
+                    .iter()
+                    .zip(other.uaf.to_array())
+                    .map(|(a, b)| (a - b).abs())
+                    .sum::<f32>();
+            }
+        }
+
+        let genes = self.connections.len().max(other.connections.len()).max(1) as f32;
+
+        config.disjoint * disjoint as f32 / genes
+            + config.weight * weight_difference / matching.max(1) as f32
+            + config.uaf * uaf_difference / uaf_matching.max(1) as f32
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{
+        rngs::StdRng,
+        SeedableRng,
+    };
+
+    use super::*;
+
+    fn node(id: u32, kind: NodeKind) -> NodeGene {
+        NodeGene {
+            id,
+            kind,
+            uaf: Uaf::IDENTITY,
+        }
+    }
+
+    fn connection(innovation: u64, from: u32, to: u32, weight: f32) -> ConnectionGene {
+        ConnectionGene {
+            innovation,
+            from,
+            to,
+            weight,
+            enabled: true,
+        }
+    }
+
+    // inputs 0 and 1, output 2, hidden 3 fed by hidden 4 so id order isn't evaluation order
+    fn chain() -> Genome {
+        Genome {
+            inputs: 2,
+            outputs: 1,
+            nodes: vec![
+                node(0, NodeKind::Input),
+                node(1, NodeKind::Input),
+                node(2, NodeKind::Output),
+                node(3, NodeKind::Hidden),
+                node(4, NodeKind::Hidden),
+            ],
+            connections: vec![
+                connection(0, 0, 4, 2.0),
+                connection(1, 4, 3, 1.5),
+                connection(2, 3, 2, 1.0),
+                connection(3, 1, 2, -1.0),
+            ],
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn activate_follows_the_hidden_order() {
+        let genome = chain();
+
+        assert_eq!(genome.hidden_order(), Some(vec![4, 3]));
+
+        // 1.5 * (2 * 1) - 1 * 2
+        let outputs = genome.activate(&[1.0, 2.0]).unwrap();
+        assert!(close(outputs[0], 1.0), "{outputs:?}");
+    }
+
+    #[test]
+    fn activate_skips_disabled_connections_and_rejects_cycles() {
+        let mut genome = chain();
+        genome.connections[3].enabled = false;
+        assert!(close(genome.activate(&[1.0, 2.0]).unwrap()[0], 3.0));
+
+        genome.connections.push(connection(4, 3, 4, 1.0));
+        assert_eq!(genome.hidden_order(), None);
+        assert_eq!(genome.activate(&[1.0, 2.0]), None);
+    }
+
+    #[test]
+    fn add_node_keeps_the_output() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut innovations = Innovations::new(2, 1);
+        let mut genome = Genome::minimal(2, 1, Uaf::IDENTITY, &mut innovations, &MutationConfig::default(), &mut rng);
+        let before = genome.activate(&[0.5, -0.25]).unwrap();
+
+        let id = genome.add_node(&mut innovations, &mut rng).unwrap();
+
+        assert_eq!(genome.node(id).map(|node| node.kind), Some(NodeKind::Hidden));
+        assert!(close(genome.activate(&[0.5, -0.25]).unwrap()[0], before[0]));
+    }
+
+    #[test]
+    fn add_connection_never_closes_a_cycle() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut innovations = Innovations::new(3, 2);
+        let config = MutationConfig::default();
+        let mut genome = Genome::minimal(3, 2, Uaf::IDENTITY, &mut innovations, &config, &mut rng);
+
+        for _ in 0..200 {
+            if rng.gen::<bool>() {
+                genome.add_node(&mut innovations, &mut rng);
+            }
+            genome.add_connection(&mut innovations, &config, &mut rng);
+
+            // disabled connections may be toggled back on, so check with all of them enabled
+            let mut enabled = genome.clone();
+            for connection in &mut enabled.connections {
+                connection.enabled = true;
+            }
+            assert!(enabled.hidden_order().is_some());
+            assert!(genome.connections.windows(2).all(|pair| pair[0].innovation < pair[1].innovation));
+        }
+
+        assert!(genome.hidden_count() > 0);
+    }
+
+    #[test]
+    fn crossover_keeps_the_fitter_structure() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut innovations = Innovations::new(2, 1);
+        let config = MutationConfig::default();
+        let other = Genome::minimal(2, 1, Uaf::IDENTITY, &mut innovations, &config, &mut rng);
+        let mut fitter = other.clone();
+        fitter.add_node(&mut innovations, &mut rng);
+
+        let child = Genome::crossover(&fitter, &other, &mut rng);
+
+        assert_eq!(child.nodes.len(), fitter.nodes.len());
+        let innovations = |genome: &Genome| genome.connections.iter().map(|c| c.innovation).collect::<Vec<_>>();
+        assert_eq!(innovations(&child), innovations(&fitter));
+        assert_eq!(child.distance(&child, &DistanceConfig::default()), 0.0);
+    }
+}
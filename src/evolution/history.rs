@@ -0,0 +1,92 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    Genome,
+    Population,
+};
+
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub generation: u64,
+    // None when no genome of the generation has a finite fitness
+    pub best_fitness: Option<f32>,
+    pub mean_fitness: Option<f32>,
+    pub min_fitness: Option<f32>,
+    pub species: usize,
+    pub mean_hidden_nodes: f32,
+    pub mean_enabled_connections: f32,
+}
+
+impl GenerationStats {
+    // call before `Population::evolve`, which clears the fitness
+    pub fn from_population(population: &Population) -> Self {
+        let fitness: Vec<f32> = population.fitness
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|fitness| fitness.is_finite())
+            .collect();
+
+        let genomes = population.genomes.len().max(1) as f32;
+
+        Self {
+            generation: population.generation,
+            best_fitness: fitness.iter().copied().reduce(f32::max),
+            mean_fitness: (!fitness.is_empty()).then(|| fitness.iter().sum::<f32>() / fitness.len() as f32),
+            min_fitness: fitness.iter().copied().reduce(f32::min),
+            species: population.species.len(),
+            mean_hidden_nodes: population.genomes.iter().map(|genome| genome.hidden_count() as f32).sum::<f32>() / genomes,
+            mean_enabled_connections: population.genomes.iter().map(|genome| genome.enabled_connections().count() as f32).sum::<f32>() / genomes,
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Champion {
+    pub generation: u64,
+    pub fitness: f32,
+    pub genome: Genome,
+}
+
+
+// per generation fitness stats of an evolution run and the best genome seen so far
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunHistory {
+    pub generations: Vec<GenerationStats>,
+    pub champion: Option<Champion>,
+}
+
+impl RunHistory {
+    // records an evaluated generation, call before `Population::evolve`
+    pub fn record(&mut self, population: &Population) -> &GenerationStats {
+        if let Some((index, fitness)) = population.best() {
+            if self.champion.as_ref().is_none_or(|champion| fitness > champion.fitness) {
+                self.champion = Some(Champion {
+                    generation: population.generation,
+                    fitness,
+                    genome: population.genomes[index].clone(),
+                });
+            }
+        }
+
+        self.generations.push(GenerationStats::from_population(population));
+        self.generations.last().unwrap()
+    }
+
+    pub fn last(&self) -> Option<&GenerationStats> {
+        self.generations.last()
+    }
+
+    pub fn len(&self) -> usize {
+        self.generations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.generations.is_empty()
+    }
+}
@@ -0,0 +1,154 @@
+use rand::{
+    seq::IteratorRandom,
+    Rng,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::{
+    Behaviour,
+    Descriptor,
+    Genome,
+    Innovations,
+    MutationConfig,
+};
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DescriptorAxis {
+    pub descriptor: Descriptor,
+    pub min: f32,
+    pub max: f32,
+    pub bins: usize,
+}
+
+impl DescriptorAxis {
+    // spans the descriptor's typical range
+    pub fn new(descriptor: Descriptor, bins: usize) -> Self {
+        let (min, max) = descriptor.range();
+
+        Self {
+            descriptor,
+            min,
+            max,
+            bins,
+        }
+    }
+
+    // values outside the axis fall into the outer bins
+    pub fn bin(&self, value: f32) -> Option<usize> {
+        if !value.is_finite() || self.bins == 0 || self.max <= self.min {
+            return None;
+        }
+
+        let t = (value - self.min) / (self.max - self.min);
+        Some(((t * self.bins as f32).max(0.0) as usize).min(self.bins - 1))
+    }
+
+    // value at the center of the bin
+    pub fn center(&self, bin: usize) -> f32 {
+        self.min + (bin as f32 + 0.5) / self.bins.max(1) as f32 * (self.max - self.min)
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Elite<T> {
+    pub solution: T,
+    pub fitness: f32,
+    pub behaviour: Behaviour,
+}
+
+
+// map-elites (Mouret & Clune 2015): the best solution found for every cell of a grid over two descriptors
+//   solutions are genomes by default, but any field parameters can be illuminated
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MapElites<T = Genome> {
+    pub x: DescriptorAxis,
+    pub y: DescriptorAxis,
+    // row-major, y major
+    pub cells: Vec<Option<Elite<T>>>,
+}
+
+impl<T: Clone> MapElites<T> {
+    pub fn new(x: DescriptorAxis, y: DescriptorAxis) -> Self {
+        Self {
+            x,
+            y,
+            cells: vec![None; x.bins * y.bins],
+        }
+    }
+
+    pub fn cell(&self, behaviour: &Behaviour) -> Option<usize> {
+        let x = self.x.bin(behaviour.descriptor(self.x.descriptor)?)?;
+        let y = self.y.bin(behaviour.descriptor(self.y.descriptor)?)?;
+
+        Some(y * self.x.bins + x)
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&Elite<T>> {
+        self.cells.get(y * self.x.bins + x)?.as_ref()
+    }
+
+    // true when the solution became the elite of its cell
+    pub fn insert(&mut self, solution: T, fitness: f32, behaviour: Behaviour) -> bool {
+        let Some(cell) = self.cell(&behaviour) else {
+            return false;
+        };
+
+        let slot = &mut self.cells[cell];
+        if slot.as_ref().is_some_and(|elite| elite.fitness >= fitness) {
+            return false;
+        }
+
+        *slot = Some(Elite {
+            solution,
+            fitness,
+            behaviour,
+        });
+        true
+    }
+
+    pub fn elites(&self) -> impl Iterator<Item = &Elite<T>> {
+        self.cells.iter().flatten()
+    }
+
+    pub fn filled(&self) -> usize {
+        self.elites().count()
+    }
+
+    // fraction of cells with an elite
+    pub fn coverage(&self) -> f32 {
+        self.filled() as f32 / self.cells.len().max(1) as f32
+    }
+
+    // sum of elite fitness, shifted so every elite adds a positive amount
+    pub fn qd_score(&self, min_fitness: f32) -> f32 {
+        self.elites().map(|elite| (elite.fitness - min_fitness).max(0.0)).sum()
+    }
+
+    pub fn best(&self) -> Option<&Elite<T>> {
+        self.elites().max_by(|a, b| a.fitness.total_cmp(&b.fitness))
+    }
+
+    // a uniformly random elite, parents of the next solutions
+    pub fn sample(&self, rng: &mut impl Rng) -> Option<&Elite<T>> {
+        self.elites().choose(rng)
+    }
+}
+
+impl MapElites<Genome> {
+    // a mutated copy of a random elite, None while the grid is empty
+    pub fn offspring(
+        &self,
+        innovations: &mut Innovations,
+        config: &MutationConfig,
+        rng: &mut impl Rng,
+    ) -> Option<Genome> {
+        let mut genome = self.sample(rng)?.solution.clone();
+        genome.mutate(innovations, config, rng);
+        Some(genome)
+    }
+}
@@ -0,0 +1,28 @@
+// neat genomes and populations, genomes are evaluated by decoding them into a field, see `HyperNeatField`
+//   genomes, populations and run histories are saved as json or binary through `EvolutionFile`
+//   `EvolutionDriver` runs whole generations on the field
+//   besides objective fitness, runs can be scored by novelty of their behaviour or illuminated with map-elites
+
+mod behaviour;
+pub use behaviour::*;
+
+mod driver;
+pub use driver::*;
+
+mod format;
+pub use format::*;
+
+mod genome;
+pub use genome::*;
+
+mod history;
+pub use history::*;
+
+mod map_elites;
+pub use map_elites::*;
+
+mod novelty;
+pub use novelty::*;
+
+mod population;
+pub use population::*;
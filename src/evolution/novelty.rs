@@ -0,0 +1,119 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::behaviour_distance;
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NoveltyConfig {
+    // neighbours averaged for the novelty score
+    pub k: usize,
+    // behaviours at least this novel join the archive, adjusted as the archive grows
+    pub threshold: f32,
+    // more additions than this in one generation raise the threshold
+    pub max_additions: usize,
+    // generations without an addition before the threshold is lowered
+    pub patience: usize,
+    pub max_archive: usize,
+}
+
+impl Default for NoveltyConfig {
+    fn default() -> Self {
+        Self {
+            k: 15,
+            threshold: 0.1,
+            max_additions: 4,
+            patience: 5,
+            max_archive: 4096,
+        }
+    }
+}
+
+
+// novelty search (Lehman & Stanley 2011): novelty is the mean distance to the k nearest behaviours
+// among the archive and the rest of the current generation
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoveltyArchive {
+    pub config: NoveltyConfig,
+    pub behaviours: Vec<Vec<f32>>,
+    stale_generations: usize,
+}
+
+impl NoveltyArchive {
+    pub fn new(config: NoveltyConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.behaviours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.behaviours.is_empty()
+    }
+
+    pub fn novelty(&self, behaviour: &[f32], generation: &[Vec<f32>]) -> f32 {
+        let mut distances: Vec<f32> = self.behaviours
+            .iter()
+            .chain(generation)
+            .map(|other| behaviour_distance(behaviour, other))
+            .collect();
+
+        // the behaviour itself, when it is part of the generation
+        if let Some(position) = distances.iter().position(|&distance| distance == 0.0) {
+            distances.swap_remove(position);
+        }
+
+        if distances.is_empty() {
+            return 0.0;
+        }
+
+        let k = self.config.k.clamp(1, distances.len());
+        distances.select_nth_unstable_by(k - 1, f32::total_cmp);
+
+        distances[..k].iter().sum::<f32>() / k as f32
+    }
+
+    // scores a whole generation, then archives its novel behaviours and adapts the threshold
+    pub fn score_generation(&mut self, generation: &[Vec<f32>]) -> Vec<f32> {
+        let scores: Vec<f32> = generation
+            .iter()
+            .map(|behaviour| self.novelty(behaviour, generation))
+            .collect();
+
+        let mut additions = 0;
+        for (behaviour, &score) in generation.iter().zip(&scores) {
+            if score >= self.config.threshold {
+                self.behaviours.push(behaviour.clone());
+                additions += 1;
+            }
+        }
+
+        if self.behaviours.len() > self.config.max_archive {
+            let excess = self.behaviours.len() - self.config.max_archive;
+            self.behaviours.drain(..excess);
+        }
+
+        if additions > self.config.max_additions {
+            self.config.threshold *= 1.2;
+        }
+
+        match additions {
+            0 => {
+                self.stale_generations += 1;
+                if self.stale_generations >= self.config.patience {
+                    self.config.threshold *= 0.95;
+                    self.stale_generations = 0;
+                }
+            }
+            _ => self.stale_generations = 0,
+        }
+
+        scores
+    }
+}
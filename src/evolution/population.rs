@@ -0,0 +1,333 @@
+use rand::{
+    seq::SliceRandom,
+    Rng,
+    SeedableRng,
+};
+use rand_chacha::ChaCha12Rng;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::uaf::Uaf;
+
+use super::{
+    DistanceConfig,
+    Genome,
+    Innovations,
+    MapElites,
+    MutationConfig,
+};
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EvolutionConfig {
+    pub population_size: usize,
+    pub mutation: MutationConfig,
+    pub distance: DistanceConfig,
+    // genomes closer than this to a species' representative join it
+    pub compatibility_threshold: f32,
+    // generations without improvement before a species stops reproducing, the best species is always kept
+    pub stagnation_limit: usize,
+    // fraction of each species allowed to reproduce
+    pub survival_fraction: f32,
+    pub crossover_rate: f32,
+    // the best genome of species at least this large is copied unchanged
+    pub elitism_size: usize,
+    pub output_uaf: Uaf,
+}
+
+impl Default for EvolutionConfig {
+    fn default() -> Self {
+        Self {
+            population_size: 64,
+            mutation: MutationConfig::default(),
+            distance: DistanceConfig::default(),
+            compatibility_threshold: 3.0,
+            stagnation_limit: 15,
+            survival_fraction: 0.2,
+            crossover_rate: 0.75,
+            elitism_size: 5,
+            output_uaf: Uaf::TANH,
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Species {
+    pub id: u64,
+    pub representative: Genome,
+    // indices into the population's genomes
+    pub members: Vec<usize>,
+    pub best_fitness: f32,
+    pub stagnation: usize,
+}
+
+
+// a neat population, fitness is assigned from outside before each `evolve`
+//   the rng is the algorithm behind `StdRng` with a serializable state, so a saved population resumes exactly
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Population {
+    pub config: EvolutionConfig,
+    pub genomes: Vec<Genome>,
+    // None until the genome has been evaluated
+    pub fitness: Vec<Option<f32>>,
+    pub species: Vec<Species>,
+    pub generation: u64,
+    pub innovations: Innovations,
+    next_species: u64,
+    rng: ChaCha12Rng,
+}
+
+impl Population {
+    pub fn new(
+        config: EvolutionConfig,
+        inputs: usize,
+        outputs: usize,
+        seed: u64,
+    ) -> Self {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let mut innovations = Innovations::new(inputs, outputs);
+
+        let genomes: Vec<Genome> = (0..config.population_size.max(1))
+            .map(|_| Genome::minimal(inputs, outputs, config.output_uaf, &mut innovations, &config.mutation, &mut rng))
+            .collect();
+
+        let mut population = Self {
+            fitness: vec![None; genomes.len()],
+            config,
+            genomes,
+            species: Vec::new(),
+            generation: 0,
+            innovations,
+            next_species: 0,
+            rng,
+        };
+        population.speciate();
+        population
+    }
+
+    pub fn len(&self) -> usize {
+        self.genomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genomes.is_empty()
+    }
+
+    pub fn set_fitness(&mut self, index: usize, fitness: f32) {
+        if let Some(slot) = self.fitness.get_mut(index) {
+            *slot = Some(fitness);
+        }
+    }
+
+    // index of the first genome without a fitness
+    pub fn next_unevaluated(&self) -> Option<usize> {
+        self.fitness.iter().position(Option::is_none)
+    }
+
+    pub fn is_evaluated(&self) -> bool {
+        self.next_unevaluated().is_none()
+    }
+
+    pub fn best(&self) -> Option<(usize, f32)> {
+        self.fitness
+            .iter()
+            .enumerate()
+            .filter_map(|(i, fitness)| fitness.filter(|fitness| fitness.is_finite()).map(|fitness| (i, fitness)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    pub fn mean_fitness(&self) -> f32 {
+        let fitness: Vec<f32> = self.fitness.iter().flatten().copied().filter(|f| f.is_finite()).collect();
+
+        match fitness.is_empty() {
+            true => f32::NAN,
+            false => fitness.iter().sum::<f32>() / fitness.len() as f32,
+        }
+    }
+
+    fn fitness_of(&self, index: usize) -> f32 {
+        self.fitness[index].filter(|fitness| fitness.is_finite()).unwrap_or(f32::MIN)
+    }
+
+    // assigns every genome to the first species whose representative is close enough
+    fn speciate(&mut self) {
+        for species in &mut self.species {
+            species.members.clear();
+        }
+
+        for (index, genome) in self.genomes.iter().enumerate() {
+            let species = self.species
+                .iter_mut()
+                .find(|species| genome.distance(&species.representative, &self.config.distance) < self.config.compatibility_threshold);
+
+            match species {
+                Some(species) => species.members.push(index),
+                None => {
+                    self.species.push(Species {
+                        id: self.next_species,
+                        representative: genome.clone(),
+                        members: vec![index],
+                        best_fitness: f32::MIN,
+                        stagnation: 0,
+                    });
+                    self.next_species += 1;
+                }
+            }
+        }
+
+        self.species.retain(|species| !species.members.is_empty());
+    }
+
+    // replaces the genomes with the next generation, unevaluated genomes count as the least fit
+    pub fn evolve(&mut self) {
+        for i in 0..self.species.len() {
+            let best = self.species[i].members
+                .iter()
+                .map(|&member| self.fitness_of(member))
+                .fold(f32::MIN, f32::max);
+
+            let species = &mut self.species[i];
+            if best > species.best_fitness {
+                species.best_fitness = best;
+                species.stagnation = 0;
+            } else {
+                species.stagnation += 1;
+            }
+        }
+
+        // stagnant species are dropped, unless every species stagnated
+        let best_species = self.species
+            .iter()
+            .max_by(|a, b| a.best_fitness.total_cmp(&b.best_fitness))
+            .map(|species| species.id);
+        let limit = self.config.stagnation_limit;
+        let mut surviving: Vec<Species> = self.species
+            .iter()
+            .filter(|species| species.stagnation < limit || Some(species.id) == best_species)
+            .cloned()
+            .collect();
+
+        // members sorted fittest first
+        for species in &mut surviving {
+            let mut members = std::mem::take(&mut species.members);
+            members.sort_by(|&a, &b| self.fitness_of(b).total_cmp(&self.fitness_of(a)));
+            species.members = members;
+        }
+
+        let offspring = self.offspring_counts(&surviving);
+
+        self.innovations.next_generation();
+        let mut genomes = Vec::with_capacity(self.config.population_size);
+
+        for (species, &count) in surviving.iter().zip(&offspring) {
+            let parents = ((species.members.len() as f32 * self.config.survival_fraction).ceil() as usize)
+                .clamp(1, species.members.len());
+            let parents = &species.members[..parents];
+
+            for child in 0..count {
+                if child == 0 && species.members.len() >= self.config.elitism_size {
+                    genomes.push(self.genomes[species.members[0]].clone());
+                    continue;
+                }
+
+                genomes.push(self.offspring(parents));
+            }
+        }
+
+        let total = self.config.population_size.max(1);
+        while genomes.len() < total {
+            let parents = surviving.first().map(|species| species.members.clone()).unwrap_or_else(|| vec![0]);
+            genomes.push(self.offspring(&parents));
+        }
+        genomes.truncate(total);
+
+        // representatives are random members of the previous generation
+        for species in &mut surviving {
+            if let Some(&member) = species.members.choose(&mut self.rng) {
+                species.representative = self.genomes[member].clone();
+            }
+        }
+
+        self.species = surviving;
+        self.fitness = vec![None; genomes.len()];
+        self.genomes = genomes;
+        self.generation += 1;
+        self.speciate();
+    }
+
+    // replaces the genomes with mutated elites of the map instead of offspring of the species
+    //   falls back to `evolve` while the map is empty
+    pub fn evolve_from_elites(&mut self, map: &MapElites<Genome>) {
+        if map.filled() == 0 {
+            self.evolve();
+            return;
+        }
+
+        self.innovations.next_generation();
+        let genomes: Vec<Genome> = (0..self.config.population_size.max(1))
+            .filter_map(|_| map.offspring(&mut self.innovations, &self.config.mutation, &mut self.rng))
+            .collect();
+
+        self.fitness = vec![None; genomes.len()];
+        self.genomes = genomes;
+        self.generation += 1;
+        self.speciate();
+    }
+
+    // offspring per species proportional to its mean shifted fitness
+    fn offspring_counts(&self, species: &[Species]) -> Vec<usize> {
+        let lowest = self.fitness
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|fitness| fitness.is_finite())
+            .fold(f32::MAX, f32::min);
+
+        let adjusted: Vec<f32> = species
+            .iter()
+            .map(|species| {
+                species.members
+                    .iter()
+                    .map(|&member| match self.fitness[member] {
+                        Some(fitness) if fitness.is_finite() => fitness - lowest + 1e-3,
+                        _ => 0.0,
+                    })
+                    .sum::<f32>() / species.members.len().max(1) as f32
+            })
+            .collect();
+
+        let total_adjusted: f32 = adjusted.iter().sum();
+        let total = self.config.population_size.max(1);
+
+        adjusted
+            .iter()
+            .map(|&adjusted| match total_adjusted > 0.0 {
+                true => (adjusted / total_adjusted * total as f32).round() as usize,
+                false => total / species.len().max(1),
+            })
+            .collect()
+    }
+
+    fn offspring(&mut self, parents: &[usize]) -> Genome {
+        let first = *parents.choose(&mut self.rng).unwrap_or(&0);
+
+        let mut child = match parents.len() > 1 && self.rng.gen::<f32>() < self.config.crossover_rate {
+            true => {
+                let second = *parents.choose(&mut self.rng).unwrap_or(&first);
+                let (fitter, other) = match self.fitness_of(first) >= self.fitness_of(second) {
+                    true => (first, second),
+                    false => (second, first),
+                };
+
+                Genome::crossover(&self.genomes[fitter], &self.genomes[other], &mut self.rng)
+            }
+            false => self.genomes[first].clone(),
+        };
+
+        child.mutate(&mut self.innovations, &self.config.mutation, &mut self.rng);
+        child
+    }
+}
@@ -0,0 +1,621 @@
+use std::{
+    fmt,
+    fs,
+    io,
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use bevy::{
+    asset::{
+        AssetLoader,
+        LoadContext,
+        LoadedAsset,
+    },
+    prelude::*,
+    reflect::{
+        TypePath,
+        TypeUuid,
+    },
+    render::{
+        render_resource::Extent3d,
+        renderer::RenderDevice,
+        settings::WgpuLimits,
+    },
+    utils::BoxedFuture,
+    window::PrimaryWindow,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    automata::{
+        AutomataField,
+        AutomataParameters,
+        EdgeInit,
+        Plasticity,
+        StepLimit,
+        StepLimitReached,
+    },
+    batch::FieldRun,
+    checkpoint::Checkpoint,
+    connectome::{
+        ImportedGraph,
+        NodeMapping,
+    },
+    evolution::{
+        EvolutionFile,
+        Genome,
+    },
+    heatmap::Heatmap,
+    hyperneat::HyperNeatField,
+    neat::NeatField,
+    npy::{
+        FieldArrays,
+        FieldMetadata,
+    },
+    readback::{
+        FieldSnapshot,
+        Readback,
+        ReadbackPlugin,
+    },
+    uaf::Uaf,
+};
+
+
+// loads `*.experiment.ron` and `*.experiment.toml` assets, the field restarts whenever the `Experiment` config changes
+//   add after the `AutomataPlugin` and `NeatPlugin`, hot reloading needs the asset watcher of `RustyAutomataApp`
+#[derive(Default)]
+pub struct ExperimentPlugin;
+
+impl Plugin for ExperimentPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<ReadbackPlugin>() {
+            app.add_plugins(ReadbackPlugin);
+        }
+
+        app.add_asset::<ExperimentConfig>();
+        app.init_asset_loader::<ExperimentLoader>();
+
+        app.add_systems(
+            Update,
+            (
+                apply_experiment,
+                save_experiment_outputs,
+            ).chain(),
+        );
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Ron,
+    Toml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match path.as_ref().extension()?.to_str()? {
+            "ron" => Some(ConfigFormat::Ron),
+            "toml" => Some(ConfigFormat::Toml),
+            _ => None,
+        }
+    }
+}
+
+
+#[derive(Debug)]
+pub enum ExperimentError {
+    Io(io::Error),
+    Ron(ron::error::SpannedError),
+    Toml(toml::de::Error),
+    UnknownFormat(PathBuf),
+}
+
+impl fmt::Display for ExperimentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExperimentError::Io(err) => write!(f, "experiment io error: {err}"),
+            ExperimentError::Ron(err) => write!(f, "experiment ron error: {err}"),
+            ExperimentError::Toml(err) => write!(f, "experiment toml error: {err}"),
+            ExperimentError::UnknownFormat(path) => write!(f, "{} is neither .ron nor .toml", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ExperimentError {}
+
+impl From<io::Error> for ExperimentError {
+    fn from(err: io::Error) -> Self {
+        ExperimentError::Io(err)
+    }
+}
+
+impl From<ron::error::SpannedError> for ExperimentError {
+    fn from(err: ron::error::SpannedError) -> Self {
+        ExperimentError::Ron(err)
+    }
+}
+
+impl From<toml::de::Error> for ExperimentError {
+    fn from(err: toml::de::Error) -> Self {
+        ExperimentError::Toml(err)
+    }
+}
+
+
+// how the edges of a new field are created
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum FieldInit {
+    // gaussian offsets and weights
+    #[default]
+    Random,
+    // weights queried from the `HyperNeatField` cppn, optionally loaded from a genome file first
+    //   needs the `HyperNeatPlugin`
+    Cppn {
+        genome: Option<PathBuf>,
+    },
+    // an imported connectome, see `ImportedGraph::load`
+    Graph {
+        path: PathBuf,
+        #[serde(default)]
+        mapping: NodeMapping,
+    },
+    // the full saved state, the checkpoint's size, edges and uaf replace the config's
+    Checkpoint {
+        path: PathBuf,
+    },
+}
+
+// files written once the step limit is reached
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    pub dir: PathBuf,
+    // steps between readback snapshots, None leaves the `Readback` interval as is
+    pub sample_interval: Option<u64>,
+    // textures read back every interval besides the nodes, the final snapshot always has all of them
+    //   the edges are the largest texture, one layer per edge
+    pub edges: bool,
+    pub uaf: bool,
+    pub checkpoint: bool,
+    pub npz: bool,
+    pub png: bool,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("results"),
+            sample_interval: None,
+            edges: true,
+            uaf: true,
+            checkpoint: true,
+            npz: true,
+            png: true,
+        }
+    }
+}
+
+impl OutputConfig {
+    pub fn any(&self) -> bool {
+        self.checkpoint || self.npz || self.png
+    }
+
+    // writes the enabled `final.*` files to `dir`, with the result of each by file name
+    pub fn save(
+        &self,
+        snapshot: &FieldSnapshot,
+        parameters: AutomataParameters,
+        init_uaf: Uaf,
+        edge_init: EdgeInit,
+        plasticity: Plasticity,
+    ) -> Vec<(&'static str, Result<(), String>)> {
+        let mut saved = Vec::new();
+
+        if let Err(err) = fs::create_dir_all(&self.dir) {
+            error!("failed to create {}: {err}", self.dir.display());
+        }
+
+        if self.npz {
+            let arrays = FieldArrays::from_snapshot(snapshot);
+            let metadata = FieldMetadata::new(parameters, std::slice::from_ref(snapshot));
+            let npz = arrays.save_npz(self.dir.join("final.npz"), Some(&metadata));
+            saved.push(("final.npz", npz.map_err(|err| err.to_string())));
+        }
+
+        if self.png {
+            let png = Heatmap::from_snapshot(snapshot).save_png(self.dir.join("final.png"));
+            saved.push(("final.png", png.map_err(|err| err.to_string())));
+        }
+
+        if self.checkpoint {
+            let checkpoint = Checkpoint::from_snapshot(snapshot, parameters, init_uaf, edge_init, plasticity)
+                .and_then(|checkpoint| checkpoint.save(self.dir.join("final.rack")));
+            saved.push(("final.rack", checkpoint.map_err(|err| err.to_string())));
+        }
+
+        saved
+    }
+}
+
+
+// everything needed to set up a field, fields left out of a file keep their defaults
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, TypeUuid, TypePath)]
+#[uuid = "3b0f6d8e-7c41-4a5e-9d2b-58e1f0a6c2d4"]
+#[serde(default)]
+pub struct ExperimentConfig {
+    pub name: String,
+    // None follows the primary window, or `DEFAULT_SIZE` without one
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub edges: AutomataParameters,
+    pub init: FieldInit,
+    pub uaf: Uaf,
+    // also applied to fields restored from a checkpoint
+    pub plasticity: Plasticity,
+    // sends `StepLimitReached` and writes the outputs at this step
+    pub step_limit: Option<u64>,
+    pub output: OutputConfig,
+}
+
+impl Default for ExperimentConfig {
+    fn default() -> Self {
+        Self {
+            name: "experiment".to_string(),
+            width: None,
+            height: None,
+            edges: AutomataParameters::default(),
+            init: FieldInit::default(),
+            uaf: NeatField::DEFAULT_UAF,
+            plasticity: Plasticity::default(),
+            step_limit: None,
+            output: OutputConfig::default(),
+        }
+    }
+}
+
+impl ExperimentConfig {
+    pub const DEFAULT_SIZE: u32 = 256;
+
+    pub fn from_bytes(bytes: &[u8], format: ConfigFormat) -> Result<Self, ExperimentError> {
+        match format {
+            ConfigFormat::Ron => Ok(ron::de::from_bytes(bytes)?),
+            ConfigFormat::Toml => {
+                let text = std::str::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                Ok(toml::from_str(text)?)
+            }
+        }
+    }
+
+    // format is picked from the extension
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ExperimentError> {
+        let path = path.as_ref();
+        let format = ConfigFormat::from_path(path).ok_or_else(|| ExperimentError::UnknownFormat(path.to_path_buf()))?;
+        Self::from_bytes(&fs::read(path)?, format)
+    }
+
+    pub fn field_size(&self, window: Option<&Window>) -> Extent3d {
+        let (window_width, window_height) = window.map_or((Self::DEFAULT_SIZE, Self::DEFAULT_SIZE), |window| {
+            (window.resolution.width() as u32, window.resolution.height() as u32)
+        });
+
+        Extent3d {
+            width: self.width.unwrap_or(window_width),
+            height: self.height.unwrap_or(window_height),
+            depth_or_array_layers: 1,
+        }
+    }
+
+    // a `BatchQueue` run, only `FieldInit::Random` fields can be batched
+    pub fn field_run(&self, default_steps: u64) -> FieldRun {
+        let size = self.field_size(None);
+
+        FieldRun {
+            label: self.name.clone(),
+            width: size.width,
+            height: size.height,
+            parameters: self.edges,
+            init_uaf: self.uaf,
+            plasticity: self.plasticity,
+            steps: self.step_limit.unwrap_or(default_steps),
+        }
+    }
+
+    // the fields described by the config, None when a file it refers to fails to load or the field exceeds `limits`
+    pub fn build_fields(
+        &self,
+        window: Option<&Window>,
+        limits: &WgpuLimits,
+        images: &mut ResMut<Assets<Image>>,
+    ) -> Option<(AutomataField, NeatField)> {
+        let size = self.field_size(window);
+
+        // a checkpoint brings its own size and edge count
+        if !matches!(self.init, FieldInit::Checkpoint { .. }) {
+            if let Err(err) = AutomataField::check_limits(size, self.edges.edge_count, limits) {
+                error!("experiment {}: {err}", self.name);
+                return None;
+            }
+        }
+
+        let automata_field = match &self.init {
+            FieldInit::Random => AutomataField::from_parameters(size, self.edges, images),
+            FieldInit::Cppn { .. } => AutomataField::from_parameters(size, self.edges, images).with_edge_init(EdgeInit::Cppn),
+            FieldInit::Graph { path, mapping } => {
+                let layout = ImportedGraph::load(path).and_then(|graph| graph.layout(size, self.edges.edge_count, mapping));
+                match layout {
+                    Ok(layout) => layout.into_field(self.edges, images),
+                    Err(err) => {
+                        error!("failed to import connectome from {}: {err}", path.display());
+                        return None;
+                    }
+                }
+            }
+            FieldInit::Checkpoint { path } => {
+                return match Checkpoint::load(path) {
+                    Ok(checkpoint) => {
+                        if let Err(err) = AutomataField::check_limits(checkpoint.field_size(), checkpoint.parameters.edge_count, limits) {
+                            error!("experiment {}: checkpoint {}: {err}", self.name, path.display());
+                            return None;
+                        }
+
+                        let (automata_field, neat_field) = checkpoint.restore(images);
+                        Some((automata_field.with_plasticity(self.plasticity), neat_field))
+                    }
+                    Err(err) => {
+                        error!("failed to load checkpoint from {}: {err}", path.display());
+                        None
+                    }
+                };
+            }
+        };
+
+        Some((automata_field.with_plasticity(self.plasticity), NeatField::new(size, images).with_uaf(self.uaf)))
+    }
+}
+
+
+#[derive(Default)]
+struct ExperimentLoader;
+
+impl AssetLoader for ExperimentLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let format = ConfigFormat::from_path(load_context.path()).unwrap_or(ConfigFormat::Ron);
+            let config = ExperimentConfig::from_bytes(bytes, format)?;
+            load_context.set_default_asset(LoadedAsset::new(config));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["experiment.ron", "experiment.toml"]
+    }
+}
+
+
+// the config the field follows, e.g. `Experiment::new(asset_server.load("experiments/neat.experiment.ron"))`
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Experiment {
+    pub config: Handle<ExperimentConfig>,
+    applied: Option<ExperimentConfig>,
+    applied_handle: Option<Handle<ExperimentConfig>>,
+    // field whose outputs are written with the next complete snapshot
+    pending_output: Option<Handle<Image>>,
+}
+
+impl Experiment {
+    pub fn new(config: Handle<ExperimentConfig>) -> Self {
+        Self {
+            config,
+            ..default()
+        }
+    }
+
+    // the config of the current field, None until it has loaded
+    pub fn applied(&self) -> Option<&ExperimentConfig> {
+        self.applied.as_ref()
+    }
+}
+
+
+#[allow(clippy::too_many_arguments)]
+fn apply_experiment(
+    mut commands: Commands,
+    experiment: Option<ResMut<Experiment>>,
+    configs: Res<Assets<ExperimentConfig>>,
+    mut events: EventReader<AssetEvent<ExperimentConfig>>,
+    mut images: ResMut<Assets<Image>>,
+    mut textures: Query<&mut Handle<Image>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    automata: Option<Res<AutomataField>>,
+    hyperneat: Option<Res<HyperNeatField>>,
+    mut step_limit: ResMut<StepLimit>,
+    mut readback: ResMut<Readback>,
+    render_device: Option<Res<RenderDevice>>,
+) {
+    let Some(mut experiment) = experiment else {
+        events.clear();
+        return;
+    };
+
+    let modified = events
+        .iter()
+        .filter(|event| match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => *handle == experiment.config,
+            AssetEvent::Removed { .. } => false,
+        })
+        .count() > 0;
+
+    if !modified && experiment.applied_handle.as_ref() == Some(&experiment.config) {
+        return;
+    }
+
+    let Some(config) = configs.get(&experiment.config) else {
+        return;
+    };
+
+    experiment.applied_handle = Some(experiment.config.clone());
+    if experiment.applied.as_ref() == Some(config) {
+        return;
+    }
+
+    let genome = match &config.init {
+        FieldInit::Cppn { genome: Some(path) } => match Genome::load(path) {
+            Ok(genome) => Some(genome),
+            Err(err) => {
+                error!("failed to load cppn genome from {}: {err}", path.display());
+                None
+            }
+        },
+        _ => None,
+    };
+
+    // only a `HyperNeatField` writes cppn weights, without one every edge would stay zero
+    if matches!(config.init, FieldInit::Cppn { .. }) && genome.is_none() && hyperneat.is_none() {
+        error!("experiment {}: cppn edges need a genome file or an existing HyperNeatField", config.name);
+        return;
+    }
+
+    // the current field keeps running when the new one does not fit, e.g. a window sized field on a large display
+    let limits = render_device.map_or_else(WgpuLimits::default, |render_device| render_device.limits());
+    let Some((automata_field, neat_field)) = config.build_fields(windows.get_single().ok(), &limits, &mut images) else {
+        return;
+    };
+
+    if let Some(genome) = genome {
+        commands.insert_resource(HyperNeatField::new(genome));
+    }
+
+    // keep sprites that displayed the previous field pointed at the new one
+    if let Some(automata) = automata {
+        for mut texture in &mut textures {
+            if *texture == automata.nodes {
+                *texture = automata_field.nodes.clone();
+            }
+        }
+    }
+
+    step_limit.max_steps = config.step_limit;
+    if let Some(interval) = config.output.sample_interval {
+        readback.interval = Some(interval.max(1));
+    }
+    readback.nodes = true;
+    readback.edges = config.output.edges;
+    readback.uaf = config.output.uaf;
+
+    info!(
+        "experiment {}: {}x{} field with {} edges",
+        config.name,
+        automata_field.width(),
+        automata_field.height(),
+        automata_field.edge_count(),
+    );
+
+    experiment.applied = Some(config.clone());
+    experiment.pending_output = None;
+
+    commands.insert_resource(automata_field);
+    commands.insert_resource(neat_field);
+}
+
+fn save_experiment_outputs(
+    experiment: Option<ResMut<Experiment>>,
+    mut reached: EventReader<StepLimitReached>,
+    mut snapshots: EventReader<FieldSnapshot>,
+    mut readback: ResMut<Readback>,
+    automata: Option<Res<AutomataField>>,
+    neat: Option<Res<NeatField>>,
+) {
+    let (Some(mut experiment), Some(automata)) = (experiment, automata) else {
+        reached.clear();
+        snapshots.clear();
+        return;
+    };
+
+    let Some(output) = experiment.applied.as_ref().map(|config| config.output.clone()) else {
+        reached.clear();
+        snapshots.clear();
+        return;
+    };
+
+    if reached.iter().last().is_some() && output.any() {
+        experiment.pending_output = Some(automata.nodes.clone());
+        readback.request();
+    }
+
+    let Some(field) = &experiment.pending_output else {
+        snapshots.clear();
+        return;
+    };
+
+    let Some(snapshot) = snapshots.iter().filter(|snapshot| snapshot.field == *field && snapshot.is_complete()).last() else {
+        return;
+    };
+
+    let init_uaf = neat.map_or(NeatField::DEFAULT_UAF, |neat| neat.init_uaf);
+    for (name, result) in output.save(
+        snapshot,
+        automata.parameters(),
+        init_uaf,
+        automata.edge_init(),
+        automata.plasticity(),
+    ) {
+        match result {
+            Ok(()) => info!("saved {} at step {}", output.dir.join(name).display(), snapshot.step),
+            Err(err) => error!("failed to save {}: {err}", output.dir.join(name).display()),
+        }
+    }
+
+    experiment.pending_output = None;
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ron_example() {
+        let config = ExperimentConfig::load("assets/experiments/neat.experiment.ron").unwrap();
+
+        assert_eq!(config.name, "neat");
+        assert_eq!((config.width, config.height), (None, None));
+        assert_eq!(config.edges.edge_count, 25);
+        assert_eq!(config.init, FieldInit::Random);
+        assert_eq!(config.output.sample_interval, Some(1));
+        assert!(!config.output.edges && !config.output.uaf);
+    }
+
+    #[test]
+    fn parses_toml_example() {
+        let config = ExperimentConfig::load("assets/experiments/headless.experiment.toml").unwrap();
+
+        assert_eq!(config.name, "headless");
+        assert_eq!((config.width, config.height), (Some(256), Some(256)));
+        assert_eq!(config.edges.edge_count, 25);
+        assert_eq!(config.step_limit, Some(1000));
+        assert_eq!(config.output.dir, PathBuf::from("results/headless"));
+        assert!(config.output.npz && !config.output.png);
+    }
+
+    #[test]
+    fn example_fields_fit_the_default_limits() {
+        for path in ["assets/experiments/neat.experiment.ron", "assets/experiments/headless.experiment.toml"] {
+            let config = ExperimentConfig::load(path).unwrap();
+            let size = config.field_size(None);
+
+            assert_eq!(AutomataField::check_limits(size, config.edges.edge_count, &WgpuLimits::default()), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+};
+
+use crate::readback::FieldSnapshot;
+
+
+const MISSING_COLOR: [u8; 3] = [128, 128, 128];
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Colormap {
+    Grayscale,
+    // blue below the middle of the range, red above
+    #[default]
+    Diverging,
+    // dark purple to yellow, for metrics without a meaningful middle
+    Sequential,
+}
+
+impl Colormap {
+    // t in 0..1
+    pub fn color(&self, t: f32) -> [u8; 3] {
+        let t = if t.is_finite() { t.clamp(0.0, 1.0) } else { 0.0 };
+
+        let rgb = match self {
+            Colormap::Grayscale => [t, t, t],
+            Colormap::Diverging => {
+                let s = (2.0 * t - 1.0).abs();
+                match t < 0.5 {
+                    true => [1.0 - s * 0.8, 1.0 - s * 0.6, 1.0],
+                    false => [1.0, 1.0 - s * 0.7, 1.0 - s * 0.8],
+                }
+            }
+            Colormap::Sequential => {
+                const STOPS: [[f32; 3]; 5] = [
+                    [0.27, 0.00, 0.33],
+                    [0.23, 0.32, 0.55],
+                    [0.13, 0.57, 0.55],
+                    [0.37, 0.79, 0.38],
+                    [0.99, 0.91, 0.15],
+                ];
+
+                let x = t * (STOPS.len() - 1) as f32;
+                let i = (x as usize).min(STOPS.len() - 2);
+                let f = x - i as f32;
+                [0, 1, 2].map(|c| STOPS[i][c] + (STOPS[i + 1][c] - STOPS[i][c]) * f)
+            }
+        };
+
+        rgb.map(|channel| (channel * 255.0).round() as u8)
+    }
+}
+
+
+#[derive(Debug)]
+pub enum HeatmapError {
+    Io(io::Error),
+    Png(png::EncodingError),
+    Shape,
+}
+
+impl fmt::Display for HeatmapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeatmapError::Io(err) => write!(f, "heatmap io error: {err}"),
+            HeatmapError::Png(err) => write!(f, "heatmap png error: {err}"),
+            HeatmapError::Shape => write!(f, "heatmap values do not match its size"),
+        }
+    }
+}
+
+impl std::error::Error for HeatmapError {}
+
+impl From<io::Error> for HeatmapError {
+    fn from(err: io::Error) -> Self {
+        HeatmapError::Io(err)
+    }
+}
+
+impl From<png::EncodingError> for HeatmapError {
+    fn from(err: png::EncodingError) -> Self {
+        HeatmapError::Png(err)
+    }
+}
+
+
+// row-major values drawn as an rgb png, one pixel per value unless scaled, non-finite values are gray
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    pub width: u32,
+    pub height: u32,
+    pub values: Vec<f32>,
+    // values mapped to the ends of the colormap, None for the finite min and max
+    pub range: Option<(f32, f32)>,
+    pub colormap: Colormap,
+    // pixels per value along each axis
+    pub scale: u32,
+}
+
+impl Heatmap {
+    pub fn new(width: u32, height: u32, values: Vec<f32>) -> Self {
+        Self {
+            width,
+            height,
+            values,
+            range: None,
+            colormap: Colormap::default(),
+            scale: 1,
+        }
+    }
+
+    // node values, -1 blue to 1 red
+    pub fn from_snapshot(snapshot: &FieldSnapshot) -> Self {
+        Self::new(snapshot.width, snapshot.height, snapshot.values()).with_range(-1.0, 1.0)
+    }
+
+    pub fn with_range(mut self, min: f32, max: f32) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+
+    pub fn with_colormap(mut self, colormap: Colormap) -> Self {
+        self.colormap = colormap;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn value_range(&self) -> (f32, f32) {
+        self.range.unwrap_or_else(|| {
+            self.values
+                .iter()
+                .copied()
+                .filter(|value| value.is_finite())
+                .fold((f32::MAX, f32::MIN), |(min, max), value| (min.min(value), max.max(value)))
+        })
+    }
+
+    // rgb8 pixels, row-major
+    pub fn pixels(&self) -> Vec<u8> {
+        let (min, max) = self.value_range();
+        let span = if max > min { max - min } else { 1.0 };
+
+        let (width, height, scale) = (self.width as usize, self.height as usize, self.scale as usize);
+        let mut pixels = Vec::with_capacity(width * height * scale * scale * 3);
+
+        for y in 0..height * scale {
+            for x in 0..width * scale {
+                let value = self.values[(y / scale) * width + x / scale];
+                match value.is_finite() {
+                    true => pixels.extend(self.colormap.color((value - min) / span)),
+                    false => pixels.extend(MISSING_COLOR),
+                }
+            }
+        }
+
+        pixels
+    }
+
+    pub fn write_png(&self, writer: impl Write) -> Result<(), HeatmapError> {
+        if self.values.len() != (self.width * self.height) as usize {
+            return Err(HeatmapError::Shape);
+        }
+
+        let mut encoder = png::Encoder::new(writer, self.width * self.scale, self.height * self.scale);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels())?;
+        writer.finish()?;
+
+        Ok(())
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Result<(), HeatmapError> {
+        self.write_png(BufWriter::new(File::create(path)?))
+    }
+}
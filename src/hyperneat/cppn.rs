@@ -0,0 +1,220 @@
+use std::{
+    collections::HashMap,
+    fmt,
+};
+
+use bevy::{
+    prelude::*,
+    render::render_resource::ShaderType,
+};
+
+use crate::evolution::{
+    Genome,
+    NodeKind,
+};
+
+
+// (x1, y1, x2, y2, distance, bias), the receiving node first and the sending node second
+pub const CPPN_INPUTS: usize = 6;
+// (weight, uaf a, b, c, d)
+pub const CPPN_OUTPUTS: usize = 5;
+// each invocation of cppn.wgsl keeps every node value in a local array
+pub const MAX_CPPN_NODES: usize = 64;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CppnError {
+    Shape {
+        inputs: usize,
+        outputs: usize,
+    },
+    Cycle,
+    TooManyNodes {
+        nodes: usize,
+        capacity: usize,
+    },
+}
+
+impl fmt::Display for CppnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CppnError::Shape { inputs, outputs } => write!(
+                f,
+                "cppn genome has {inputs} inputs and {outputs} outputs, expected {CPPN_INPUTS} and {CPPN_OUTPUTS}",
+            ),
+            CppnError::Cycle => write!(f, "cppn genome is not feed-forward"),
+            CppnError::TooManyNodes { nodes, capacity } => write!(f, "cppn genome has {nodes} nodes, at most {capacity} are supported"),
+        }
+    }
+}
+
+impl std::error::Error for CppnError {}
+
+
+// lays out the same as `CppnNode` in cppn.wgsl
+#[derive(Clone, Copy, Debug, Default, PartialEq, ShaderType)]
+pub struct CppnNode {
+    pub uaf: Vec4,
+    pub e: f32,
+    pub first_link: u32,
+    pub link_count: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, ShaderType)]
+pub struct CppnLink {
+    // index of the sending node in the program
+    pub source: u32,
+    pub weight: f32,
+}
+
+
+// a genome flattened for the gpu: inputs first, then hidden nodes in evaluation order, outputs last
+//   each node's incoming links are contiguous in `links`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CppnProgram {
+    pub nodes: Vec<CppnNode>,
+    pub links: Vec<CppnLink>,
+}
+
+impl CppnProgram {
+    pub fn compile(genome: &Genome) -> Result<Self, CppnError> {
+        if genome.inputs != CPPN_INPUTS || genome.outputs != CPPN_OUTPUTS {
+            return Err(CppnError::Shape {
+                inputs: genome.inputs,
+                outputs: genome.outputs,
+            });
+        }
+
+        let hidden = genome.hidden_order().ok_or(CppnError::Cycle)?;
+        let ids: Vec<u32> = (0..CPPN_INPUTS as u32)
+            .chain(hidden)
+            .chain((CPPN_INPUTS..CPPN_INPUTS + CPPN_OUTPUTS).map(|id| id as u32))
+            .collect();
+
+        if ids.len() > MAX_CPPN_NODES {
+            return Err(CppnError::TooManyNodes {
+                nodes: ids.len(),
+                capacity: MAX_CPPN_NODES,
+            });
+        }
+
+        let index: HashMap<u32, u32> = ids
+            .iter()
+            .enumerate()
+            .map(|(index, &id)| (id, index as u32))
+            .collect();
+
+        let mut program = Self::default();
+        for &id in &ids {
+            let node = genome.node(id);
+            let first_link = program.links.len() as u32;
+
+            if node.is_some_and(|node| node.kind != NodeKind::Input) {
+                program.links.extend(
+                    genome.enabled_connections()
+                        .filter(|connection| connection.to == id)
+                        .filter_map(|connection| Some(CppnLink {
+                            source: *index.get(&connection.from)?,
+                            weight: connection.weight,
+                        }))
+                );
+            }
+
+            let uaf = node.map(|node| node.uaf).unwrap_or_default();
+            program.nodes.push(CppnNode {
+                uaf: uaf.texel(),
+                e: uaf.e,
+                first_link,
+                link_count: program.links.len() as u32 - first_link,
+            });
+        }
+
+        Ok(program)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::{
+        rngs::StdRng,
+        SeedableRng,
+    };
+
+    use super::*;
+    use crate::{
+        evolution::{
+            ConnectionGene,
+            Innovations,
+            MutationConfig,
+        },
+        uaf::Uaf,
+    };
+
+    fn cppn(hidden: usize, seed: u64) -> Genome {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut innovations = Innovations::new(CPPN_INPUTS, CPPN_OUTPUTS);
+        let mut genome = Genome::minimal(CPPN_INPUTS, CPPN_OUTPUTS, Uaf::IDENTITY, &mut innovations, &MutationConfig::default(), &mut rng);
+
+        while genome.hidden_count() < hidden {
+            genome.add_node(&mut innovations, &mut rng);
+        }
+
+        genome
+    }
+
+    #[test]
+    fn compile_orders_inputs_hidden_then_outputs() {
+        let genome = cppn(4, 5);
+        let program = CppnProgram::compile(&genome).unwrap();
+
+        assert_eq!(program.nodes.len(), CPPN_INPUTS + 4 + CPPN_OUTPUTS);
+
+        // inputs have no links, every other node only reads nodes before it
+        for (index, node) in program.nodes.iter().enumerate() {
+            let links = &program.links[node.first_link as usize..(node.first_link + node.link_count) as usize];
+
+            if index < CPPN_INPUTS {
+                assert!(links.is_empty());
+            }
+            assert!(links.iter().all(|link| (link.source as usize) < index), "node {index} reads ahead");
+        }
+
+        // links are contiguous per node
+        let total: u32 = program.nodes.iter().map(|node| node.link_count).sum();
+        assert_eq!(total as usize, program.links.len());
+    }
+
+    #[test]
+    fn compile_matches_the_enabled_connections() {
+        let mut genome = cppn(2, 9);
+        genome.connections[0].enabled = false;
+        let program = CppnProgram::compile(&genome).unwrap();
+
+        assert_eq!(program.links.len(), genome.enabled_connections().count());
+    }
+
+    #[test]
+    fn compile_rejects_bad_genomes() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let wrong_shape = Genome::minimal(2, 1, Uaf::IDENTITY, &mut Innovations::new(2, 1), &MutationConfig::default(), &mut rng);
+        assert!(matches!(CppnProgram::compile(&wrong_shape), Err(CppnError::Shape { inputs: 2, outputs: 1 })));
+
+        let mut cyclic = cppn(2, 4);
+        let hidden = cyclic.hidden_order().unwrap();
+        let innovation = cyclic.connections.last().unwrap().innovation;
+        for (offset, (from, to)) in [(hidden[0], hidden[1]), (hidden[1], hidden[0])].into_iter().enumerate() {
+            cyclic.connections.push(ConnectionGene {
+                innovation: innovation + 1 + offset as u64,
+                from,
+                to,
+                weight: 1.0,
+                enabled: true,
+            });
+        }
+        assert_eq!(CppnProgram::compile(&cyclic), Err(CppnError::Cycle));
+
+        let large = cppn(MAX_CPPN_NODES, 2);
+        assert!(matches!(CppnProgram::compile(&large), Err(CppnError::TooManyNodes { .. })));
+    }
+}
@@ -0,0 +1,144 @@
+#import rusty_automata::automata                automata_uniforms, edge_offset, init_state, set_edge, wrap_location, Edge
+#import rusty_automata::uaf                     fUAF
+
+
+struct CppnNode {
+    uaf: vec4<f32>,
+    e: f32,
+    first_link: u32,
+    link_count: u32,
+};
+
+struct CppnLink {
+    source: u32,
+    weight: f32,
+};
+
+struct HyperNeatUniforms {
+    // a, b, c and d of the neat field's `init_uaf`
+    init_uaf: vec4<f32>,
+    node_count: u32,
+    weight_threshold: f32,
+    uaf_scale: f32,
+};
+
+@group(1) @binding(0)
+var uaf_activations: texture_storage_2d<rgba32float, read_write>;
+
+@group(1) @binding(1)
+var<uniform> hyperneat_uniforms: HyperNeatUniforms;
+
+@group(1) @binding(2)
+var<storage, read> cppn_nodes: array<CppnNode>;
+
+@group(1) @binding(3)
+var<storage, read> cppn_links: array<CppnLink>;
+
+// must match `MAX_CPPN_NODES`, `CPPN_INPUTS` and `CPPN_OUTPUTS` in cppn.rs
+const MAX_CPPN_NODES: u32 = 64u;
+const CPPN_INPUTS: u32 = 6u;
+const CPPN_OUTPUTS: u32 = 5u;
+
+
+@compute @workgroup_size(4, 4, 1)
+fn init(
+    @builtin(global_invocation_id) invocation_id: vec3<u32>,
+) {
+    let location = vec2<i32>(i32(invocation_id.x), i32(invocation_id.y));
+    init_substrate(location);
+}
+
+
+struct CppnOutput {
+    weight: f32,
+    uaf: vec4<f32>,
+};
+
+// nodes are stored in evaluation order, inputs first and outputs last
+fn query_cppn(
+    receiver: vec2<f32>,
+    sender: vec2<f32>,
+) -> CppnOutput {
+    var values: array<f32, 64>;
+    values[0] = receiver.x;
+    values[1] = receiver.y;
+    values[2] = sender.x;
+    values[3] = sender.y;
+    values[4] = distance(receiver, sender);
+    values[5] = 1.0;
+
+    let node_count = clamp(hyperneat_uniforms.node_count, CPPN_INPUTS + CPPN_OUTPUTS, MAX_CPPN_NODES);
+    for (var i = CPPN_INPUTS; i < node_count; i = i + 1u) {
+        let node = cppn_nodes[i];
+
+        var x = 0.0;
+        for (var j = 0u; j < node.link_count; j = j + 1u) {
+            let link = cppn_links[node.first_link + j];
+            x += values[min(link.source, MAX_CPPN_NODES - 1u)] * link.weight;
+        }
+
+        values[i] = fUAF(x, node.uaf.x, node.uaf.y, node.uaf.z, node.uaf.w, node.e);
+    }
+
+    let outputs = node_count - CPPN_OUTPUTS;
+    return CppnOutput(
+        values[outputs],
+        vec4<f32>(
+            values[outputs + 1u],
+            values[outputs + 2u],
+            values[outputs + 3u],
+            values[outputs + 4u],
+        ),
+    );
+}
+
+// -1..1 across the field, locations past the edge are not wrapped so distances stay local
+fn substrate_coordinates(
+    location: vec2<i32>,
+) -> vec2<f32> {
+    let field_size = vec2<f32>(f32(automata_uniforms.width), f32(automata_uniforms.height));
+    return (vec2<f32>(location) + 0.5) / field_size * 2.0 - 1.0;
+}
+
+// weights under the threshold are not expressed, the rest are rescaled to the full weight range
+fn express_weight(
+    weight: f32,
+) -> f32 {
+    let w = clamp(weight, -1.0, 1.0);
+    let threshold = hyperneat_uniforms.weight_threshold;
+
+    if (abs(w) <= threshold) {
+        return 0.0;
+    }
+
+    return sign(w) * (abs(w) - threshold) / (1.0 - threshold) * automata_uniforms.max_edge_weight;
+}
+
+
+// the edge locations are the ones `init_edges` would pick, the cppn decides their weights
+//   a node's own uaf is queried with itself as the sending node
+fn init_substrate(
+    location: vec2<i32>,
+) {
+    let receiver = substrate_coordinates(location);
+
+    let uaf = hyperneat_uniforms.init_uaf + query_cppn(receiver, receiver).uaf * hyperneat_uniforms.uaf_scale;
+    textureStore(uaf_activations, location, uaf);
+
+    for (var i = 0u; i < automata_uniforms.edge_count; i = i + 1u) {
+        let from_location = location + edge_offset(location, i);
+        let output = query_cppn(receiver, substrate_coordinates(from_location));
+
+        set_edge(
+            location,
+            i,
+            Edge(
+                wrap_location(from_location),
+                express_weight(output.weight),
+                0.0,
+            )
+        );
+    }
+
+    init_state(location);
+}
@@ -0,0 +1,434 @@
+use std::{
+    borrow::Cow,
+    sync::Mutex,
+};
+
+use bevy::{
+    asset::{
+        load_internal_asset,
+        HandleUntyped,
+    },
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        extract_resource::{
+            ExtractResource,
+            ExtractResourcePlugin,
+        },
+        render_asset::RenderAssets,
+        renderer::{
+            RenderContext,
+            RenderDevice,
+            RenderQueue,
+        },
+        render_graph::{
+            self,
+            RenderGraph,
+        },
+        render_resource::{
+            BindGroup,
+            BindGroupDescriptor,
+            BindGroupEntry,
+            BindGroupLayout,
+            BindGroupLayoutDescriptor,
+            BindGroupLayoutEntry,
+            BindingResource,
+            BindingType,
+            BufferBindingType,
+            CachedComputePipelineId,
+            CachedPipelineState,
+            ComputePassDescriptor,
+            ComputePipelineDescriptor,
+            PipelineCache,
+            ShaderStages,
+            ShaderType,
+            StorageBuffer,
+            StorageTextureAccess,
+            TextureFormat,
+            TextureViewDimension,
+            UniformBuffer,
+        },
+        Render,
+        RenderApp,
+        RenderSet,
+    },
+};
+
+use crate::{
+    automata::{
+        AutomataBindGroup,
+        AutomataField,
+        AutomataPipeline,
+        EdgeInit,
+    },
+    evolution::{
+        EvolutionConfig,
+        Genome,
+        Population,
+    },
+    neat::NeatField,
+};
+
+mod cppn;
+pub use cppn::*;
+
+
+const CPPN_SHADER_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 37190455218260);
+const WORKGROUP_SIZE: u32 = 4;
+
+
+// initializes fields with `EdgeInit::Cppn` from the `HyperNeatField` genome, add after the `NeatPlugin`
+//   the field is treated as a hyperneat substrate: the cppn is queried per (node, from node) pair for
+//   edge weights and per node for uaf parameters, so a small genome describes the whole field
+#[derive(Default)]
+pub struct HyperNeatPlugin;
+
+impl Plugin for HyperNeatPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            CPPN_SHADER_HANDLE,
+            "cppn.wgsl",
+            Shader::from_wgsl
+        );
+
+        app.add_plugins(ExtractResourcePlugin::<HyperNeatField>::default());
+
+        app.add_systems(Update, restart_substrate);
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.add_systems(
+            Render,
+            (
+                prepare_hyperneat_buffers.in_set(RenderSet::Prepare),
+                queue_hyperneat_bind_group.in_set(RenderSet::Queue),
+            )
+        );
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node("hyperneat", HyperNeatNode::default());
+        render_graph.add_node_edge("neat", "hyperneat");
+        render_graph.add_node_edge(
+            "hyperneat",
+            bevy::render::main_graph::node::CAMERA_DRIVER,
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.init_resource::<HyperNeatPipeline>();
+        render_app.init_resource::<HyperNeatBuffers>();
+    }
+}
+
+
+// the cppn of the field, any change restarts the field as a substrate of the same size and parameters
+#[derive(Resource, Clone, Debug, ExtractResource)]
+pub struct HyperNeatField {
+    pub genome: Genome,
+    // cppn weight outputs within ±threshold express no edge, the rest are rescaled to ±max_edge_weight
+    pub weight_threshold: f32,
+    // cppn uaf outputs are scaled and added to the neat field's `init_uaf`
+    pub uaf_scale: f32,
+}
+
+impl HyperNeatField {
+    pub fn new(genome: Genome) -> Self {
+        Self {
+            genome,
+            weight_threshold: 0.2,
+            uaf_scale: 1.0,
+        }
+    }
+
+    pub fn with_weight_threshold(mut self, weight_threshold: f32) -> Self {
+        self.weight_threshold = weight_threshold;
+        self
+    }
+
+    pub fn with_uaf_scale(mut self, uaf_scale: f32) -> Self {
+        self.uaf_scale = uaf_scale;
+        self
+    }
+
+    pub fn program(&self) -> Result<CppnProgram, CppnError> {
+        CppnProgram::compile(&self.genome)
+    }
+}
+
+fn restart_substrate(
+    mut commands: Commands,
+    hyperneat: Option<Res<HyperNeatField>>,
+    automata: Option<Res<AutomataField>>,
+    mut images: ResMut<Assets<Image>>,
+    mut textures: Query<&mut Handle<Image>>,
+) {
+    let (Some(hyperneat), Some(automata)) = (hyperneat, automata) else {
+        return;
+    };
+
+    if !hyperneat.is_changed() {
+        return;
+    }
+
+    if let Err(err) = hyperneat.program() {
+        error!("not restarting the field: {err}");
+        return;
+    }
+
+    let automata_field = AutomataField::from_parameters(automata.size(), automata.parameters(), &mut images)
+        .with_edge_init(EdgeInit::Cppn)
+        .with_plasticity(automata.plasticity());
+
+    for mut texture in &mut textures {
+        if *texture == automata.nodes {
+            *texture = automata_field.nodes.clone();
+        }
+    }
+
+    commands.insert_resource(automata_field);
+}
+
+// a population of genomes shaped for `HyperNeatField`
+pub fn cppn_population(config: EvolutionConfig, seed: u64) -> Population {
+    Population::new(config, CPPN_INPUTS, CPPN_OUTPUTS, seed)
+}
+
+
+#[derive(Clone, Default, ShaderType)]
+struct HyperNeatUniform {
+    init_uaf: Vec4,
+    node_count: u32,
+    weight_threshold: f32,
+    uaf_scale: f32,
+}
+
+#[derive(Resource, Default)]
+struct HyperNeatBuffers {
+    uniform: UniformBuffer<HyperNeatUniform>,
+    nodes: StorageBuffer<Vec<CppnNode>>,
+    links: StorageBuffer<Vec<CppnLink>>,
+    // the current genome compiled, an invalid genome leaves fields uninitialized
+    ready: bool,
+}
+
+fn prepare_hyperneat_buffers(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffers: ResMut<HyperNeatBuffers>,
+    hyperneat: Option<Res<HyperNeatField>>,
+    neat_field: Option<Res<NeatField>>,
+) {
+    let (Some(hyperneat), Some(neat_field)) = (hyperneat, neat_field) else {
+        return;
+    };
+
+    if hyperneat.is_changed() {
+        let program = match hyperneat.program() {
+            Ok(program) => program,
+            Err(err) => {
+                error!("{err}");
+                buffers.ready = false;
+                return;
+            }
+        };
+
+        buffers.uniform.get_mut().node_count = program.nodes.len() as u32;
+
+        // storage bindings can't be empty
+        let mut links = program.links;
+        if links.is_empty() {
+            links.push(CppnLink::default());
+        }
+
+        buffers.nodes.set(program.nodes);
+        buffers.nodes.write_buffer(&render_device, &render_queue);
+        buffers.links.set(links);
+        buffers.links.write_buffer(&render_device, &render_queue);
+        buffers.ready = true;
+    }
+
+    let uniform = buffers.uniform.get_mut();
+    uniform.init_uaf = neat_field.init_uaf.texel();
+    uniform.weight_threshold = hyperneat.weight_threshold.clamp(0.0, 0.99);
+    uniform.uaf_scale = hyperneat.uaf_scale;
+    buffers.uniform.write_buffer(&render_device, &render_queue);
+}
+
+
+#[derive(Resource)]
+pub struct HyperNeatBindGroup(pub BindGroup);
+
+fn queue_hyperneat_bind_group(
+    mut commands: Commands,
+    pipeline: Res<HyperNeatPipeline>,
+    gpu_images: Res<RenderAssets<Image>>,
+    neat_field: Option<Res<NeatField>>,
+    render_device: Res<RenderDevice>,
+    buffers: Res<HyperNeatBuffers>,
+) {
+    let uaf_activations = neat_field.and_then(|neat_field| gpu_images.get(&neat_field.uaf_activations));
+    let (true, Some(uaf_activations)) = (buffers.ready, uaf_activations) else {
+        commands.remove_resource::<HyperNeatBindGroup>();
+        return;
+    };
+
+    let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&uaf_activations.texture_view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: buffers.uniform.binding().unwrap(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: buffers.nodes.binding().unwrap(),
+            },
+            BindGroupEntry {
+                binding: 3,
+                resource: buffers.links.binding().unwrap(),
+            },
+        ],
+    });
+
+    commands.insert_resource(HyperNeatBindGroup(bind_group));
+}
+
+
+#[derive(Resource)]
+pub struct HyperNeatPipeline {
+    pub bind_group_layout: BindGroupLayout,
+    init_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for HyperNeatPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let automata = world.resource::<AutomataPipeline>();
+
+        let storage_entry = |binding| BindGroupLayoutEntry {
+            binding,
+            visibility: ShaderStages::COMPUTE,
+            ty: BindingType::Buffer {
+                ty: BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        let bind_group_layout =
+            world
+                .resource::<RenderDevice>()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("hyperneat bind group layout"),
+                    entries: &[
+                        BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::StorageTexture {
+                                access: StorageTextureAccess::ReadWrite,
+                                format: TextureFormat::Rgba32Float,
+                                view_dimension: TextureViewDimension::D2,
+                            },
+                            count: None,
+                        },
+                        BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        storage_entry(2),
+                        storage_entry(3),
+                    ],
+                });
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let init_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: None,
+            layout: vec![automata.bind_group_layout.clone(), bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader: CPPN_SHADER_HANDLE.typed(),
+            shader_defs: vec![],
+            entry_point: Cow::from("init"),
+        });
+
+        HyperNeatPipeline {
+            bind_group_layout,
+            init_pipeline,
+        }
+    }
+}
+
+
+// writes the substrate right after every neat init dispatch, which resets the uaf parameters
+//   if the cppn pipeline wasn't ready during init it writes late and restarts the node states too
+#[derive(Default)]
+struct HyperNeatNode {
+    // nodes texture of the field last written, `run` only has shared access to the node
+    written: Mutex<Option<Handle<Image>>>,
+}
+
+impl render_graph::Node for HyperNeatNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(automata) = world.get_resource::<AutomataField>() else {
+            return Ok(());
+        };
+        let automata_pipeline = world.resource::<AutomataPipeline>();
+
+        if automata.edge_init() != EdgeInit::Cppn || automata_pipeline.resumed {
+            return Ok(());
+        }
+        let Some(step) = automata_pipeline.step else {
+            return Ok(());
+        };
+
+        let mut written = self.written.lock().unwrap();
+        if step > 0 && written.as_ref() == Some(&automata.nodes) {
+            return Ok(());
+        }
+
+        let (Some(automata_bind_group), Some(hyperneat_bind_group)) = (
+            world.get_resource::<AutomataBindGroup>(),
+            world.get_resource::<HyperNeatBindGroup>(),
+        ) else {
+            return Ok(());
+        };
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<HyperNeatPipeline>();
+        let CachedPipelineState::Ok(_) = pipeline_cache.get_compute_pipeline_state(pipeline.init_pipeline) else {
+            return Ok(());
+        };
+        let init_pipeline = pipeline_cache
+            .get_compute_pipeline(pipeline.init_pipeline)
+            .unwrap();
+
+        let mut pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor::default());
+
+        pass.set_bind_group(0, &automata_bind_group.0, &[]);
+        pass.set_bind_group(1, &hyperneat_bind_group.0, &[]);
+        pass.set_pipeline(init_pipeline);
+        pass.dispatch_workgroups(automata_pipeline.width / WORKGROUP_SIZE, automata_pipeline.height / WORKGROUP_SIZE, 1);
+
+        *written = Some(automata.nodes.clone());
+
+        Ok(())
+    }
+}
@@ -1,6 +1,12 @@
+use std::time::Duration;
+
 use bevy::{
     prelude::*,
-    app::AppExit,
+    asset::ChangeWatcher,
+    app::{
+        AppExit,
+        ScheduleRunnerPlugin,
+    },
     diagnostic::{
         DiagnosticsStore,
         FrameTimeDiagnosticsPlugin,
@@ -8,10 +14,14 @@ use bevy::{
     render::{
         RenderPlugin,
         settings::{
+            Backends,
+            PowerPreference,
             WgpuLimits,
             WgpuSettings,
         },
     },
+    window::ExitCondition,
+    winit::WinitPlugin,
 };
 // TODO: update to latest framepace
 // use bevy_framepace::{
@@ -21,16 +31,31 @@ use bevy::{
 
 // TODO: move to crate project structure
 pub mod automata;
+pub mod batch;
+pub mod checkpoint;
+pub mod connectome;
+pub mod criticality;
 pub mod editor;
+pub mod environment;
+pub mod evolution;
+pub mod experiment;
+pub mod heatmap;
+pub mod hyperneat;
 pub mod neat;
 pub mod noise;
+pub mod npy;
 pub mod plot;
+pub mod readback;
+pub mod reservoir;
+pub mod sweep;
+pub mod trace;
 pub mod uaf;
 pub mod utils;
 
 
 pub struct RustyAutomataApp {
     editor: bool,
+    headless: bool,
     esc_close: bool,
     //fps_limit: f64,
     show_fps: bool,
@@ -43,6 +68,7 @@ impl Default for RustyAutomataApp {
     fn default() -> RustyAutomataApp {
         RustyAutomataApp {
             editor: true,
+            headless: false,
             esc_close: true,
             //fps_limit: 0.0,
             show_fps: true,
@@ -53,34 +79,85 @@ impl Default for RustyAutomataApp {
     }
 }
 
+impl RustyAutomataApp {
+    // no window or winit event loop, the compute graph runs as fast as the adapter allows
+    //   exits on `StepLimitReached`, so set a `StepLimit`
+    //   without a gpu, vulkan falls back to a software adapter such as lavapipe
+    //   gl is skipped unless `WGPU_BACKEND` asks for it, it lacks read-write rgba32float storage textures
+    pub fn headless() -> Self {
+        Self {
+            editor: false,
+            headless: true,
+            esc_close: false,
+            show_fps: false,
+            ..default()
+        }
+    }
+}
+
 impl Plugin for RustyAutomataApp {
     fn build(&self, app: &mut App) {
         app.insert_resource(ClearColor(Color::rgb_u8(112, 48, 48)));
-        app.add_plugins(
-            DefaultPlugins
-            .set(ImagePlugin::default_nearest())
-            .set(RenderPlugin {
-                wgpu_settings: WgpuSettings {
-                    limits: WgpuLimits {
-                        //max_texture_dimension_2d: 16384, // TODO: use 2d texture array for tiling fields
+
+        if self.headless {
+            app.add_plugins(
+                DefaultPlugins
+                .set(ImagePlugin::default_nearest())
+                .set(RenderPlugin {
+                    wgpu_settings: WgpuSettings {
+                        backends: match std::env::var_os("WGPU_BACKEND") {
+                            Some(_) => WgpuSettings::default().backends,
+                            None => Some(Backends::PRIMARY),
+                        },
+                        // prefers a discrete gpu on multi gpu machines, software adapters are still picked when nothing else is found
+                        power_preference: PowerPreference::HighPerformance,
                         ..Default::default()
-                    },
-                    ..Default::default()
-                }
-            })
-            .set(WindowPlugin {
-                primary_window: Some(Window {
-                    fit_canvas_to_parent: false,
-                    mode: bevy::window::WindowMode::Windowed,
-                    present_mode: bevy::window::PresentMode::AutoVsync,
-                    prevent_default_event_handling: false,
-                    resolution: (self.width, self.height).into(),
-                    title: self.name.clone(),
+                    }
+                })
+                .set(WindowPlugin {
+                    primary_window: None,
+                    exit_condition: ExitCondition::DontExit,
+                    close_when_requested: false,
+                })
+                .disable::<WinitPlugin>()
+            );
+            app.add_plugins(ScheduleRunnerPlugin::run_loop(Duration::ZERO));
+
+            app.add_event::<automata::StepLimitReached>();
+            app.add_systems(Update, exit_on_step_limit);
+        } else {
+            app.add_plugins(
+                DefaultPlugins
+                .set(ImagePlugin::default_nearest())
+                .set(AssetPlugin {
+                    // reloads changed assets such as experiment configs
+                    watch_for_changes: ChangeWatcher::with_delay(Duration::from_millis(200)),
                     ..default()
-                }),
-                ..default()
-            })
-        );
+                })
+                .set(RenderPlugin {
+                    wgpu_settings: WgpuSettings {
+                        limits: WgpuLimits {
+                            //max_texture_dimension_2d: 16384, // TODO: use 2d texture array for tiling fields
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    }
+                })
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        fit_canvas_to_parent: false,
+                        mode: bevy::window::WindowMode::Windowed,
+                        present_mode: bevy::window::PresentMode::AutoVsync,
+                        prevent_default_event_handling: false,
+                        resolution: (self.width, self.height).into(),
+                        title: self.name.clone(),
+                        ..default()
+                    }),
+                    ..default()
+                })
+            );
+        }
+
         app.add_plugins(noise::NoisePlugin);
 
         if self.editor {
@@ -119,6 +196,17 @@ pub fn esc_close(
 }
 
 
+pub fn exit_on_step_limit(
+    mut reached: EventReader<automata::StepLimitReached>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Some(limit) = reached.iter().last() {
+        info!("step limit reached at step {}", limit.step);
+        exit.send(AppExit);
+    }
+}
+
+
 // fn fps_throttle_setup(
 //     mut settings: ResMut<FramepaceSettings>,
 //     fps: f64,
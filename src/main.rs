@@ -1,60 +1,718 @@
+use std::path::{
+    Path,
+    PathBuf,
+};
+
 use bevy::{
+    app::AppExit,
     prelude::*,
+    render::{
+        render_resource::Extent3d,
+        settings::WgpuLimits,
+    },
 };
+use clap::{
+    Args,
+    Parser,
+    Subcommand,
+    ValueEnum,
+};
+use serde::Serialize;
 
 use rusty_automata::{
     RustyAutomataApp,
+    automata::{
+        AutomataField,
+        AutomataParameters,
+        AutomataPlugin,
+        EdgeInit,
+    },
+    batch::{
+        BatchFinished,
+        BatchPlugin,
+        BatchQueue,
+        FieldRun,
+        FieldRunFinished,
+        Manifest,
+    },
+    checkpoint::Checkpoint,
+    environment::EnvironmentKind,
+    experiment::{
+        ExperimentConfig,
+        FieldInit,
+        OutputConfig,
+    },
+    evolution::{
+        Descriptor,
+        DescriptorAxis,
+        EvolutionDriver,
+        EvolutionFile,
+        EvolutionFinished,
+        EvolutionPlugin,
+        EvolutionSettings,
+        GenerationFinished,
+        Objective,
+        Population,
+        RunHistory,
+        SearchMode,
+    },
+    heatmap::Heatmap,
+    hyperneat::HyperNeatPlugin,
+    neat::{
+        NeatField,
+        NeatPlugin,
+    },
+    npy::{
+        FieldArrays,
+        FieldMetadata,
+    },
+    sweep::{
+        Sweep,
+        SweepAxis,
+        SweepConfig,
+        SweepFinished,
+        SweepParameter,
+        SweepPlugin,
+        SweepSampling,
+    },
     utils::setup_hooks,
 };
 
 
-fn example_app() {
-    App::new()
-        .add_plugins(RustyAutomataApp::default())
-        .add_systems(Startup, help_display_setup)
-        .run();
+/// batch experiments without a window, every command writes its results and a manifest.json to --out
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// runs one field headless for --steps and saves its final state
+    Run(RunArgs),
+    /// runs every combination of the listed parameters for several seeds
+    Sweep(SweepArgs),
+    /// converts a checkpoint to npy arrays or a png, no gpu needed
+    Export(ExportArgs),
+    /// evolves hyperneat cppns on the field
+    Evolve(EvolveArgs),
+}
 
-pub fn main() {
-    setup_hooks();
-    example_app();
+
+#[derive(Args, Clone, Debug, Serialize)]
+struct FieldArgs {
+    #[arg(long, default_value_t = 256)]
+    width: u32,
+    #[arg(long, default_value_t = 256)]
+    height: u32,
+    #[arg(long, default_value_t = 1000)]
+    steps: u64,
+    /// steps between the activity and spectrum samples, avalanches are recorded every step
+    #[arg(long, default_value_t = 10)]
+    sample_interval: u64,
 }
 
+#[derive(Args, Clone, Debug, Serialize)]
+struct RunArgs {
+    /// an experiment .ron or .toml, replaces the field and edge arguments
+    #[arg(long)]
+    config: Option<PathBuf>,
+    /// defaults to the output dir of the config
+    #[arg(long)]
+    out: Option<PathBuf>,
+    #[command(flatten)]
+    field: FieldArgs,
+    #[arg(long, default_value_t = 25)]
+    edge_count: u32,
+    #[arg(long, default_value_t = 15.0)]
+    max_radius: f32,
+    #[arg(long, default_value_t = 16.0)]
+    max_edge_weight: f32,
+    #[arg(long, default_value_t = 1.0)]
+    seed: f32,
+}
 
+#[derive(Args, Clone, Debug, Serialize)]
+struct SweepArgs {
+    #[arg(long)]
+    out: PathBuf,
+    /// an experiment .ron or .toml for the field size, uaf and steps
+    #[arg(long)]
+    config: Option<PathBuf>,
+    #[command(flatten)]
+    field: FieldArgs,
+    /// grid values, a latin hypercube samples between the smallest and largest
+    #[arg(long, value_delimiter = ',', default_value = "25")]
+    edge_count: Vec<f32>,
+    #[arg(long, value_delimiter = ',', default_value = "5,10,15,20,25")]
+    max_radius: Vec<f32>,
+    #[arg(long, value_delimiter = ',', default_value = "4,8,16,32")]
+    max_edge_weight: Vec<f32>,
+    #[arg(long, value_enum, default_value = "grid")]
+    sampling: SamplingArg,
+    /// points of a latin hypercube
+    #[arg(long, default_value_t = 32)]
+    samples: usize,
+    /// seeds 1..=seeds for every point
+    #[arg(long, default_value_t = 1)]
+    seeds: u32,
+    #[arg(long, default_value_t = 0)]
+    rng_seed: u64,
+}
 
-fn help_display_setup(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-) {
-    commands.spawn(NodeBundle {
-        style: Style {
-            width: Val::Percent(100.0),
-            align_items: AlignItems::Center,
-            justify_content: JustifyContent::Center,
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+enum SamplingArg {
+    Grid,
+    Lhs,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+enum ExportFormat {
+    /// one .npy per array
+    Npy,
+    Npz,
+    /// node values, -1 blue to 1 red
+    Png,
+}
+
+#[derive(Args, Clone, Debug, Serialize)]
+struct ExportArgs {
+    #[arg(long)]
+    checkpoint: PathBuf,
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "npy,png")]
+    format: Vec<ExportFormat>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+enum ModeArg {
+    Objective,
+    Novelty,
+    MapElites,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+enum DescriptorArg {
+    MeanActivity,
+    ActivityVariability,
+    SpectralCentroid,
+    AvalancheExponent,
+    BranchingRatio,
+}
+
+impl From<DescriptorArg> for Descriptor {
+    fn from(descriptor: DescriptorArg) -> Self {
+        match descriptor {
+            DescriptorArg::MeanActivity => Descriptor::MeanActivity,
+            DescriptorArg::ActivityVariability => Descriptor::ActivityVariability,
+            DescriptorArg::SpectralCentroid => Descriptor::SpectralCentroid,
+            DescriptorArg::AvalancheExponent => Descriptor::AvalancheExponent,
+            DescriptorArg::BranchingRatio => Descriptor::BranchingRatio,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Serialize)]
+enum ObjectiveArg {
+    Xor,
+    PoleBalancing,
+    MountainCar,
+    Foraging,
+    MeanActivity,
+    ActivityVariability,
+    SpectralCentroid,
+    AvalancheExponent,
+    BranchingRatio,
+}
+
+impl From<ObjectiveArg> for Objective {
+    fn from(objective: ObjectiveArg) -> Self {
+        match objective {
+            ObjectiveArg::Xor => Objective::Environment(EnvironmentKind::Xor),
+            ObjectiveArg::PoleBalancing => Objective::Environment(EnvironmentKind::PoleBalancing),
+            ObjectiveArg::MountainCar => Objective::Environment(EnvironmentKind::MountainCar),
+            ObjectiveArg::Foraging => Objective::Environment(EnvironmentKind::Foraging),
+            ObjectiveArg::MeanActivity => Objective::Descriptor(Descriptor::MeanActivity),
+            ObjectiveArg::ActivityVariability => Objective::Descriptor(Descriptor::ActivityVariability),
+            ObjectiveArg::SpectralCentroid => Objective::Descriptor(Descriptor::SpectralCentroid),
+            ObjectiveArg::AvalancheExponent => Objective::Descriptor(Descriptor::AvalancheExponent),
+            ObjectiveArg::BranchingRatio => Objective::Descriptor(Descriptor::BranchingRatio),
+        }
+    }
+}
+
+#[derive(Args, Clone, Debug, Serialize)]
+struct EvolveArgs {
+    #[arg(long)]
+    out: PathBuf,
+    #[arg(long, default_value_t = 256)]
+    width: u32,
+    #[arg(long, default_value_t = 256)]
+    height: u32,
+    #[arg(long, default_value_t = 25)]
+    edge_count: u32,
+    #[arg(long, default_value_t = 50)]
+    generations: u64,
+    #[arg(long, default_value_t = 64)]
+    population: usize,
+    /// steps each genome's field runs before it is scored
+    #[arg(long, default_value_t = 200)]
+    steps: u64,
+    /// steps between activity and spectrum samples, novelty and avalanche descriptors read back every step
+    #[arg(long, default_value_t = 5)]
+    sample_interval: u64,
+    #[arg(long, value_enum, default_value = "objective")]
+    mode: ModeArg,
+    #[arg(long, value_enum, default_value = "activity-variability")]
+    objective: ObjectiveArg,
+    #[arg(long, value_enum, default_value = "mean-activity")]
+    map_x: DescriptorArg,
+    #[arg(long, value_enum, default_value = "spectral-centroid")]
+    map_y: DescriptorArg,
+    #[arg(long, default_value_t = 16)]
+    bins: usize,
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// continues a population.bin (and the history.json, archive.bin and map_elites.json next to it) from an earlier run
+    #[arg(long)]
+    resume: Option<PathBuf>,
+}
+
+
+// results of the running command, the manifest is saved whenever an output is added
+#[derive(Resource)]
+struct Output {
+    dir: PathBuf,
+    manifest: Manifest,
+    // final files of a single run
+    files: OutputConfig,
+}
+
+impl Output {
+    fn new(dir: &Path, manifest: Manifest) -> Self {
+        if let Err(err) = std::fs::create_dir_all(dir) {
+            error!("failed to create {}: {err}", dir.display());
+        }
+
+        Self {
+            dir: dir.to_path_buf(),
+            manifest,
+            files: OutputConfig {
+                dir: dir.to_path_buf(),
+                ..default()
+            },
+        }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    fn saved(&mut self, name: &str, result: Result<(), impl std::fmt::Display>) {
+        match result {
+            Ok(()) => {
+                if !self.manifest.outputs.iter().any(|output| output == Path::new(name)) {
+                    self.manifest.add_output(name);
+                }
+            }
+            Err(err) => error!("failed to save {name}: {err}"),
+        }
+
+        if let Err(err) = self.manifest.save(&self.dir) {
+            error!("failed to save the manifest: {err}");
+        }
+    }
+
+    fn finish(&mut self) {
+        self.manifest.finish();
+        if let Err(err) = self.manifest.save(&self.dir) {
+            error!("failed to save the manifest: {err}");
+        }
+    }
+}
+
+
+fn headless_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((
+        RustyAutomataApp::headless(),
+        AutomataPlugin,
+        NeatPlugin,
+    ));
+    app
+}
+
+fn batch_app(output: Output, runs: Vec<FieldRun>, sample_interval: u64) -> App {
+    let mut app = headless_app();
+    app.add_plugins(BatchPlugin)
+        .insert_resource(BatchQueue::new(runs).with_sample_interval(sample_interval))
+        .insert_resource(output)
+        .add_systems(Update, (save_runs, finish_batch).chain());
+    app
+}
+
+// the headless app requests the default limits, a field beyond them would panic on the gpu
+fn check_limits<'a>(runs: impl IntoIterator<Item = &'a FieldRun>) {
+    let limits = WgpuLimits::default();
+
+    for run in runs {
+        if let Err(err) = run.check_limits(&limits) {
+            eprintln!("{}: {err}", run.label);
+            std::process::exit(1);
+        }
+    }
+}
+
+
+fn run(args: RunArgs) {
+    let mut config = match &args.config {
+        Some(path) => match ExperimentConfig::load(path) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("failed to load {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => ExperimentConfig {
+            name: "run".to_string(),
+            width: Some(args.field.width),
+            height: Some(args.field.height),
+            edges: AutomataParameters {
+                edge_count: args.edge_count,
+                max_radius: args.max_radius,
+                max_edge_weight: args.max_edge_weight,
+                seed: args.seed,
+            },
+            step_limit: Some(args.field.steps),
+            output: OutputConfig {
+                sample_interval: Some(args.field.sample_interval),
+                ..default()
+            },
             ..default()
         },
-        ..default()
-    }).with_children(|parent| {
-        parent.spawn(
-            (
-                TextBundle::from_sections([
-                    TextSection::new(
-                        "cargo run --example {name}",
-                        TextStyle {
-                            font: asset_server.load("fonts/Caveat-Bold.ttf"),
-                            font_size: 60.0,
-                            color: Color::WHITE,
-                        },
-                    ),
-                ]),
-            )
-        );
-    });
-
-    commands.spawn((
-        Camera2dBundle {
+    };
+
+    if config.init != FieldInit::Random {
+        eprintln!("the run command only creates random fields, {:?} needs the ExperimentPlugin", config.init);
+        std::process::exit(1);
+    }
+
+    if let Some(out) = &args.out {
+        config.output.dir = out.clone();
+    }
+
+    let run = config.field_run(args.field.steps);
+    check_limits([&run]);
+    let sample_interval = config.output.sample_interval.unwrap_or(args.field.sample_interval);
+
+    let mut output = Output::new(&config.output.dir, Manifest::new("run", &config));
+    output.files = config.output;
+    batch_app(output, vec![run], sample_interval).run();
+}
+
+fn sweep(args: SweepArgs) {
+    let base = match &args.config {
+        Some(path) => match ExperimentConfig::load(path) {
+            Ok(config) => config.field_run(args.field.steps),
+            Err(err) => {
+                eprintln!("failed to load {}: {err}", path.display());
+                std::process::exit(1);
+            }
+        },
+        None => FieldRun {
+            label: "sweep".to_string(),
+            width: args.field.width,
+            height: args.field.height,
+            steps: args.field.steps,
             ..default()
         },
-    ));
+    };
+
+    let config = SweepConfig {
+        base,
+        axes: vec![
+            SweepAxis::new(SweepParameter::EdgeCount, args.edge_count.clone()),
+            SweepAxis::new(SweepParameter::MaxRadius, args.max_radius.clone()),
+            SweepAxis::new(SweepParameter::MaxEdgeWeight, args.max_edge_weight.clone()),
+        ],
+        sampling: match args.sampling {
+            SamplingArg::Grid => SweepSampling::Grid,
+            SamplingArg::Lhs => SweepSampling::LatinHypercube {
+                samples: args.samples,
+            },
+        },
+        seeds: args.seeds,
+        sample_interval: args.field.sample_interval,
+        rng_seed: args.rng_seed,
+    };
+
+    // an empty axis or zero lhs samples leaves nothing to run
+    if config.runs().is_empty() {
+        eprintln!("the sweep has no runs, check the axis values and --samples");
+        std::process::exit(1);
+    }
+    check_limits(&config.runs());
+
+    let output = Output::new(&args.out, Manifest::new("sweep", &config));
+
+    let mut app = headless_app();
+    app.add_plugins(SweepPlugin)
+        .insert_resource(config.queue())
+        .insert_resource(Sweep::new(config))
+        .insert_resource(output)
+        .add_systems(PostUpdate, save_sweep)
+        .run();
+}
+
+// the run table is rewritten after every run, the summary and heatmaps once the sweep has finished
+fn save_sweep(
+    mut output: ResMut<Output>,
+    sweep: Res<Sweep>,
+    queue: Res<BatchQueue>,
+    mut finished_runs: EventReader<FieldRunFinished>,
+    mut finished: EventReader<SweepFinished>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let (_, total) = queue.progress();
+
+    for run in finished_runs.iter() {
+        info!("finished run {} of {total}: {}", run.index + 1, run.run.label);
+    }
+
+    if sweep.is_changed() {
+        let saved = sweep.save_csv(output.path("results.csv"));
+        output.saved("results.csv", saved);
+    }
+
+    let Some(sweep_finished) = finished.iter().last() else {
+        return;
+    };
+
+    let saved = sweep.save_summary_csv(output.path("summary.csv"));
+    output.saved("summary.csv", saved);
+
+    if let Some((x, y)) = sweep.heatmap_axes() {
+        // a single varying parameter is drawn as one row
+        let y = y.unwrap_or_else(|| *SweepParameter::ALL.iter().find(|&&parameter| parameter != x).unwrap());
+
+        for descriptor in Descriptor::ALL {
+            let name = format!("heatmap_{}.png", descriptor.name().replace(' ', "_"));
+            let saved = sweep.save_heatmap(output.path(&name), x, y, descriptor);
+            output.saved(&name, saved);
+        }
+    }
+
+    info!("finished {} runs", sweep_finished.runs);
+    output.finish();
+    exit.send(AppExit);
+}
+
+fn save_runs(
+    mut output: ResMut<Output>,
+    queue: Res<BatchQueue>,
+    mut finished: EventReader<FieldRunFinished>,
+) {
+    let (_, total) = queue.progress();
+
+    for result in finished.iter() {
+        for (name, saved) in output.files.save(
+            &result.snapshot,
+            result.run.parameters,
+            result.run.init_uaf,
+            EdgeInit::default(),
+            result.run.plasticity,
+        ) {
+            output.saved(name, saved);
+        }
+
+        let behaviour = serde_json::to_string_pretty(&result.behaviour)
+            .map_err(|err| err.to_string())
+            .and_then(|json| std::fs::write(output.path("behaviour.json"), json).map_err(|err| err.to_string()));
+        output.saved("behaviour.json", behaviour);
+
+        info!("finished run {} of {total}: {}", result.index + 1, result.run.label);
+    }
+}
+
+fn finish_batch(
+    mut output: ResMut<Output>,
+    mut finished: EventReader<BatchFinished>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if let Some(batch) = finished.iter().last() {
+        info!("finished {} runs", batch.runs);
+        output.finish();
+        exit.send(AppExit);
+    }
+}
+
+
+fn export(args: ExportArgs) {
+    let checkpoint = match Checkpoint::load(&args.checkpoint) {
+        Ok(checkpoint) => checkpoint,
+        Err(err) => {
+            eprintln!("failed to load {}: {err}", args.checkpoint.display());
+            std::process::exit(1);
+        }
+    };
+    let snapshot = checkpoint.snapshot();
+    let arrays = FieldArrays::from_snapshot(&snapshot);
+
+    let mut output = Output::new(&args.out, Manifest::new("export", &args));
+
+    for format in &args.format {
+        match format {
+            ExportFormat::Npy => {
+                let saved = arrays.save_dir(&args.out);
+                for (name, _) in &arrays.arrays {
+                    output.saved(&format!("{name}.npy"), saved.as_ref().map(|_| ()).map_err(|err| err.to_string()));
+                }
+            }
+            ExportFormat::Npz => {
+                let metadata = FieldMetadata::new(checkpoint.parameters, std::slice::from_ref(&snapshot));
+                let saved = arrays.save_npz(output.path("field.npz"), Some(&metadata));
+                output.saved("field.npz", saved);
+            }
+            ExportFormat::Png => {
+                let saved = Heatmap::from_snapshot(&snapshot).save_png(output.path("field.png"));
+                output.saved("field.png", saved);
+            }
+        }
+    }
+
+    output.finish();
+}
+
+
+fn evolve(args: EvolveArgs) {
+    let mut settings = EvolutionSettings {
+        mode: match args.mode {
+            ModeArg::Objective => SearchMode::Objective,
+            ModeArg::Novelty => SearchMode::Novelty,
+            ModeArg::MapElites => SearchMode::MapElites,
+        },
+        objective: args.objective.into(),
+        map_x: DescriptorAxis::new(args.map_x.into(), args.bins),
+        map_y: DescriptorAxis::new(args.map_y.into(), args.bins),
+        steps: args.steps,
+        sample_interval: args.sample_interval,
+        generations: Some(args.generations),
+        seed: args.seed,
+        ..default()
+    };
+    settings.evolution.population_size = args.population;
+
+    let driver = match &args.resume {
+        Some(path) => {
+            let population = match Population::load(path) {
+                Ok(population) => population,
+                Err(err) => {
+                    eprintln!("failed to load {}: {err}", path.display());
+                    std::process::exit(1);
+                }
+            };
+            let history: RunHistory = load_saved(path.with_file_name("history.json")).unwrap_or_default();
+            let archive = load_saved(path.with_file_name("archive.bin"));
+            let map = load_saved(path.with_file_name("map_elites.json"));
+
+            settings.evolution = population.config.clone();
+            settings.generations = Some(history.len() as u64 + args.generations);
+
+            let mut driver = EvolutionDriver::resume(settings.clone(), population, history);
+            if let Some(archive) = archive {
+                driver = driver.with_archive(archive);
+            }
+            if let Some(map) = map {
+                driver = driver.with_map(map);
+            }
+            driver
+        }
+        None => EvolutionDriver::new(settings.clone()),
+    };
+
+    let mut manifest = Manifest::new("evolve", &args);
+    manifest.config = serde_json::to_value(&settings).unwrap_or_default();
+    let output = Output::new(&args.out, manifest);
+
+    let field_size = Extent3d {
+        width: args.width,
+        height: args.height,
+        depth_or_array_layers: 1,
+    };
+    let edge_count = args.edge_count;
+
+    let mut app = headless_app();
+    app.add_plugins((HyperNeatPlugin, EvolutionPlugin))
+        .insert_resource(driver)
+        .insert_resource(output)
+        .add_systems(Startup, move |mut commands: Commands, mut images: ResMut<Assets<Image>>| {
+            let automata_field = AutomataField::new(field_size, edge_count, &mut images).with_edge_init(EdgeInit::Cppn);
+            commands.insert_resource(automata_field);
+            commands.insert_resource(NeatField::new(field_size, &mut images));
+        })
+        .add_systems(Update, save_generations)
+        .run();
+}
+
+// files saved next to the population, a missing file starts fresh but a broken one stops the resume
+fn load_saved<T: EvolutionFile>(path: PathBuf) -> Option<T> {
+    if !path.exists() {
+        return None;
+    }
+
+    match T::load(&path) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            eprintln!("failed to load {}: {err}", path.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+// saved every generation, so a crashed run resumes from its last generation
+fn save_generations(
+    mut output: ResMut<Output>,
+    driver: Res<EvolutionDriver>,
+    mut generations: EventReader<GenerationFinished>,
+    mut finished: EventReader<EvolutionFinished>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if generations.iter().last().is_some() {
+        let saved = driver.population.save(output.path("population.bin"));
+        output.saved("population.bin", saved);
+
+        let saved = driver.history.save(output.path("history.json"));
+        output.saved("history.json", saved);
+
+        if let Some(champion) = &driver.history.champion {
+            let saved = champion.genome.save(output.path("champion.json"));
+            output.saved("champion.json", saved);
+        }
+
+        let saved = driver.archive.save(output.path("archive.bin"));
+        output.saved("archive.bin", saved);
+
+        let saved = driver.map.save(output.path("map_elites.json"));
+        output.saved("map_elites.json", saved);
+    }
+
+    if let Some(evolution) = finished.iter().last() {
+        info!("finished {} generations", evolution.generations);
+        output.finish();
+        exit.send(AppExit);
+    }
+}
+
+
+pub fn main() {
+    setup_hooks();
+
+    match Cli::parse().command {
+        Command::Run(args) => run(args),
+        Command::Sweep(args) => sweep(args),
+        Command::Export(args) => export(args),
+        Command::Evolve(args) => evolve(args),
+    }
 }
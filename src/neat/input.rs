@@ -0,0 +1,189 @@
+use std::sync::atomic::{
+    AtomicU64,
+    Ordering,
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::ExtractResource,
+        render_resource::ShaderType,
+    },
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+
+pub const MAX_INPUT_REGIONS: usize = 8;
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect, Serialize, Deserialize)]
+pub enum InputMode {
+    // added to the node's pre-activation, the activation still applies
+    #[default]
+    Add,
+    // the node's value is held at the input
+    Clamp,
+}
+
+// names a field input or output region while regions before it come and go, assigned by `add_region`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+pub struct RegionId(u64);
+
+impl RegionId {
+    pub(crate) fn next() -> Self {
+        static NEXT: AtomicU64 = AtomicU64::new(1);
+        Self(NEXT.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+// a rectangle of nodes driven by `values`, row-major from `origin`
+//   missing values read as zero, regions overlapping earlier regions are ignored where they overlap
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct InputRegion {
+    #[reflect(ignore)]
+    pub id: RegionId,
+    pub origin: UVec2,
+    pub size: UVec2,
+    pub mode: InputMode,
+    pub values: Vec<f32>,
+}
+
+impl InputRegion {
+    pub fn new(origin: UVec2, size: UVec2, mode: InputMode) -> Self {
+        Self {
+            id: RegionId::default(),
+            origin,
+            size,
+            mode,
+            values: vec![0.0; (size.x * size.y) as usize],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        (self.size.x * self.size.y) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn contains(&self, location: UVec2) -> bool {
+        location.cmpge(self.origin).all() && location.cmplt(self.origin + self.size).all()
+    }
+
+    // copies up to `len` values, the rest keep their previous values
+    pub fn set(&mut self, values: &[f32]) {
+        self.values.resize(self.len(), 0.0);
+
+        let count = values.len().min(self.len());
+        self.values[..count].copy_from_slice(&values[..count]);
+    }
+
+    pub fn fill(&mut self, value: f32) {
+        self.values = vec![value; self.len()];
+    }
+}
+
+
+// streamed into the field each step, set regions from any system before the frame is rendered
+//   `sequence` is echoed by the `OutputSample` of the step it was streamed into, to align responses with inputs
+#[derive(Resource, Clone, Debug, Default, ExtractResource, Reflect)]
+#[reflect(Resource)]
+pub struct FieldInput {
+    pub enabled: bool,
+    pub regions: Vec<InputRegion>,
+    pub sequence: Option<u64>,
+}
+
+impl FieldInput {
+    pub fn with_region(mut self, region: InputRegion) -> Self {
+        self.add_region(region);
+        self
+    }
+
+    // returns the region's index, or None once `MAX_INPUT_REGIONS` are in use
+    pub fn add_region(&mut self, mut region: InputRegion) -> Option<usize> {
+        if self.regions.len() >= MAX_INPUT_REGIONS {
+            warn!("field input is limited to {MAX_INPUT_REGIONS} regions");
+            return None;
+        }
+
+        region.id = RegionId::next();
+        self.enabled = true;
+        self.regions.push(region);
+        Some(self.regions.len() - 1)
+    }
+
+    pub fn region_mut(&mut self, index: usize) -> Option<&mut InputRegion> {
+        self.regions.get_mut(index)
+    }
+
+    pub fn index_of(&self, id: RegionId) -> Option<usize> {
+        self.regions.iter().position(|region| region.id == id)
+    }
+
+    // later regions shift down by one
+    pub fn remove_region(&mut self, index: usize) -> Option<InputRegion> {
+        (index < self.regions.len()).then(|| self.regions.remove(index))
+    }
+
+    pub fn remove_id(&mut self, id: RegionId) -> Option<InputRegion> {
+        self.remove_region(self.index_of(id)?)
+    }
+
+    pub(crate) fn uniforms(&self) -> ([InputRegionUniform; MAX_INPUT_REGIONS], u32) {
+        let mut regions = [InputRegionUniform::default(); MAX_INPUT_REGIONS];
+        if !self.enabled {
+            return (regions, 0);
+        }
+
+        let mut offset = 0;
+        for (uniform, region) in regions.iter_mut().zip(&self.regions) {
+            *uniform = InputRegionUniform {
+                bounds: IVec4::new(
+                    region.origin.x as i32,
+                    region.origin.y as i32,
+                    (region.origin.x + region.size.x) as i32,
+                    (region.origin.y + region.size.y) as i32,
+                ),
+                offset,
+                mode: region.mode as u32,
+            };
+            offset += region.len() as u32;
+        }
+
+        (regions, self.regions.len().min(MAX_INPUT_REGIONS) as u32)
+    }
+
+    // every region's values back to back, matching the offsets of `uniforms`
+    pub(crate) fn values(&self) -> Vec<f32> {
+        let mut values = Vec::new();
+
+        if self.enabled {
+            for region in self.regions.iter().take(MAX_INPUT_REGIONS) {
+                let start = values.len();
+                values.extend(region.values.iter().take(region.len()));
+                values.resize(start + region.len(), 0.0);
+            }
+        }
+
+        // empty storage buffers can't be bound
+        if values.is_empty() {
+            values.push(0.0);
+        }
+
+        values
+    }
+}
+
+
+#[derive(Clone, Copy, Default, ShaderType)]
+pub(crate) struct InputRegionUniform {
+    // min x, min y, max x, max y (exclusive)
+    bounds: IVec4,
+    offset: u32,
+    mode: u32,
+}
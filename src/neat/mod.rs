@@ -14,6 +14,7 @@ use bevy::{
         renderer::{
             RenderContext,
             RenderDevice,
+            RenderQueue,
         },
         render_graph::{
             self,
@@ -28,6 +29,7 @@ use bevy::{
             BindGroupLayoutEntry,
             BindingResource,
             BindingType,
+            BufferBindingType,
             CachedComputePipelineId,
             CachedPipelineState,
             ComputePassDescriptor,
@@ -35,12 +37,15 @@ use bevy::{
             Extent3d,
             PipelineCache,
             ShaderStages,
+            ShaderType,
+            StorageBuffer,
             StorageTextureAccess,
-            TextureDimension,
             TextureFormat,
-            TextureUsages,
             TextureViewDimension,
+            UniformBuffer,
         },
+        settings::WgpuLimits,
+        texture::Volume,
         Render,
         RenderApp,
         RenderSet,
@@ -49,29 +54,30 @@ use bevy::{
 
 use super::{
     automata::{
+        storage_image,
         AutomataBindGroup,
+        AutomataField,
+        AutomataParameters,
         AutomataPipeline,
+        EdgeInit,
+        TEXEL_SIZE,
+    },
+    uaf::{
+        Uaf,
+        UafPlugin,
     },
-    uaf::UafPlugin,
 };
 
 use std::borrow::Cow;
 
+mod input;
+pub use input::*;
+
 
 const NEAT_SHADER_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 21533341678341);
 const WORKGROUP_SIZE: u32 = 4;
 
 
-// TODO: IO visualization (e.g. EEG of neuron outputs)
-// TODO: streaming IO
-// #[derive(Resource, Clone, ExtractResource)]
-// struct NeatIO {
-//     handle: Handle<Image>,
-//     size: (u32, u32),
-//     current_index: u32,
-// }
-
-
 #[derive(Default)]
 pub struct NeatPlugin;
 
@@ -79,9 +85,20 @@ impl Plugin for NeatPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins((
             ExtractResourcePlugin::<NeatField>::default(),
-            UafPlugin,
+            ExtractResourcePlugin::<FieldInput>::default(),
         ));
 
+        app.register_type::<FieldInput>();
+        app.register_type::<NeatField>();
+        app.init_resource::<FieldInput>();
+
+        // after every edit of the frame, so the render world never pairs edited parameters with the old textures
+        app.add_systems(Last, restart_edited_field);
+
+        if !app.is_plugin_added::<UafPlugin>() {
+            app.add_plugins(UafPlugin);
+        }
+
         load_internal_asset!(
             app,
             NEAT_SHADER_HANDLE,
@@ -93,6 +110,7 @@ impl Plugin for NeatPlugin {
         render_app.add_systems(
             Render,
             (
+                prepare_neat_uniforms.in_set(RenderSet::Prepare),
                 queue_neat_bind_group.in_set(RenderSet::Queue),
             )
         );
@@ -106,40 +124,205 @@ impl Plugin for NeatPlugin {
 
         // TODO: automata node should feed into neat node :D
         //       output of automata node is pre_activation, can swap pipelines for different behaviors
-
-        // TODO: register UI editable types
     }
 
     fn finish(&self, app: &mut App) {
         let render_app = app.sub_app_mut(RenderApp);
         render_app.init_resource::<NeatPipeline>();
+        render_app.init_resource::<NeatUniformBuffer>();
+        render_app.init_resource::<NeatInputBuffer>();
     }
 }
 
 
-#[derive(Resource, Clone, ExtractResource)]
+#[derive(Resource, Clone, ExtractResource, Reflect)]
+#[reflect(Resource)]
 pub struct NeatField {
+    #[reflect(ignore)]
     pub uaf_activations: Handle<Image>,
+    // every node starts with this activation, `e` is shared by the whole field
+    pub init_uaf: Uaf,
+}
+
+// sized like the `AutomataField` when there is one
+impl FromWorld for NeatField {
+    fn from_world(world: &mut World) -> Self {
+        let size = world.get_resource::<AutomataField>().map_or(AutomataField::DEFAULT_SIZE, |automata| automata.size());
+        let mut images = world.resource_mut::<Assets<Image>>();
+        Self::new(size, &mut images)
+    }
 }
 
 impl NeatField {
+    pub const DEFAULT_UAF: Uaf = Uaf::new(-1.0, -1.0, -1.0, 1.0, 0.0);
+
     pub fn new(
         field_size: Extent3d,
-        images: &mut ResMut<Assets<Image>>,
+        images: &mut Assets<Image>,
     ) -> Self {
-        let mut uaf_activations = Image::new_fill(
-            field_size,
-            TextureDimension::D2,
-            &[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
-            TextureFormat::Rgba32Float,
-        );
-        uaf_activations.texture_descriptor.usage = TextureUsages::COPY_DST | TextureUsages::STORAGE_BINDING | TextureUsages::TEXTURE_BINDING;
-        let uaf_activations = images.add(uaf_activations);
+        Self::restore(field_size, vec![0; field_size.volume() * TEXEL_SIZE], images)
+    }
+
+    // uaf parameters as raw rgba32float texel data, e.g. from a checkpoint
+    pub fn restore(
+        field_size: Extent3d,
+        uaf_activations: Vec<u8>,
+        images: &mut Assets<Image>,
+    ) -> Self {
+        let uaf_activations = images.add(storage_image(field_size, uaf_activations));
 
         Self {
             uaf_activations,
+            init_uaf: Self::DEFAULT_UAF,
+        }
+    }
+
+    pub fn with_uaf(mut self, uaf: impl Into<Uaf>) -> Self {
+        self.init_uaf = uaf.into();
+        self
+    }
+}
+
+
+// what the textures of the current field were initialized with
+#[derive(Clone, PartialEq)]
+struct InitializedField {
+    field: Handle<Image>,
+    size: Extent3d,
+    parameters: AutomataParameters,
+    edge_init: EdgeInit,
+    // without `e`, which the update pass reads every step
+    init_uaf: Uaf,
+}
+
+// re-creates the field when an edit, e.g. from the inspector, changes what it was initialized with
+//   fields replaced by other systems are only recorded
+//   edits apply once no mouse button is held, dragging a value restarts the field once on release
+//   sizes and edge counts beyond the device limits are reverted
+#[allow(clippy::too_many_arguments)]
+fn restart_edited_field(
+    mut commands: Commands,
+    automata: Option<ResMut<AutomataField>>,
+    neat: Option<Res<NeatField>>,
+    mouse: Option<Res<Input<MouseButton>>>,
+    render_device: Option<Res<RenderDevice>>,
+    mut images: ResMut<Assets<Image>>,
+    mut textures: Query<&mut Handle<Image>>,
+    mut initialized: Local<Option<InitializedField>>,
+    mut pending: Local<bool>,
+) {
+    let (Some(mut automata), Some(neat)) = (automata, neat) else {
+        return;
+    };
+
+    if !automata.is_changed() && !neat.is_changed() && !*pending {
+        return;
+    }
+
+    let current = InitializedField {
+        field: automata.nodes.clone(),
+        size: automata.edited_size(),
+        parameters: automata.edited_parameters(),
+        edge_init: automata.edge_init(),
+        init_uaf: Uaf {
+            e: 0.0,
+            ..neat.init_uaf
+        },
+    };
+
+    let Some(previous) = initialized.as_ref().filter(|previous| previous.field == current.field) else {
+        *initialized = Some(current);
+        *pending = false;
+        return;
+    };
+
+    if *previous == current {
+        *pending = false;
+        return;
+    }
+
+    *pending = mouse.is_some_and(|mouse| mouse.any_pressed([MouseButton::Left, MouseButton::Right, MouseButton::Middle]));
+    if *pending {
+        return;
+    }
+
+    let limits = render_device.map_or_else(WgpuLimits::default, |render_device| render_device.limits());
+    if let Err(err) = AutomataField::check_limits(current.size, current.parameters.edge_count, &limits) {
+        warn!("not restarting the edited field: {err}");
+        automata.revert_layout();
+        return;
+    }
+
+    // an imported graph does not fit the edited field
+    let edge_init = match current.edge_init {
+        EdgeInit::Keep => EdgeInit::Random,
+        edge_init => edge_init,
+    };
+    let automata_field = AutomataField::from_parameters(current.size, current.parameters, &mut images)
+        .with_edge_init(edge_init)
+        .with_plasticity(automata.plasticity());
+    let neat_field = NeatField::new(current.size, &mut images).with_uaf(neat.init_uaf);
+
+    for mut texture in &mut textures {
+        if *texture == automata.nodes {
+            *texture = automata_field.nodes.clone();
         }
     }
+
+    info!(
+        "restarting the edited {}x{} field with {} edges",
+        current.size.width,
+        current.size.height,
+        current.parameters.edge_count,
+    );
+
+    *initialized = Some(InitializedField {
+        field: automata_field.nodes.clone(),
+        edge_init,
+        ..current
+    });
+
+    commands.insert_resource(automata_field);
+    commands.insert_resource(neat_field);
+}
+
+
+#[derive(Clone, Default, ShaderType)]
+struct NeatUniform {
+    init_uaf: Uaf,
+    input_regions: [InputRegionUniform; MAX_INPUT_REGIONS],
+    input_region_count: u32,
+}
+
+#[derive(Resource, Default)]
+struct NeatUniformBuffer {
+    buffer: UniformBuffer<NeatUniform>,
+}
+
+// values of every input region, rewritten each frame
+#[derive(Resource, Default)]
+struct NeatInputBuffer {
+    buffer: StorageBuffer<Vec<f32>>,
+}
+
+fn prepare_neat_uniforms(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut uniform_buffer: ResMut<NeatUniformBuffer>,
+    mut input_buffer: ResMut<NeatInputBuffer>,
+    neat_field: Res<NeatField>,
+    field_input: Res<FieldInput>,
+) {
+    let (input_regions, input_region_count) = field_input.uniforms();
+
+    let buffer = uniform_buffer.buffer.get_mut();
+    buffer.init_uaf = neat_field.init_uaf;
+    buffer.input_regions = input_regions;
+    buffer.input_region_count = input_region_count;
+    uniform_buffer.buffer.write_buffer(&render_device, &render_queue);
+
+    input_buffer.buffer.set(field_input.values());
+    input_buffer.buffer.write_buffer(&render_device, &render_queue);
 }
 
 
@@ -152,6 +335,8 @@ fn queue_neat_bind_group(
     gpu_images: Res<RenderAssets<Image>>,
     neat_field: Res<NeatField>,
     render_device: Res<RenderDevice>,
+    uniform_buffer: Res<NeatUniformBuffer>,
+    input_buffer: Res<NeatInputBuffer>,
 ) {
     let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
         label: None,
@@ -163,6 +348,14 @@ fn queue_neat_bind_group(
                     &gpu_images[&neat_field.uaf_activations].texture_view
                 ),
             },
+            BindGroupEntry {
+                binding: 1,
+                resource: uniform_buffer.buffer.binding().unwrap(),
+            },
+            BindGroupEntry {
+                binding: 2,
+                resource: input_buffer.buffer.binding().unwrap(),
+            },
         ],
     });
 
@@ -196,6 +389,26 @@ impl FromWorld for NeatPipeline {
                             },
                             count: None,
                         },
+                        BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        BindGroupLayoutEntry {
+                            binding: 2,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: true },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
                     ],
                 });
 
@@ -240,18 +453,31 @@ enum NeatState {
 // TODO: switch render pipelines via UI switches (also init pipelines for random initialization mode/'interesting universes')
 struct NeatNode {
     state: NeatState,
+    // nodes texture of the field being simulated, a new field restarts initialization
+    field: Option<Handle<Image>>,
 }
 
 impl Default for NeatNode {
     fn default() -> Self {
         Self {
             state: NeatState::Loading,
+            field: None,
         }
     }
 }
 
 impl render_graph::Node for NeatNode {
     fn update(&mut self, world: &mut World) {
+        let Some(automata) = world.get_resource::<AutomataField>() else {
+            return;
+        };
+        let resume_step = automata.resume_step();
+
+        if self.field.as_ref() != Some(&automata.nodes) {
+            self.field = Some(automata.nodes.clone());
+            self.state = NeatState::Loading;
+        }
+
         let pipeline = world.resource::<NeatPipeline>();
         let pipeline_cache = world.resource::<PipelineCache>();
 
@@ -272,6 +498,14 @@ impl render_graph::Node for NeatNode {
             }
             NeatState::Update => {}
         }
+
+        let mut automata_pipeline = world.resource_mut::<AutomataPipeline>();
+        automata_pipeline.step = match self.state {
+            NeatState::Loading => None,
+            NeatState::Init => Some(resume_step.unwrap_or(0)),
+            NeatState::Update => Some(automata_pipeline.step.map_or(1, |step| step + 1)),
+        };
+        automata_pipeline.resumed = resume_step.is_some();
     }
 
     fn run(
@@ -296,6 +530,7 @@ impl render_graph::Node for NeatNode {
 
         match self.state {
             NeatState::Loading => {}
+            NeatState::Init if automata_pipeline.resumed => {}
             NeatState::Init => {
                 let init_pipeline = pipeline_cache
                     .get_compute_pipeline(pipeline.init_pipeline)
@@ -1,6 +1,6 @@
 #define_import_path rusty_automata::neat
 
-#import rusty_automata::automata                automata_uniforms, get_state, init_automata, pre_activation, set_next_state
+#import rusty_automata::automata                automata_uniforms, get_state, init_automata, pre_activation, set_next_state, update_edges
 #import rusty_automata::noise                   gaussian_rand
 #import rusty_automata::uaf                     fUAFp, UafParameters
 
@@ -8,6 +8,29 @@
 @group(1) @binding(0)
 var uaf_activations: texture_storage_2d<rgba32float, read_write>;
 
+struct InputRegion {
+    bounds: vec4<i32>,
+    offset: u32,
+    mode: u32,
+};
+
+struct NeatUniforms {
+    init_uaf: UafParameters,
+    input_regions: array<InputRegion, 8>,
+    input_region_count: u32,
+};
+
+@group(1) @binding(1)
+var<uniform> neat_uniforms: NeatUniforms;
+
+@group(1) @binding(2)
+var<storage, read> input_values: array<f32>;
+
+// `InputMode` in input.rs
+const INPUT_NONE: u32 = 0u;
+const INPUT_ADD: u32 = 1u;
+const INPUT_CLAMP: u32 = 2u;
+
 @compute @workgroup_size(4, 4, 1)
 fn init(
     @builtin(global_invocation_id) invocation_id: vec3<u32>,
@@ -40,7 +63,7 @@ fn get_uaf_params(
         activation.y,
         activation.z,
         activation.w,
-        0.0, // TODO: bind UAF.e to a texture (or uniform 'noise-floor'?)
+        neat_uniforms.init_uaf.e, // TODO: bind UAF.e to a texture
     );
 }
 
@@ -61,17 +84,44 @@ fn set_uaf_params(
 }
 
 
+// (mode, value) of the first region containing the location
+fn get_input(
+    location: vec2<i32>,
+) -> vec2<f32> {
+    for (var i = 0u; i < neat_uniforms.input_region_count; i = i + 1u) {
+        let region = neat_uniforms.input_regions[i];
+
+        if (all(location >= region.bounds.xy) && all(location < region.bounds.zw)) {
+            let local = location - region.bounds.xy;
+            let index = region.offset + u32(local.y * (region.bounds.z - region.bounds.x) + local.x);
+
+            return vec2<f32>(f32(region.mode + 1u), input_values[index]);
+        }
+    }
+
+    return vec2<f32>(f32(INPUT_NONE), 0.0);
+}
+
+
 fn compute_next_neat_state(
     location: vec2<i32>,
 ) {
     let current_state = get_state(location);
+    let input = get_input(location);
+    let input_mode = u32(input.x);
 
-    let next_value = fUAFp(
-        pre_activation(location, current_state),
-        get_uaf_params(location),
-    );
+    var x = pre_activation(location, current_state);
+    if (input_mode == INPUT_ADD) {
+        x += input.y;
+    }
+
+    var next_value = clamp(fUAFp(x, get_uaf_params(location)), -1.0, 1.0);
+    if (input_mode == INPUT_CLAMP) {
+        next_value = input.y;
+    }
 
-    set_next_state(location, current_state, clamp(next_value, -1.0, 1.0));
+    update_edges(location, next_value);
+    set_next_state(location, current_state, next_value);
 }
 
 
@@ -86,13 +136,7 @@ fn init_neat_field(
     let uaf_d = gaussian_rand(scaled_location + vec2<f32>(-0.037, -0.017)) * 1.25;
     set_uaf_params(
         location,
-        UafParameters(
-            -1.0,
-            -1.0,
-            -1.0,
-            1.0,
-            0.0,
-        ),
+        neat_uniforms.init_uaf,
         // UafParameters(
         //     -abs(uaf_a),
         //     abs(uaf_b) / 1000.0,
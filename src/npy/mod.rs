@@ -0,0 +1,499 @@
+use std::{
+    fmt,
+    fs::{
+        self,
+        File,
+    },
+    io::{
+        self,
+        BufWriter,
+        Seek,
+        Write,
+    },
+    path::{
+        Path,
+        PathBuf,
+    },
+};
+
+use bevy::prelude::*;
+use serde::Serialize;
+use zip::{
+    write::FileOptions,
+    CompressionMethod,
+    ZipWriter,
+};
+
+use crate::{
+    automata::{
+        AutomataField,
+        AutomataParameters,
+    },
+    readback::{
+        FieldSnapshot,
+        Readback,
+        SnapshotRequests,
+    },
+};
+
+
+#[derive(Default)]
+pub struct NpyPlugin;
+
+impl Plugin for NpyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FieldSnapshot>();
+        app.add_event::<ExportNpz>();
+
+        app.init_resource::<NpzRecordings>();
+
+        app.add_systems(
+            Update,
+            (
+                start_npz_recordings,
+                record_npz_frames,
+            ).chain(),
+        );
+    }
+}
+
+
+// records the next `frames` snapshots (every step unless `Readback::interval` is set) into an .npz
+#[derive(Event, Clone, Debug)]
+pub struct ExportNpz {
+    pub path: PathBuf,
+    pub frames: usize,
+}
+
+
+#[derive(Debug)]
+pub enum NpyError {
+    Io(io::Error),
+    Zip(zip::result::ZipError),
+    Json(serde_json::Error),
+    EmptySeries,
+    MismatchedFrames,
+}
+
+impl fmt::Display for NpyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NpyError::Io(err) => write!(f, "npy io error: {err}"),
+            NpyError::Zip(err) => write!(f, "npz archive error: {err}"),
+            NpyError::Json(err) => write!(f, "npz metadata error: {err}"),
+            NpyError::EmptySeries => write!(f, "no frames to export"),
+            NpyError::MismatchedFrames => write!(f, "frames differ in size or contents"),
+        }
+    }
+}
+
+impl std::error::Error for NpyError {}
+
+impl From<io::Error> for NpyError {
+    fn from(err: io::Error) -> Self {
+        NpyError::Io(err)
+    }
+}
+
+impl From<zip::result::ZipError> for NpyError {
+    fn from(err: zip::result::ZipError) -> Self {
+        NpyError::Zip(err)
+    }
+}
+
+impl From<serde_json::Error> for NpyError {
+    fn from(err: serde_json::Error) -> Self {
+        NpyError::Json(err)
+    }
+}
+
+
+// a little endian, C ordered array as understood by `numpy.load`
+#[derive(Clone, Debug, PartialEq)]
+pub struct NpyArray {
+    pub descr: &'static str,
+    pub shape: Vec<usize>,
+    pub data: Vec<u8>,
+}
+
+impl NpyArray {
+    pub fn from_f32(shape: Vec<usize>, values: impl IntoIterator<Item = f32>) -> Self {
+        Self {
+            descr: "<f4",
+            shape,
+            data: values.into_iter().flat_map(f32::to_le_bytes).collect(),
+        }
+    }
+
+    pub fn from_i32(shape: Vec<usize>, values: impl IntoIterator<Item = i32>) -> Self {
+        Self {
+            descr: "<i4",
+            shape,
+            data: values.into_iter().flat_map(i32::to_le_bytes).collect(),
+        }
+    }
+
+    pub fn from_u64(shape: Vec<usize>, values: impl IntoIterator<Item = u64>) -> Self {
+        Self {
+            descr: "<u8",
+            shape,
+            data: values.into_iter().flat_map(u64::to_le_bytes).collect(),
+        }
+    }
+
+    // format version 1.0, header padded so the data starts on a 64 byte boundary
+    pub fn write(&self, mut writer: impl Write) -> io::Result<()> {
+        let shape = match self.shape.as_slice() {
+            [length] => format!("({length},)"),
+            shape => format!("({})", shape.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")),
+        };
+        let mut header = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {shape}, }}", self.descr);
+
+        let unpadded = b"\x93NUMPY".len() + 2 + 2 + header.len() + 1;
+        header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+        header.push('\n');
+
+        writer.write_all(b"\x93NUMPY")?;
+        writer.write_all(&[1, 0])?;
+        writer.write_all(&(header.len() as u16).to_le_bytes())?;
+        writer.write_all(header.as_bytes())?;
+        writer.write_all(&self.data)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer)?;
+        writer.flush()
+    }
+}
+
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FieldMetadata {
+    pub width: u32,
+    pub height: u32,
+    pub edge_count: u32,
+    pub max_radius: f32,
+    pub max_edge_weight: f32,
+    pub seed: f32,
+    pub steps: Vec<u64>,
+}
+
+impl FieldMetadata {
+    pub fn new(parameters: AutomataParameters, frames: &[FieldSnapshot]) -> Self {
+        let first = frames.first();
+
+        Self {
+            width: first.map_or(0, |frame| frame.width),
+            height: first.map_or(0, |frame| frame.height),
+            edge_count: parameters.edge_count,
+            max_radius: parameters.max_radius,
+            max_edge_weight: parameters.max_edge_weight,
+            seed: parameters.seed,
+            steps: frames.iter().map(|frame| frame.step).collect(),
+        }
+    }
+}
+
+
+// arrays are shaped (H, W, ...) for a single snapshot and (T, H, W, ...) for a series
+// nodes: [value, derivative, integral], edge_sources: (E, H, W, [x, y]), edge_weights: (E, H, W), uaf: [a, b, c, d]
+#[derive(Clone, Debug)]
+pub struct FieldArrays {
+    pub arrays: Vec<(&'static str, NpyArray)>,
+}
+
+impl FieldArrays {
+    pub fn from_snapshot(snapshot: &FieldSnapshot) -> Self {
+        let mut arrays = Self::from_frames(std::slice::from_ref(snapshot))
+            .expect("a single snapshot is always a consistent series");
+
+        for (name, array) in &mut arrays.arrays {
+            if *name != "steps" {
+                array.shape.remove(0);
+            }
+        }
+
+        arrays
+    }
+
+    pub fn from_frames(frames: &[FieldSnapshot]) -> Result<Self, NpyError> {
+        let Some(first) = frames.first() else {
+            return Err(NpyError::EmptySeries);
+        };
+
+        let consistent = frames.iter().all(|frame| {
+            frame.width == first.width
+                && frame.height == first.height
+                && frame.edge_count == first.edge_count
+                && frame.nodes.len() == first.nodes.len()
+                && frame.edges.len() == first.edges.len()
+                && frame.uaf.len() == first.uaf.len()
+        });
+        if !consistent {
+            return Err(NpyError::MismatchedFrames);
+        }
+
+        let t = frames.len();
+        let (h, w, e) = (first.height as usize, first.width as usize, first.edge_count as usize);
+
+        let mut arrays = vec![
+            ("steps", NpyArray::from_u64(vec![t], frames.iter().map(|frame| frame.step))),
+        ];
+
+        if !first.nodes.is_empty() {
+            arrays.push((
+                "nodes",
+                NpyArray::from_f32(
+                    vec![t, h, w, 3],
+                    frames
+                        .iter()
+                        .flat_map(|frame| &frame.nodes)
+                        .flat_map(|node| [node.value, node.derivative, node.integral]),
+                ),
+            ));
+        }
+
+        if !first.edges.is_empty() {
+            arrays.push((
+                "edge_sources",
+                NpyArray::from_i32(
+                    vec![t, e, h, w, 2],
+                    frames
+                        .iter()
+                        .flat_map(|frame| &frame.edges)
+                        .flat_map(|edge| edge.from_node_location.to_array()),
+                ),
+            ));
+            arrays.push((
+                "edge_weights",
+                NpyArray::from_f32(
+                    vec![t, e, h, w],
+                    frames
+                        .iter()
+                        .flat_map(|frame| &frame.edges)
+                        .map(|edge| edge.weight),
+                ),
+            ));
+        }
+
+        if !first.uaf.is_empty() {
+            arrays.push((
+                "uaf",
+                NpyArray::from_f32(
+                    vec![t, h, w, 4],
+                    frames
+                        .iter()
+                        .flat_map(|frame| &frame.uaf)
+                        .flat_map(|uaf| uaf.to_array()),
+                ),
+            ));
+        }
+
+        Ok(Self {
+            arrays,
+        })
+    }
+
+    // one `{name}.npy` per array
+    pub fn save_dir(&self, dir: impl AsRef<Path>) -> io::Result<()> {
+        fs::create_dir_all(&dir)?;
+
+        for (name, array) in &self.arrays {
+            array.save(dir.as_ref().join(format!("{name}.npy")))?;
+        }
+
+        Ok(())
+    }
+
+    // `metadata.json` is read back as raw bytes by `numpy.load(...)["metadata.json"]`
+    pub fn write_npz(
+        &self,
+        writer: impl Write + Seek,
+        metadata: Option<&FieldMetadata>,
+    ) -> Result<(), NpyError> {
+        let mut zip = ZipWriter::new(writer);
+        let options = FileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(true);
+
+        for (name, array) in &self.arrays {
+            zip.start_file(format!("{name}.npy"), options)?;
+            array.write(&mut zip)?;
+        }
+
+        if let Some(metadata) = metadata {
+            zip.start_file("metadata.json", options)?;
+            serde_json::to_writer_pretty(&mut zip, metadata)?;
+        }
+
+        zip.finish()?;
+        Ok(())
+    }
+
+    pub fn save_npz(
+        &self,
+        path: impl AsRef<Path>,
+        metadata: Option<&FieldMetadata>,
+    ) -> Result<(), NpyError> {
+        self.write_npz(BufWriter::new(File::create(path)?), metadata)
+    }
+}
+
+
+#[derive(Resource)]
+struct NpzRecordings(SnapshotRequests<PathBuf>);
+
+impl Default for NpzRecordings {
+    fn default() -> Self {
+        Self(SnapshotRequests::frames())
+    }
+}
+
+fn start_npz_recordings(
+    mut requests: EventReader<ExportNpz>,
+    mut recordings: ResMut<NpzRecordings>,
+    mut readback: Option<ResMut<Readback>>,
+    automata: Option<Res<AutomataField>>,
+) {
+    recordings.0.update(
+        requests.iter().map(|request| (request.path.clone(), request.frames)),
+        automata.as_deref(),
+        readback.as_deref_mut(),
+        "npz export",
+    );
+}
+
+fn record_npz_frames(
+    mut recordings: ResMut<NpzRecordings>,
+    mut snapshots: EventReader<FieldSnapshot>,
+    automata: Option<Res<AutomataField>>,
+) {
+    if recordings.0.is_empty() {
+        snapshots.clear();
+        return;
+    }
+
+    let parameters = automata.map(|automata| automata.parameters()).unwrap_or_default();
+    for (path, frames) in recordings.0.receive(snapshots.iter()) {
+        let metadata = FieldMetadata::new(parameters, &frames);
+        let result = FieldArrays::from_frames(&frames)
+            .and_then(|arrays| arrays.save_npz(&path, Some(&metadata)));
+
+        match result {
+            Ok(()) => info!("exported {} frames to {}", frames.len(), path.display()),
+            Err(err) => error!("failed to export {}: {err}", path.display()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::{
+        Cursor,
+        Read,
+    };
+
+    use zip::ZipArchive;
+
+    use super::*;
+    use crate::readback::{
+        EdgeState,
+        NodeState,
+    };
+
+    fn encode(array: &NpyArray) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        array.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    // header text and the offset of the data
+    fn header(bytes: &[u8]) -> (&str, usize) {
+        let length = u16::from_le_bytes([bytes[8], bytes[9]]) as usize;
+        (std::str::from_utf8(&bytes[10..10 + length]).unwrap(), 10 + length)
+    }
+
+    fn snapshot() -> FieldSnapshot {
+        FieldSnapshot {
+            step: 5,
+            width: 2,
+            height: 1,
+            edge_count: 1,
+            nodes: vec![
+                NodeState { value: 0.5, derivative: 0.0, integral: 0.0 },
+                NodeState { value: -1.0, derivative: 0.0, integral: 0.0 },
+            ],
+            edges: vec![
+                EdgeState { from_node_location: IVec2::new(1, 0), weight: 2.0, downregulation: 0.0 },
+                EdgeState { from_node_location: IVec2::new(0, 0), weight: -2.0, downregulation: 0.0 },
+            ],
+            uaf: vec![Vec4::ONE; 2],
+            ..default()
+        }
+    }
+
+    #[test]
+    fn npy_header_is_aligned_and_describes_the_array() {
+        let array = NpyArray::from_f32(vec![2, 3], (0..6).map(|i| i as f32));
+        let bytes = encode(&array);
+
+        assert_eq!(&bytes[..6], b"\x93NUMPY");
+        assert_eq!(&bytes[6..8], &[1, 0]);
+
+        let (text, data) = header(&bytes);
+        assert_eq!(data % 64, 0);
+        assert!(text.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }"));
+        assert!(text.ends_with('\n'));
+        assert_eq!(&bytes[data..], array.data.as_slice());
+        assert_eq!(&bytes[data + 4..data + 8], &1.0f32.to_le_bytes());
+    }
+
+    #[test]
+    fn one_dimensional_shape_keeps_the_trailing_comma() {
+        let bytes = encode(&NpyArray::from_u64(vec![3], [1, 2, 3]));
+
+        let (text, _) = header(&bytes);
+        assert!(text.contains("'descr': '<u8'"));
+        assert!(text.contains("'shape': (3,)"));
+    }
+
+    #[test]
+    fn npz_round_trip() {
+        let snapshot = snapshot();
+        let arrays = FieldArrays::from_snapshot(&snapshot);
+        let metadata = FieldMetadata::new(AutomataParameters { edge_count: 1, ..default() }, std::slice::from_ref(&snapshot));
+
+        let shapes: Vec<(&str, Vec<usize>)> = arrays.arrays.iter().map(|(name, array)| (*name, array.shape.clone())).collect();
+        assert_eq!(shapes, vec![
+            ("steps", vec![1]),
+            ("nodes", vec![1, 2, 3]),
+            ("edge_sources", vec![1, 1, 2, 2]),
+            ("edge_weights", vec![1, 1, 2]),
+            ("uaf", vec![1, 2, 4]),
+        ]);
+
+        let mut bytes = Cursor::new(Vec::new());
+        arrays.write_npz(&mut bytes, Some(&metadata)).unwrap();
+        let mut archive = ZipArchive::new(bytes).unwrap();
+
+        let mut names: Vec<&str> = archive.file_names().collect();
+        names.sort_unstable();
+        assert_eq!(names, vec!["edge_sources.npy", "edge_weights.npy", "metadata.json", "nodes.npy", "steps.npy", "uaf.npy"]);
+
+        for (name, array) in &arrays.arrays {
+            let mut entry = Vec::new();
+            archive.by_name(&format!("{name}.npy")).unwrap().read_to_end(&mut entry).unwrap();
+            assert_eq!(entry, encode(array), "{name}.npy");
+        }
+
+        let mut json = String::new();
+        archive.by_name("metadata.json").unwrap().read_to_string(&mut json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(json["width"], 2);
+        assert_eq!(json["height"], 1);
+        assert_eq!(json["edge_count"], 1);
+        assert_eq!(json["steps"], serde_json::json!([5]));
+    }
+}
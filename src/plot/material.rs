@@ -0,0 +1,340 @@
+use bevy::{
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{
+            AsBindGroup,
+            AsBindGroupShaderType,
+            ShaderRef,
+            ShaderType,
+        },
+    },
+    sprite::Material2d,
+};
+
+use crate::uaf::{
+    Uaf,
+    UafPreset,
+};
+
+use super::{
+    LegendEntry,
+    LineStyle,
+    PLOT_MATERIAL_SHADER_HANDLE,
+};
+
+
+pub const MAX_PLOT_CURVES: usize = 8;
+pub const MAX_PLOT_SAMPLES: usize = 128;
+pub const MAX_GRID_STEPS: usize = 4;
+
+
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub enum CurveSource {
+    // values evenly spaced over `x_range`, resampled to `MAX_PLOT_SAMPLES`
+    Samples {
+        x_range: Vec2,
+        values: Vec<f32>,
+    },
+    Uaf(Uaf),
+    UafDerivative(Uaf),
+    Reference(UafPreset),
+}
+
+impl CurveSource {
+    // None outside the range of a sampled curve
+    pub fn eval(&self, x: f32) -> Option<f32> {
+        match self {
+            CurveSource::Samples { x_range, values } => {
+                let t = (x - x_range.x) / (x_range.y - x_range.x);
+                if values.len() < 2 || !(0.0..=1.0).contains(&t) {
+                    return None;
+                }
+
+                let position = t * (values.len() - 1) as f32;
+                let index = (position as usize).min(values.len() - 2);
+                let t = position - index as f32;
+
+                Some(values[index] * (1.0 - t) + values[index + 1] * t)
+            }
+            CurveSource::Uaf(uaf) => Some(uaf.eval(x)),
+            CurveSource::UafDerivative(uaf) => Some(uaf.derivative(x)),
+            CurveSource::Reference(preset) => Some(preset.reference(x)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Reflect)]
+pub struct PlotCurve {
+    pub label: String,
+    pub color: Color,
+    pub style: LineStyle,
+    pub visible: bool,
+    pub source: CurveSource,
+}
+
+impl PlotCurve {
+    pub fn new(label: impl Into<String>, source: CurveSource, color: Color) -> Self {
+        Self {
+            label: label.into(),
+            color,
+            style: LineStyle::Solid,
+            visible: true,
+            source,
+        }
+    }
+
+    pub fn samples(label: impl Into<String>, x_range: Vec2, values: Vec<f32>, color: Color) -> Self {
+        Self::new(label, CurveSource::Samples { x_range, values }, color)
+    }
+
+    pub fn uaf(label: impl Into<String>, uaf: Uaf, color: Color) -> Self {
+        Self::new(label, CurveSource::Uaf(uaf), color)
+    }
+
+    pub fn derivative(label: impl Into<String>, uaf: Uaf, color: Color) -> Self {
+        Self::new(label, CurveSource::UafDerivative(uaf), color).with_style(LineStyle::Dashed)
+    }
+
+    pub fn reference(preset: UafPreset, color: Color) -> Self {
+        Self::new(preset.name(), CurveSource::Reference(preset), color).with_style(LineStyle::Dashed)
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    pub fn with_style(mut self, style: LineStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    pub fn legend_entry(&self) -> LegendEntry {
+        LegendEntry::new(&self.label, self.color, self.style)
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Reflect)]
+pub struct GridStep {
+    pub step: f32,
+    pub color: Color,
+}
+
+impl GridStep {
+    pub fn new(step: f32, color: Color) -> Self {
+        Self {
+            step,
+            color,
+        }
+    }
+}
+
+
+// a graph of up to `MAX_PLOT_CURVES` curves over the rectangle `x_range` × `y_range`
+//   the quad it is drawn on maps uv (0, 0) to (x_range.x, y_range.y)
+#[derive(AsBindGroup, Clone, Debug, Reflect, TypeUuid)]
+#[uuid = "5d3a3f2c-1b7e-4c55-9a0f-6a8f1e2b4c71"]
+#[uniform(0, PlotUniform)]
+pub struct PlotMaterial {
+    pub x_range: Vec2,
+    pub y_range: Vec2,
+    pub background: Color,
+    pub axis_color: Color,
+    pub grid: Vec<GridStep>,
+    pub curves: Vec<PlotCurve>,
+}
+
+impl Default for PlotMaterial {
+    fn default() -> Self {
+        Self {
+            x_range: Vec2::new(-5.1, 5.1),
+            y_range: Vec2::new(-5.1, 5.1),
+            background: Color::rgb(0.1, 0.1, 0.1),
+            axis_color: Color::rgba(0.6, 0.6, 0.6, 0.5),
+            grid: vec![
+                GridStep::new(1.0, Color::rgba(0.6, 0.6, 0.6, 0.1)),
+                GridStep::new(5.0, Color::rgba(0.6, 0.6, 0.6, 0.2)),
+                GridStep::new(10.0, Color::rgba(0.6, 0.6, 0.6, 0.3)),
+            ],
+            curves: Vec::new(),
+        }
+    }
+}
+
+impl PlotMaterial {
+    pub fn with_x_range(mut self, start: f32, end: f32) -> Self {
+        self.x_range = Vec2::new(start, end);
+        self
+    }
+
+    pub fn with_y_range(mut self, start: f32, end: f32) -> Self {
+        self.y_range = Vec2::new(start, end);
+        self
+    }
+
+    pub fn with_grid(mut self, grid: impl IntoIterator<Item = GridStep>) -> Self {
+        self.grid = grid.into_iter().collect();
+        self
+    }
+
+    pub fn with_background(mut self, background: Color) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn with_curve(mut self, curve: PlotCurve) -> Self {
+        self.curves.push(curve);
+        self
+    }
+
+    pub fn visible_curves(&self) -> impl Iterator<Item = &PlotCurve> {
+        self.curves
+            .iter()
+            .filter(|curve| curve.visible)
+            .take(MAX_PLOT_CURVES)
+    }
+
+    pub fn legend(&self) -> Vec<LegendEntry> {
+        self.visible_curves()
+            .map(PlotCurve::legend_entry)
+            .collect()
+    }
+
+    // widens or narrows `x_range` around its center to match the aspect ratio of a `size` quad
+    pub fn fit_aspect(&mut self, size: Vec2) {
+        let center = (self.x_range.x + self.x_range.y) * 0.5;
+        let half_width = (self.y_range.y - self.y_range.x) * size.x / size.y.max(1.0) * 0.5;
+
+        self.x_range = Vec2::new(center - half_width, center + half_width);
+    }
+}
+
+impl Material2d for PlotMaterial {
+    fn fragment_shader() -> ShaderRef {
+        PLOT_MATERIAL_SHADER_HANDLE.typed().into()
+    }
+}
+
+
+// curve kinds, see `evaluate_curve` in plot_material.wgsl
+const CURVE_SAMPLES: u32 = 0;
+const CURVE_UAF: u32 = 1;
+const CURVE_UAF_DERIVATIVE: u32 = 2;
+const CURVE_REFERENCE: u32 = 3;
+
+#[derive(Clone, Copy, Default, ShaderType)]
+struct PlotCurveUniform {
+    color: Vec4,
+    // uaf a, b, c, d
+    parameters: Vec4,
+    // uaf e, or sample x range start and end
+    extra: Vec4,
+    kind: u32,
+    dashed: u32,
+    // offset into `samples`, or the reference preset index
+    offset: u32,
+    count: u32,
+}
+
+#[derive(Clone, Copy, Default, ShaderType)]
+struct GridStepUniform {
+    color: Vec4,
+    step: f32,
+}
+
+#[derive(Clone, ShaderType)]
+pub struct PlotUniform {
+    // x start, x end, y start, y end
+    view: Vec4,
+    background: Vec4,
+    axis_color: Vec4,
+    grid: [GridStepUniform; MAX_GRID_STEPS],
+    curves: [PlotCurveUniform; MAX_PLOT_CURVES],
+    // four samples per element
+    samples: [Vec4; MAX_PLOT_CURVES * MAX_PLOT_SAMPLES / 4],
+    grid_count: u32,
+    curve_count: u32,
+}
+
+impl AsBindGroupShaderType<PlotUniform> for PlotMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> PlotUniform {
+        let mut grid = [GridStepUniform::default(); MAX_GRID_STEPS];
+        for (uniform, step) in grid.iter_mut().zip(&self.grid) {
+            *uniform = GridStepUniform {
+                color: step.color.as_rgba_f32().into(),
+                step: step.step,
+            };
+        }
+
+        let mut curves = [PlotCurveUniform::default(); MAX_PLOT_CURVES];
+        let mut samples = [0.0; MAX_PLOT_CURVES * MAX_PLOT_SAMPLES];
+        let mut curve_count = 0;
+
+        for ((i, uniform), curve) in curves.iter_mut().enumerate().zip(self.visible_curves()) {
+            *uniform = PlotCurveUniform {
+                color: curve.color.as_rgba_f32().into(),
+                dashed: (curve.style == LineStyle::Dashed) as u32,
+                ..default()
+            };
+
+            match &curve.source {
+                CurveSource::Samples { x_range, values } => {
+                    let offset = i * MAX_PLOT_SAMPLES;
+                    let count = resample(values, &mut samples[offset..offset + MAX_PLOT_SAMPLES]);
+
+                    uniform.kind = CURVE_SAMPLES;
+                    uniform.extra = Vec4::new(0.0, x_range.x, x_range.y, 0.0);
+                    uniform.offset = offset as u32;
+                    uniform.count = count as u32;
+                }
+                CurveSource::Uaf(uaf) | CurveSource::UafDerivative(uaf) => {
+                    uniform.kind = match curve.source {
+                        CurveSource::Uaf(_) => CURVE_UAF,
+                        _ => CURVE_UAF_DERIVATIVE,
+                    };
+                    uniform.parameters = uaf.texel();
+                    uniform.extra = Vec4::new(uaf.e, 0.0, 0.0, 0.0);
+                }
+                CurveSource::Reference(preset) => {
+                    uniform.kind = CURVE_REFERENCE;
+                    uniform.offset = preset.index();
+                }
+            }
+
+            curve_count += 1;
+        }
+
+        PlotUniform {
+            view: Vec4::new(self.x_range.x, self.x_range.y, self.y_range.x, self.y_range.y),
+            background: self.background.as_rgba_f32().into(),
+            axis_color: self.axis_color.as_rgba_f32().into(),
+            grid,
+            curves,
+            samples: std::array::from_fn(|i| Vec4::from_slice(&samples[i * 4..i * 4 + 4])),
+            grid_count: self.grid.len().min(MAX_GRID_STEPS) as u32,
+            curve_count,
+        }
+    }
+}
+
+// linear resampling when there are more values than fit, returns the number written
+fn resample(values: &[f32], samples: &mut [f32]) -> usize {
+    if values.len() <= samples.len() {
+        samples[..values.len()].copy_from_slice(values);
+        return values.len();
+    }
+
+    let scale = (values.len() - 1) as f32 / (samples.len() - 1) as f32;
+    for (i, sample) in samples.iter_mut().enumerate() {
+        let position = i as f32 * scale;
+        let index = (position as usize).min(values.len() - 2);
+        let t = position - index as f32;
+
+        *sample = values[index] * (1.0 - t) + values[index + 1] * t;
+    }
+
+    samples.len()
+}
@@ -6,11 +6,24 @@ use bevy::{
     prelude::*,
     reflect::{
         TypeUuid,
-    }
+    },
+    sprite::Material2dPlugin,
+};
+
+use crate::uaf::UafPlugin;
+
+mod material;
+pub use material::*;
+
+mod view;
+pub use view::{
+    tick_step,
+    PlotView,
 };
 
 
 const PLOT_SHADER_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 809823407934);
+const PLOT_MATERIAL_SHADER_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 809823407935);
 
 #[derive(Default)]
 pub struct PlotPlugin;
@@ -23,5 +36,93 @@ impl Plugin for PlotPlugin {
             "plot.wgsl",
             Shader::from_wgsl
         );
+        load_internal_asset!(
+            app,
+            PLOT_MATERIAL_SHADER_HANDLE,
+            "plot_material.wgsl",
+            Shader::from_wgsl
+        );
+
+        // plot materials draw uaf curves
+        if !app.is_plugin_added::<UafPlugin>() {
+            app.add_plugins(UafPlugin);
+        }
+
+        app.add_plugins(Material2dPlugin::<PlotMaterial>::default());
+        app.register_asset_reflect::<PlotMaterial>();
+
+        app.add_systems(
+            Update,
+            (
+                view::pan_zoom_plots,
+                view::update_plot_ticks,
+                view::update_plot_readouts,
+            ).chain(),
+        );
+    }
+}
+
+
+// distinguishable curve colours, the first matches the original uaf curve
+pub const PLOT_PALETTE: [Color; 8] = [
+    Color::rgb(0.91, 0.13, 0.23),
+    Color::rgb(0.16, 0.63, 0.92),
+    Color::rgb(0.98, 0.72, 0.12),
+    Color::rgb(0.30, 0.80, 0.38),
+    Color::rgb(0.72, 0.40, 0.95),
+    Color::rgb(0.98, 0.50, 0.20),
+    Color::rgb(0.25, 0.88, 0.82),
+    Color::rgb(0.95, 0.45, 0.75),
+];
+
+pub fn curve_color(index: usize) -> Color {
+    PLOT_PALETTE[index % PLOT_PALETTE.len()]
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LegendEntry {
+    pub label: String,
+    pub color: Color,
+    pub style: LineStyle,
+}
+
+impl LegendEntry {
+    pub fn new(label: impl Into<String>, color: Color, style: LineStyle) -> Self {
+        Self {
+            label: label.into(),
+            color,
+            style,
+        }
+    }
+}
+
+// one row per entry, a line sample next to its label
+pub fn legend_ui(ui: &mut egui::Ui, entries: &[LegendEntry]) {
+    for entry in entries {
+        ui.horizontal(|ui| {
+            let (rect, _) = ui.allocate_exact_size(egui::vec2(24.0, 12.0), egui::Sense::hover());
+            let [r, g, b, a] = entry.color.as_rgba_u8();
+            let stroke = egui::Stroke::new(2.0, egui::Color32::from_rgba_unmultiplied(r, g, b, a));
+            let points = [rect.left_center(), rect.right_center()];
+
+            match entry.style {
+                LineStyle::Solid => {
+                    ui.painter().line_segment(points, stroke);
+                }
+                LineStyle::Dashed => {
+                    ui.painter().extend(egui::Shape::dashed_line(&points, stroke, 4.0, 3.0));
+                }
+            }
+
+            ui.label(&entry.label);
+        });
     }
 }
@@ -25,3 +25,9 @@ fn plot_point(f: f32, y: f32) -> f32 {
 fn draw_curve(baseCol: vec4<f32>, y: f32, value: f32, curveCol: vec4<f32>) -> vec4<f32> {
     return mix_color(baseCol, curveCol, plot_point(value, y) * curveCol.w);
 }
+
+// dashes along x, `period` in graph units
+fn draw_dashed_curve(baseCol: vec4<f32>, xy: vec2<f32>, value: f32, curveCol: vec4<f32>, period: f32) -> vec4<f32> {
+    let dash: f32 = step(0.5, fract(xy.x / period));
+    return mix_color(baseCol, curveCol, plot_point(value, xy.y) * curveCol.w * dash);
+}
@@ -0,0 +1,117 @@
+#import rusty_automata::plot                    draw_curve, draw_dashed_curve, draw_grid, mix_color
+#import rusty_automata::uaf                     fUAF, fUAFdx, uaf_reference
+
+#import bevy_sprite::mesh2d_vertex_output       MeshVertexOutput
+
+
+struct GridStep {
+    color: vec4<f32>,
+    step: f32,
+};
+
+struct PlotCurve {
+    color: vec4<f32>,
+    parameters: vec4<f32>,
+    extra: vec4<f32>,
+    kind: u32,
+    dashed: u32,
+    offset: u32,
+    count: u32,
+};
+
+struct PlotMaterial {
+    view: vec4<f32>,
+    background: vec4<f32>,
+    axis_color: vec4<f32>,
+    grid: array<GridStep, 4>,
+    curves: array<PlotCurve, 8>,
+    samples: array<vec4<f32>, 256>,
+    grid_count: u32,
+    curve_count: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: PlotMaterial;
+
+
+fn get_sample(index: u32) -> f32 {
+    return material.samples[index / 4u][index % 4u];
+}
+
+// linear interpolation between samples, returns (value, coverage)
+fn sample_curve(curve: PlotCurve, x: f32) -> vec2<f32> {
+    if (curve.count < 2u) {
+        return vec2<f32>(0.0, 0.0);
+    }
+
+    let t = (x - curve.extra.y) / (curve.extra.z - curve.extra.y);
+    let position = clamp(t, 0.0, 1.0) * f32(curve.count - 1u);
+    let index = min(u32(position), curve.count - 2u);
+
+    let value = mix(
+        get_sample(curve.offset + index),
+        get_sample(curve.offset + index + 1u),
+        position - f32(index),
+    );
+
+    return vec2<f32>(value, select(0.0, 1.0, t >= 0.0 && t <= 1.0));
+}
+
+// `kind` is one of the `CURVE_*` constants in material.rs
+fn evaluate_curve(curve: PlotCurve, x: f32) -> vec2<f32> {
+    let p = curve.parameters;
+
+    switch curve.kind {
+        case 0u: {
+            return sample_curve(curve, x);
+        }
+        case 1u: {
+            return vec2<f32>(fUAF(x, p.x, p.y, p.z, p.w, curve.extra.x), 1.0);
+        }
+        case 2u: {
+            return vec2<f32>(fUAFdx(x, p.x, p.y, p.z, p.w), 1.0);
+        }
+        default: {
+            return vec2<f32>(uaf_reference(curve.offset, x), 1.0);
+        }
+    }
+}
+
+
+@fragment
+fn fragment(
+    in: MeshVertexOutput,
+) -> @location(0) vec4<f32> {
+    let uv = vec2<f32>(in.uv.x, 1.0 - in.uv.y);
+
+    let xy = mix(material.view.xz, material.view.yw, uv); // graph coords
+    let dxy = abs(vec2<f32>(dpdx(xy.x), dpdy(xy.y))); // pixel size in graph units
+
+    // background
+    var col: vec4<f32> = mix(material.background, vec4<f32>(0.0, 0.0, 0.0, 1.0), pow(length(0.5 - uv) * 1.414, 3.5));
+
+    // grid
+    for (var i: u32 = 0u; i < material.grid_count; i = i + 1u) {
+        let grid = material.grid[i];
+        col = draw_grid(col, xy, dxy, grid.step, grid.color);
+    }
+
+    // axes
+    let axes = vec2<f32>(1.0) - smoothstep(vec2<f32>(0.0), dxy * 1.5, abs(xy));
+    col = mix_color(col, material.axis_color, max(axes.x, axes.y));
+
+    // curves
+    for (var i: u32 = 0u; i < material.curve_count; i = i + 1u) {
+        let curve = material.curves[i];
+        let value = evaluate_curve(curve, xy.x);
+        let color = curve.color * vec4<f32>(1.0, 1.0, 1.0, value.y);
+
+        if (curve.dashed != 0u) {
+            col = draw_dashed_curve(col, xy, value.x, color, 24.0 * dxy.x);
+        } else {
+            col = draw_curve(col, xy.y, value.x, color);
+        }
+    }
+
+    return col;
+}
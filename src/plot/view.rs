@@ -0,0 +1,382 @@
+use bevy::{
+    input::mouse::{
+        MouseScrollUnit,
+        MouseWheel,
+    },
+    prelude::*,
+    sprite::Anchor,
+    text::Text2dBounds,
+    window::PrimaryWindow,
+};
+use bevy_egui::EguiContext;
+
+use super::{
+    GridStep,
+    PlotMaterial,
+};
+
+
+// ticks, labels, cursor readout and pan/zoom for a `PlotMaterial` drawn on a `size` quad
+//   add next to the `Handle<PlotMaterial>`, labels are spawned as children
+#[derive(Component, Clone, Debug)]
+pub struct PlotView {
+    pub size: Vec2,
+    pub font: Handle<Font>,
+    pub font_size: f32,
+    pub label_color: Color,
+    // roughly how many ticks to place along each axis
+    pub ticks: u32,
+    // replace the material grid with one that follows the tick spacing
+    pub adaptive_grid: bool,
+    pub interactive: bool,
+}
+
+impl PlotView {
+    pub fn new(size: Vec2, asset_server: &AssetServer) -> Self {
+        Self {
+            size,
+            font: asset_server.load("fonts/Caveat-Medium.ttf"),
+            font_size: 24.0,
+            label_color: Color::rgba(0.8, 0.8, 0.8, 0.9),
+            ticks: 10,
+            adaptive_grid: true,
+            interactive: true,
+        }
+    }
+
+    fn text_style(&self, color: Color) -> TextStyle {
+        TextStyle {
+            font: self.font.clone(),
+            font_size: self.font_size,
+            color,
+        }
+    }
+
+    fn local_position(&self, material: &PlotMaterial, graph: Vec2) -> Vec2 {
+        let uv = (graph - Vec2::new(material.x_range.x, material.y_range.x))
+            / Vec2::new(material.x_range.y - material.x_range.x, material.y_range.y - material.y_range.x);
+
+        (uv - 0.5) * self.size
+    }
+
+    fn graph_position(&self, material: &PlotMaterial, local: Vec2) -> Vec2 {
+        let uv = local / self.size + 0.5;
+
+        Vec2::new(
+            material.x_range.x + uv.x * (material.x_range.y - material.x_range.x),
+            material.y_range.x + uv.y * (material.y_range.y - material.y_range.x),
+        )
+    }
+}
+
+
+// 1, 2 or 5 times a power of ten, about `range / ticks` apart
+pub fn tick_step(range: f32, ticks: u32) -> f32 {
+    let raw = range.abs() / ticks.max(1) as f32;
+    if !raw.is_normal() {
+        return 1.0;
+    }
+
+    let magnitude = 10f32.powf(raw.log10().floor());
+    let step = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .find(|multiple| raw <= multiple * magnitude)
+        .unwrap_or(10.0);
+
+    step * magnitude
+}
+
+fn tick_values(start: f32, end: f32, step: f32) -> impl Iterator<Item = f32> {
+    let (start, end) = (start.min(end), start.max(end));
+    let first = (start / step).ceil() as i64;
+    let last = (end / step).floor() as i64;
+
+    (first..=last).map(move |i| i as f32 * step)
+}
+
+fn format_value(value: f32, step: f32) -> String {
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let value = if value.abs() < step * 1e-3 { 0.0 } else { value };
+
+    format!("{value:.decimals$}")
+}
+
+
+#[derive(Component)]
+pub(crate) struct PlotTickLabel;
+
+#[derive(Component)]
+pub(crate) struct PlotReadout;
+
+
+// the cursor in the local space of the plot quad, when it is over one
+fn cursor_local_position(
+    window: &Window,
+    cameras: &Query<(&Camera, &GlobalTransform)>,
+    transform: &GlobalTransform,
+) -> Option<Vec2> {
+    let cursor = window.cursor_position()?;
+
+    let mut cameras = cameras
+        .iter()
+        .filter(|(camera, _)| camera.is_active)
+        .filter(|(camera, _)| camera.logical_viewport_rect().is_none_or(|rect| rect.contains(cursor)))
+        .collect::<Vec<_>>();
+    cameras.sort_by_key(|(camera, _)| -camera.order);
+
+    let (camera, camera_transform) = cameras.first()?;
+    let world = camera.viewport_to_world_2d(camera_transform, cursor)?;
+
+    Some(transform.affine().inverse().transform_point3(world.extend(0.0)).truncate())
+}
+
+fn egui_wants_pointer(egui_contexts: &mut Query<&mut EguiContext, With<PrimaryWindow>>) -> bool {
+    egui_contexts
+        .iter_mut()
+        .any(|mut context| {
+            let context = context.get_mut();
+            context.wants_pointer_input() || context.is_pointer_over_area()
+        })
+}
+
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn pan_zoom_plots(
+    plots: Query<(Entity, &PlotView, &Handle<PlotMaterial>, &GlobalTransform)>,
+    mut plot_materials: ResMut<Assets<PlotMaterial>>,
+    mut wheel: EventReader<MouseWheel>,
+    buttons: Res<Input<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut egui_contexts: Query<&mut EguiContext, With<PrimaryWindow>>,
+    mut dragging: Local<Option<(Entity, Vec2)>>,
+) {
+    let scroll = wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 40.0,
+        })
+        .sum::<f32>();
+
+    if !buttons.pressed(MouseButton::Left) {
+        *dragging = None;
+    }
+
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    let egui_wants_pointer = egui_wants_pointer(&mut egui_contexts);
+
+    for (entity, view, material_handle, transform) in &plots {
+        if !view.interactive {
+            continue;
+        }
+
+        let Some(local) = cursor_local_position(window, &cameras, transform) else {
+            continue;
+        };
+        let Some(material) = plot_materials.get(material_handle) else {
+            continue;
+        };
+        let cursor = view.graph_position(material, local);
+
+        let hovered = local.abs().cmple(view.size * 0.5).all() && !egui_wants_pointer;
+
+        // drag the graph point under the cursor along with it
+        let pan = match *dragging {
+            Some((dragged, anchor)) if dragged == entity => Some(anchor - cursor),
+            _ => None,
+        };
+
+        if hovered && buttons.just_pressed(MouseButton::Left) {
+            *dragging = Some((entity, cursor));
+        }
+
+        let zoom = if hovered && scroll != 0.0 {
+            Some(0.9f32.powf(scroll))
+        } else {
+            None
+        };
+
+        if pan.is_none() && zoom.is_none() {
+            continue;
+        }
+
+        let Some(material) = plot_materials.get_mut(material_handle) else {
+            continue;
+        };
+
+        if let Some(pan) = pan.filter(|pan| *pan != Vec2::ZERO) {
+            material.x_range += pan.x;
+            material.y_range += pan.y;
+        }
+
+        if let Some(zoom) = zoom {
+            material.x_range = cursor.x + (material.x_range - cursor.x) * zoom;
+            material.y_range = cursor.y + (material.y_range - cursor.y) * zoom;
+        }
+    }
+}
+
+
+pub(crate) fn update_plot_ticks(
+    mut commands: Commands,
+    mut plots: Query<(Entity, &PlotView, &Handle<PlotMaterial>, Option<&Children>)>,
+    mut plot_materials: ResMut<Assets<PlotMaterial>>,
+    mut labels: Query<(&mut Text, &mut Transform, &mut Anchor), With<PlotTickLabel>>,
+) {
+    for (entity, view, material_handle, children) in &mut plots {
+        let Some(material) = plot_materials.get(material_handle) else {
+            continue;
+        };
+
+        let x_step = tick_step(material.x_range.y - material.x_range.x, view.ticks);
+        let y_step = tick_step(material.y_range.y - material.y_range.x, view.ticks);
+
+        // axes sit at zero, pinned to the plot edges when zero is out of view
+        let half = view.size * 0.5;
+        let origin = view
+            .local_position(material, Vec2::ZERO)
+            .clamp(-half, half - Vec2::new(view.font_size * 2.0, view.font_size));
+
+        let mut ticks = Vec::new();
+        for x in tick_values(material.x_range.x, material.x_range.y, x_step) {
+            let position = Vec2::new(view.local_position(material, Vec2::new(x, 0.0)).x, origin.y);
+            ticks.push((format_value(x, x_step), position + Vec2::new(4.0, -2.0), Anchor::TopLeft));
+        }
+        for y in tick_values(material.y_range.x, material.y_range.y, y_step) {
+            if y == 0.0 {
+                continue;
+            }
+
+            let position = Vec2::new(origin.x, view.local_position(material, Vec2::new(0.0, y)).y);
+            ticks.push((format_value(y, y_step), position + Vec2::new(4.0, 2.0), Anchor::BottomLeft));
+        }
+
+        let mut existing = children
+            .into_iter()
+            .flatten()
+            .filter(|child| labels.contains(**child))
+            .copied()
+            .collect::<Vec<_>>();
+
+        for (text, position, anchor) in &ticks {
+            let sections = vec![TextSection::new(text.clone(), view.text_style(view.label_color))];
+            let translation = position.extend(0.1);
+
+            match existing.pop() {
+                Some(label) => {
+                    let (mut label_text, mut label_transform, mut label_anchor) = labels.get_mut(label).unwrap();
+                    if label_text.sections[0].value != *text {
+                        label_text.sections = sections;
+                    }
+                    label_transform.translation = translation;
+                    *label_anchor = anchor.clone();
+                }
+                None => {
+                    let label = commands.spawn((
+                        Text2dBundle {
+                            text: Text::from_sections(sections),
+                            text_anchor: anchor.clone(),
+                            transform: Transform::from_translation(translation),
+                            ..default()
+                        },
+                        PlotTickLabel,
+                    )).id();
+                    commands.entity(entity).add_child(label);
+                }
+            }
+        }
+
+        for label in existing {
+            commands.entity(label).despawn_recursive();
+        }
+
+        if view.adaptive_grid {
+            let step = x_step.max(y_step);
+            let grid = [
+                GridStep::new(step / 5.0, Color::rgba(0.6, 0.6, 0.6, 0.1)),
+                GridStep::new(step, Color::rgba(0.6, 0.6, 0.6, 0.25)),
+            ];
+
+            if material.grid != grid {
+                plot_materials.get_mut(material_handle).unwrap().grid = grid.to_vec();
+            }
+        }
+    }
+}
+
+
+type PlotReadoutQuery<'w, 's> = Query<'w, 's, (
+    Entity,
+    &'static PlotView,
+    &'static Handle<PlotMaterial>,
+    &'static GlobalTransform,
+    Option<&'static Children>,
+)>;
+
+pub(crate) fn update_plot_readouts(
+    mut commands: Commands,
+    plots: PlotReadoutQuery,
+    plot_materials: Res<Assets<PlotMaterial>>,
+    mut readouts: Query<(&mut Text, &mut Transform, &mut Visibility), With<PlotReadout>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+
+    for (entity, view, material_handle, transform, children) in &plots {
+        let readout = children
+            .into_iter()
+            .flatten()
+            .find(|child| readouts.contains(**child))
+            .copied();
+
+        let Some(readout) = readout else {
+            let readout = commands.spawn((
+                Text2dBundle {
+                    text_anchor: Anchor::BottomLeft,
+                    text_2d_bounds: Text2dBounds::UNBOUNDED,
+                    visibility: Visibility::Hidden,
+                    ..default()
+                },
+                PlotReadout,
+            )).id();
+            commands.entity(entity).add_child(readout);
+            continue;
+        };
+
+        let (mut text, mut readout_transform, mut visibility) = readouts.get_mut(readout).unwrap();
+
+        let local = cursor_local_position(window, &cameras, transform)
+            .filter(|local| local.abs().cmple(view.size * 0.5).all());
+        let (Some(local), Some(material)) = (local, plot_materials.get(material_handle)) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        let cursor = view.graph_position(material, local);
+        let precision = tick_step(material.x_range.y - material.x_range.x, view.ticks) / 100.0;
+
+        let mut sections = vec![TextSection::new(
+            format!("({}, {})", format_value(cursor.x, precision), format_value(cursor.y, precision)),
+            view.text_style(view.label_color),
+        )];
+        for curve in material.visible_curves() {
+            if let Some(value) = curve.source.eval(cursor.x) {
+                sections.push(TextSection::new(
+                    format!("\n{}: {}", curve.label, format_value(value, precision)),
+                    view.text_style(curve.color.with_a(1.0)),
+                ));
+            }
+        }
+
+        text.sections = sections;
+        readout_transform.translation = (local + Vec2::new(12.0, 12.0)).extend(0.2);
+        *visibility = Visibility::Inherited;
+    }
+}
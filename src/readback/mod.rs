@@ -0,0 +1,566 @@
+use std::sync::{
+    atomic::{
+        AtomicU8,
+        Ordering,
+    },
+    mpsc::{
+        channel,
+        Receiver,
+        Sender,
+    },
+    Arc,
+    Mutex,
+};
+
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{
+            ExtractResource,
+            ExtractResourcePlugin,
+        },
+        render_asset::RenderAssets,
+        render_graph::{
+            self,
+            RenderGraph,
+        },
+        render_resource::{
+            Buffer,
+            Extent3d,
+            ImageCopyBuffer,
+            ImageDataLayout,
+            MapMode,
+            Texture,
+        },
+        renderer::{
+            RenderContext,
+            RenderDevice,
+        },
+        Render,
+        RenderApp,
+        RenderSet,
+    },
+};
+
+use crate::{
+    automata::{
+        AutomataField,
+        AutomataPipeline,
+        TEXEL_SIZE,
+    },
+    neat::NeatField,
+};
+
+mod output;
+pub use output::*;
+
+mod request;
+pub use request::*;
+
+
+// add after the `NeatPlugin`, the readback node is ordered after its "neat" render graph node
+#[derive(Default)]
+pub struct ReadbackPlugin;
+
+impl Plugin for ReadbackPlugin {
+    fn build(&self, app: &mut App) {
+        let (sender, receiver) = channel();
+
+        app.add_event::<FieldSnapshot>();
+        app.init_resource::<Readback>();
+        app.insert_resource(SnapshotReceiver(Mutex::new(receiver)));
+
+        app.add_plugins(ExtractResourcePlugin::<Readback>::default());
+
+        if !app.is_plugin_added::<OutputPlugin>() {
+            app.add_plugins(OutputPlugin);
+        }
+
+        app.add_systems(First, clear_readback_request);
+        app.add_systems(PreUpdate, receive_snapshots);
+
+        let (pending_sender, pending_receiver) = channel();
+        // up to three textures per snapshot, each mapped a frame or two after its copy
+        let staging = StagingPool::new("readback staging buffer", 8);
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.insert_resource(PendingReadbacks {
+            receiver: Mutex::new(pending_receiver),
+            mapping: Vec::new(),
+            snapshots: Mutex::new(sender),
+            staging: staging.clone(),
+        });
+        render_app.add_systems(
+            Render,
+            map_pending_readbacks.in_set(RenderSet::Cleanup),
+        );
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node("readback", ReadbackNode {
+            pending: Mutex::new(pending_sender),
+            staging,
+        });
+        render_graph.add_node_edge("neat", "readback");
+        render_graph.add_node_edge(
+            "readback",
+            bevy::render::main_graph::node::CAMERA_DRIVER,
+        );
+    }
+}
+
+
+// snapshots are taken after the update dispatch of every `interval` steps, or once after `request`
+// requested snapshots always include every texture so they are complete enough for checkpoints
+#[derive(Resource, Clone, ExtractResource)]
+pub struct Readback {
+    pub interval: Option<u64>,
+    pub nodes: bool,
+    pub edges: bool,
+    pub uaf: bool,
+    requested: bool,
+}
+
+impl Default for Readback {
+    fn default() -> Self {
+        Self {
+            interval: None,
+            nodes: true,
+            edges: true,
+            uaf: true,
+            requested: false,
+        }
+    }
+}
+
+impl Readback {
+    pub fn every(interval: u64) -> Self {
+        Self {
+            interval: Some(interval),
+            ..default()
+        }
+    }
+
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    fn should_read(&self, step: u64) -> bool {
+        self.requested || self.interval.is_some_and(|interval| interval > 0 && step.is_multiple_of(interval))
+    }
+}
+
+fn clear_readback_request(
+    mut readback: ResMut<Readback>,
+) {
+    if readback.requested {
+        readback.requested = false;
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NodeState {
+    pub value: f32,
+    pub derivative: f32,
+    pub integral: f32,
+}
+
+impl From<[f32; 4]> for NodeState {
+    fn from(texel: [f32; 4]) -> Self {
+        Self {
+            value: texel[0],
+            derivative: texel[1],
+            integral: texel[2],
+        }
+    }
+}
+
+// alpha is always written as 1.0 by `set_state`
+impl From<NodeState> for [f32; 4] {
+    fn from(node: NodeState) -> Self {
+        [node.value, node.derivative, node.integral, 1.0]
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EdgeState {
+    pub from_node_location: IVec2,
+    pub weight: f32,
+    pub downregulation: f32,
+}
+
+impl EdgeState {
+    // unused slot of an imported graph, `update_edges` and the connectome exporters skip it
+    pub const EMPTY: Self = Self {
+        from_node_location: IVec2::NEG_ONE,
+        weight: 0.0,
+        downregulation: 0.0,
+    };
+
+    pub fn is_empty(&self) -> bool {
+        self.from_node_location.x < 0
+    }
+}
+
+impl From<[f32; 4]> for EdgeState {
+    fn from(texel: [f32; 4]) -> Self {
+        Self {
+            from_node_location: IVec2::new(texel[0] as i32, texel[1] as i32),
+            weight: texel[2],
+            downregulation: texel[3],
+        }
+    }
+}
+
+impl From<EdgeState> for [f32; 4] {
+    fn from(edge: EdgeState) -> Self {
+        [
+            edge.from_node_location.x as f32,
+            edge.from_node_location.y as f32,
+            edge.weight,
+            edge.downregulation,
+        ]
+    }
+}
+
+
+// row-major field state, edges are stored layer by layer (edge index major)
+#[derive(Event, Clone, Debug, Default)]
+pub struct FieldSnapshot {
+    // nodes texture of the field the snapshot was read from, tells restarted fields apart
+    pub field: Handle<Image>,
+    pub step: u64,
+    pub width: u32,
+    pub height: u32,
+    pub edge_count: u32,
+    pub nodes: Vec<NodeState>,
+    pub edges: Vec<EdgeState>,
+    pub uaf: Vec<Vec4>,
+}
+
+impl FieldSnapshot {
+    pub fn index(&self, location: UVec2) -> usize {
+        (location.y * self.width + location.x) as usize
+    }
+
+    pub fn node(&self, location: UVec2) -> Option<&NodeState> {
+        self.nodes.get(self.index(location))
+    }
+
+    pub fn edge(&self, location: UVec2, edge_index: u32) -> Option<&EdgeState> {
+        let layer = (edge_index * self.width * self.height) as usize;
+        self.edges.get(layer + self.index(location))
+    }
+
+    pub fn uaf(&self, location: UVec2) -> Option<&Vec4> {
+        self.uaf.get(self.index(location))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        let texels = (self.width * self.height) as usize;
+
+        self.nodes.len() == texels
+            && self.edges.len() == texels * self.edge_count as usize
+            && self.uaf.len() == texels
+    }
+
+    pub fn values(&self) -> Vec<f32> {
+        self.nodes.iter().map(|node| node.value).collect()
+    }
+}
+
+
+#[derive(Resource)]
+struct SnapshotReceiver(Mutex<Receiver<FieldSnapshot>>);
+
+fn receive_snapshots(
+    receiver: Res<SnapshotReceiver>,
+    mut snapshots: EventWriter<FieldSnapshot>,
+) {
+    let receiver = receiver.0.lock().unwrap();
+    snapshots.send_batch(receiver.try_iter());
+}
+
+
+#[derive(Clone, Copy)]
+enum ReadbackTarget {
+    Nodes,
+    Edges,
+    Uaf,
+}
+
+impl ReadbackTarget {
+    fn name(self) -> &'static str {
+        match self {
+            ReadbackTarget::Nodes => "nodes",
+            ReadbackTarget::Edges => "edges",
+            ReadbackTarget::Uaf => "uaf activations",
+        }
+    }
+}
+
+struct StagingBuffer {
+    target: ReadbackTarget,
+    buffer: Buffer,
+    padded_bytes_per_row: usize,
+    size: Extent3d,
+}
+
+impl StagingBuffer {
+    // None when the texture does not fit a buffer, e.g. a field beyond the checks of `AutomataField::check_limits`
+    fn copy_from(
+        render_context: &mut RenderContext,
+        staging: &StagingPool,
+        target: ReadbackTarget,
+        texture: &Texture,
+        size: Extent3d,
+    ) -> Option<Self> {
+        let padded_bytes_per_row = RenderDevice::align_copy_bytes_per_row(size.width as usize * TEXEL_SIZE);
+        let bytes = (padded_bytes_per_row as u64)
+            .checked_mul(size.height as u64 * size.depth_or_array_layers as u64);
+
+        let max_buffer_size = render_context.render_device().limits().max_buffer_size;
+        let Some(bytes) = bytes.filter(|&bytes| bytes <= max_buffer_size) else {
+            error!("not reading back the {}, the texture exceeds the {max_buffer_size} byte buffer limit", target.name());
+            return None;
+        };
+
+        let buffer = staging.take(render_context.render_device(), bytes);
+
+        render_context.command_encoder().copy_texture_to_buffer(
+            texture.as_image_copy(),
+            ImageCopyBuffer {
+                buffer: &buffer,
+                layout: ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row as u32),
+                    rows_per_image: Some(size.height),
+                },
+            },
+            size,
+        );
+
+        Some(Self {
+            target,
+            buffer,
+            padded_bytes_per_row,
+            size,
+        })
+    }
+
+    // drops the row padding, must only be called once mapped
+    fn texels(&self) -> Vec<[f32; 4]> {
+        let data = self.buffer.slice(..).get_mapped_range();
+        let row_bytes = self.size.width as usize * TEXEL_SIZE;
+
+        let texels = data
+            .chunks_exact(self.padded_bytes_per_row)
+            .flat_map(|row| row[..row_bytes].chunks_exact(TEXEL_SIZE))
+            .map(|texel| {
+                let channel = |i: usize| f32::from_ne_bytes(texel[i * 4..i * 4 + 4].try_into().unwrap());
+                [channel(0), channel(1), channel(2), channel(3)]
+            })
+            .collect();
+
+        drop(data);
+        self.buffer.unmap();
+
+        texels
+    }
+}
+
+// state of a `map_buffer` request, set from its callback
+#[derive(Clone, Default)]
+pub(crate) struct MapState(Arc<AtomicU8>);
+
+impl MapState {
+    const PENDING: u8 = 0;
+    const MAPPED: u8 = 1;
+    const FAILED: u8 = 2;
+
+    pub(crate) fn map(render_device: &RenderDevice, buffer: &Buffer, label: &'static str) -> Self {
+        let state = Self::default();
+        let callback_state = state.0.clone();
+
+        render_device.map_buffer(&buffer.slice(..), MapMode::Read, move |result| {
+            let value = match result {
+                Ok(()) => Self::MAPPED,
+                Err(err) => {
+                    error!("failed to map {label}: {err}");
+                    Self::FAILED
+                }
+            };
+
+            callback_state.store(value, Ordering::Release);
+        });
+
+        state
+    }
+
+    pub(crate) fn is_mapped(&self) -> bool {
+        self.0.load(Ordering::Acquire) == Self::MAPPED
+    }
+
+    pub(crate) fn is_failed(&self) -> bool {
+        self.0.load(Ordering::Acquire) == Self::FAILED
+    }
+
+    pub(crate) fn is_pending(&self) -> bool {
+        self.0.load(Ordering::Acquire) == Self::PENDING
+    }
+}
+
+struct PendingReadback {
+    snapshot: FieldSnapshot,
+    buffers: Vec<StagingBuffer>,
+    mapped: Vec<MapState>,
+}
+
+impl PendingReadback {
+    fn is_mapped(&self) -> bool {
+        self.mapped.iter().all(MapState::is_mapped)
+    }
+
+    // a failed buffer never maps, the whole snapshot is dropped once the others settle
+    fn is_failed(&self) -> bool {
+        self.mapped.iter().any(MapState::is_failed)
+            && !self.mapped.iter().any(MapState::is_pending)
+    }
+
+    // must only be called once mapped, the unmapped buffers go back to `staging`
+    fn into_snapshot(self, staging: &StagingPool) -> FieldSnapshot {
+        let mut snapshot = self.snapshot;
+
+        for buffer in self.buffers {
+            let texels = buffer.texels();
+            staging.give(buffer.buffer);
+
+            match buffer.target {
+                ReadbackTarget::Nodes => snapshot.nodes = texels.into_iter().map(NodeState::from).collect(),
+                ReadbackTarget::Edges => snapshot.edges = texels.into_iter().map(EdgeState::from).collect(),
+                ReadbackTarget::Uaf => snapshot.uaf = texels.into_iter().map(Vec4::from_array).collect(),
+            }
+        }
+
+        snapshot
+    }
+}
+
+
+#[derive(Resource)]
+struct PendingReadbacks {
+    receiver: Mutex<Receiver<PendingReadback>>,
+    mapping: Vec<PendingReadback>,
+    snapshots: Mutex<Sender<FieldSnapshot>>,
+    staging: StagingPool,
+}
+
+// runs after the render graph has been submitted, so copies are queued before mapping
+fn map_pending_readbacks(
+    mut pending: ResMut<PendingReadbacks>,
+    render_device: Res<RenderDevice>,
+) {
+    let submitted: Vec<PendingReadback> = pending.receiver.lock().unwrap().try_iter().collect();
+
+    for mut readback in submitted {
+        readback.mapped = readback.buffers
+            .iter()
+            .map(|staging| MapState::map(&render_device, &staging.buffer, "readback buffer"))
+            .collect();
+
+        pending.mapping.push(readback);
+    }
+
+    let (ready, mapping): (Vec<_>, Vec<_>) = std::mem::take(&mut pending.mapping)
+        .into_iter()
+        .filter(|readback| !readback.is_failed())
+        .partition(PendingReadback::is_mapped);
+    pending.mapping = mapping;
+
+    let sender = pending.snapshots.lock().unwrap();
+    for readback in ready {
+        let _ = sender.send(readback.into_snapshot(&pending.staging));
+    }
+}
+
+
+struct ReadbackNode {
+    pending: Mutex<Sender<PendingReadback>>,
+    staging: StagingPool,
+}
+
+impl render_graph::Node for ReadbackNode {
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(readback) = world.get_resource::<Readback>() else {
+            return Ok(());
+        };
+        let Some(step) = world.resource::<AutomataPipeline>().step else {
+            return Ok(());
+        };
+        if !readback.should_read(step) {
+            return Ok(());
+        }
+        let Some(automata) = world.get_resource::<AutomataField>() else {
+            return Ok(());
+        };
+
+        let gpu_images = world.resource::<RenderAssets<Image>>();
+        let field_size = Extent3d {
+            width: automata.width(),
+            height: automata.height(),
+            depth_or_array_layers: 1,
+        };
+
+        let mut buffers = Vec::new();
+
+        if readback.nodes || readback.requested {
+            if let Some(nodes) = gpu_images.get(&automata.nodes) {
+                buffers.extend(StagingBuffer::copy_from(render_context, &self.staging, ReadbackTarget::Nodes, &nodes.texture, field_size));
+            }
+        }
+
+        if readback.edges || readback.requested {
+            if let Some(edges) = gpu_images.get(&automata.edges) {
+                let edges_size = Extent3d {
+                    depth_or_array_layers: automata.edge_count(),
+                    ..field_size
+                };
+                buffers.extend(StagingBuffer::copy_from(render_context, &self.staging, ReadbackTarget::Edges, &edges.texture, edges_size));
+            }
+        }
+
+        if readback.uaf || readback.requested {
+            let uaf_activations = world
+                .get_resource::<NeatField>()
+                .and_then(|neat_field| gpu_images.get(&neat_field.uaf_activations));
+
+            if let Some(uaf_activations) = uaf_activations {
+                buffers.extend(StagingBuffer::copy_from(render_context, &self.staging, ReadbackTarget::Uaf, &uaf_activations.texture, field_size));
+            }
+        }
+
+        if buffers.is_empty() {
+            return Ok(());
+        }
+
+        let _ = self.pending.lock().unwrap().send(PendingReadback {
+            snapshot: FieldSnapshot {
+                field: automata.nodes.clone(),
+                step,
+                width: automata.width(),
+                height: automata.height(),
+                edge_count: automata.edge_count(),
+                ..default()
+            },
+            buffers,
+            mapped: Vec::new(),
+        });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,679 @@
+use std::{
+    borrow::Cow,
+    collections::VecDeque,
+    sync::{
+        mpsc::{
+            channel,
+            Receiver,
+            Sender,
+        },
+        Arc,
+        Mutex,
+    },
+};
+
+use bevy::{
+    asset::{
+        load_internal_asset,
+        HandleUntyped,
+    },
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        extract_resource::{
+            ExtractResource,
+            ExtractResourcePlugin,
+        },
+        render_graph::{
+            self,
+            RenderGraph,
+        },
+        render_resource::{
+            BindGroup,
+            BindGroupDescriptor,
+            BindGroupEntry,
+            BindGroupLayout,
+            BindGroupLayoutDescriptor,
+            BindGroupLayoutEntry,
+            BindingType,
+            Buffer,
+            BufferBindingType,
+            BufferDescriptor,
+            BufferUsages,
+            CachedComputePipelineId,
+            ComputePassDescriptor,
+            ComputePipelineDescriptor,
+            PipelineCache,
+            ShaderStages,
+            ShaderType,
+            StorageBuffer,
+            UniformBuffer,
+        },
+        renderer::{
+            RenderContext,
+            RenderDevice,
+            RenderQueue,
+        },
+        Render,
+        RenderApp,
+        RenderSet,
+    },
+};
+
+use crate::{
+    automata::{
+        AutomataBindGroup,
+        AutomataPipeline,
+    },
+    neat::{
+        FieldInput,
+        RegionId,
+    },
+};
+
+use super::MapState;
+
+
+const OUTPUT_SHADER_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 90412736518843);
+const WORKGROUP_SIZE: u32 = 64;
+
+pub const MAX_OUTPUT_REGIONS: usize = 8;
+
+
+// gathers the values of `FieldOutput` regions every step, added by the `ReadbackPlugin`
+#[derive(Default)]
+pub struct OutputPlugin;
+
+impl Plugin for OutputPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            OUTPUT_SHADER_HANDLE,
+            "output.wgsl",
+            Shader::from_wgsl
+        );
+
+        let (sender, receiver) = channel();
+
+        app.add_event::<OutputSample>();
+        app.register_type::<FieldOutput>();
+        app.init_resource::<FieldOutput>();
+        app.init_resource::<OutputSeries>();
+        app.insert_resource(OutputReceiver(Mutex::new(receiver)));
+
+        app.add_plugins(ExtractResourcePlugin::<FieldOutput>::default());
+
+        app.add_systems(PreUpdate, receive_output_samples);
+
+        let (pending_sender, pending_receiver) = channel();
+        let staging = StagingPool::new("output staging buffer", 4);
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.insert_resource(PendingOutputs {
+            receiver: Mutex::new(pending_receiver),
+            mapping: Vec::new(),
+            samples: Mutex::new(sender),
+            staging: staging.clone(),
+        });
+        render_app.add_systems(
+            Render,
+            (
+                prepare_output_buffers.in_set(RenderSet::Prepare),
+                queue_output_bind_group.in_set(RenderSet::Queue),
+                map_pending_outputs.in_set(RenderSet::Cleanup),
+            )
+        );
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node("output", OutputNode {
+            pending: Mutex::new(pending_sender),
+            staging,
+            last_step: None,
+            record: false,
+        });
+        render_graph.add_node_edge("neat", "output");
+        render_graph.add_node_edge(
+            "output",
+            bevy::render::main_graph::node::CAMERA_DRIVER,
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.init_resource::<OutputPipeline>();
+        render_app.init_resource::<OutputBuffers>();
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub struct OutputRegion {
+    #[reflect(ignore)]
+    pub id: RegionId,
+    pub origin: UVec2,
+    pub size: UVec2,
+}
+
+impl OutputRegion {
+    pub fn new(origin: UVec2, size: UVec2) -> Self {
+        Self {
+            id: RegionId::default(),
+            origin,
+            size,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        (self.size.x * self.size.y) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// node values of each region are read back after every step as an `OutputSample`
+#[derive(Resource, Clone, Debug, Default, ExtractResource, Reflect)]
+#[reflect(Resource)]
+pub struct FieldOutput {
+    pub enabled: bool,
+    pub regions: Vec<OutputRegion>,
+}
+
+impl FieldOutput {
+    pub fn with_region(mut self, region: OutputRegion) -> Self {
+        self.add_region(region);
+        self
+    }
+
+    // returns the region's index, or None once `MAX_OUTPUT_REGIONS` are in use
+    pub fn add_region(&mut self, mut region: OutputRegion) -> Option<usize> {
+        if self.regions.len() >= MAX_OUTPUT_REGIONS {
+            warn!("field output is limited to {MAX_OUTPUT_REGIONS} regions");
+            return None;
+        }
+
+        region.id = RegionId::next();
+        self.enabled = true;
+        self.regions.push(region);
+        Some(self.regions.len() - 1)
+    }
+
+    pub fn index_of(&self, id: RegionId) -> Option<usize> {
+        self.regions.iter().position(|region| region.id == id)
+    }
+
+    // later regions shift down by one
+    pub fn remove_region(&mut self, index: usize) -> Option<OutputRegion> {
+        (index < self.regions.len()).then(|| self.regions.remove(index))
+    }
+
+    pub fn remove_id(&mut self, id: RegionId) -> Option<OutputRegion> {
+        self.remove_region(self.index_of(id)?)
+    }
+
+    fn active_regions(&self) -> &[OutputRegion] {
+        match self.enabled {
+            true => &self.regions[..self.regions.len().min(MAX_OUTPUT_REGIONS)],
+            false => &[],
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.active_regions().iter().map(OutputRegion::len).sum()
+    }
+}
+
+
+// row-major node values of each output region after `step`
+#[derive(Event, Clone, Debug, Default, PartialEq)]
+pub struct OutputSample {
+    pub step: u64,
+    // `FieldInput::sequence` of the input streamed into the same step
+    pub sequence: Option<u64>,
+    pub regions: Vec<Vec<f32>>,
+}
+
+impl OutputSample {
+    pub fn region(&self, index: usize) -> Option<&[f32]> {
+        self.regions.get(index).map(Vec::as_slice)
+    }
+
+    pub fn mean(&self, index: usize) -> Option<f32> {
+        self.region(index)
+            .filter(|values| !values.is_empty())
+            .map(|values| values.iter().sum::<f32>() / values.len() as f32)
+    }
+}
+
+// the most recent `capacity` samples in step order
+#[derive(Resource, Clone, Debug)]
+pub struct OutputSeries {
+    pub capacity: usize,
+    samples: VecDeque<OutputSample>,
+}
+
+impl Default for OutputSeries {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+impl OutputSeries {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            samples: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, sample: OutputSample) {
+        while self.samples.len() >= self.capacity.max(1) {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &OutputSample> {
+        self.samples.iter()
+    }
+
+    pub fn latest(&self) -> Option<&OutputSample> {
+        self.samples.back()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    // (step, mean value) of a region, e.g. for plotting
+    pub fn means(&self, region: usize) -> Vec<(u64, f32)> {
+        self.samples
+            .iter()
+            .filter_map(|sample| sample.mean(region).map(|mean| (sample.step, mean)))
+            .collect()
+    }
+}
+
+
+#[derive(Resource)]
+struct OutputReceiver(Mutex<Receiver<OutputSample>>);
+
+fn receive_output_samples(
+    receiver: Res<OutputReceiver>,
+    mut series: ResMut<OutputSeries>,
+    mut samples: EventWriter<OutputSample>,
+) {
+    let receiver = receiver.0.lock().unwrap();
+
+    for sample in receiver.try_iter() {
+        series.push(sample.clone());
+        samples.send(sample);
+    }
+}
+
+
+#[derive(Clone, Copy, Default, ShaderType)]
+struct OutputRegionUniform {
+    // min x, min y, max x, max y (exclusive)
+    bounds: IVec4,
+    offset: u32,
+}
+
+#[derive(Clone, Default, ShaderType)]
+struct OutputUniform {
+    regions: [OutputRegionUniform; MAX_OUTPUT_REGIONS],
+    region_count: u32,
+    value_count: u32,
+}
+
+#[derive(Resource)]
+struct OutputBuffers {
+    uniform: UniformBuffer<OutputUniform>,
+    // compact values of every region, copied into a staging buffer after each gather
+    values: StorageBuffer<Vec<f32>>,
+    // region lengths the values buffer was laid out for
+    lengths: Vec<usize>,
+}
+
+impl Default for OutputBuffers {
+    fn default() -> Self {
+        let mut values = StorageBuffer::<Vec<f32>>::default();
+        values.add_usages(BufferUsages::COPY_SRC);
+
+        Self {
+            uniform: UniformBuffer::default(),
+            values,
+            lengths: Vec::new(),
+        }
+    }
+}
+
+fn prepare_output_buffers(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut buffers: ResMut<OutputBuffers>,
+    field_output: Res<FieldOutput>,
+) {
+    let regions = field_output.active_regions();
+
+    let uniform = buffers.uniform.get_mut();
+    uniform.regions = [OutputRegionUniform::default(); MAX_OUTPUT_REGIONS];
+
+    let mut offset = 0;
+    for (uniform, region) in uniform.regions.iter_mut().zip(regions) {
+        *uniform = OutputRegionUniform {
+            bounds: IVec4::new(
+                region.origin.x as i32,
+                region.origin.y as i32,
+                (region.origin.x + region.size.x) as i32,
+                (region.origin.y + region.size.y) as i32,
+            ),
+            offset,
+        };
+        offset += region.len() as u32;
+    }
+    uniform.region_count = regions.len() as u32;
+    uniform.value_count = offset;
+    buffers.uniform.write_buffer(&render_device, &render_queue);
+
+    // the gather overwrites every value, so the buffer is only reallocated when the layout changes
+    let lengths: Vec<usize> = regions.iter().map(OutputRegion::len).collect();
+    if buffers.lengths != lengths || buffers.values.buffer().is_none() {
+        // empty storage buffers can't be bound
+        buffers.values.set(vec![0.0; field_output.len().max(1)]);
+        buffers.values.write_buffer(&render_device, &render_queue);
+        buffers.lengths = lengths;
+    }
+}
+
+
+#[derive(Resource)]
+struct OutputBindGroup(BindGroup);
+
+fn queue_output_bind_group(
+    mut commands: Commands,
+    pipeline: Res<OutputPipeline>,
+    render_device: Res<RenderDevice>,
+    buffers: Res<OutputBuffers>,
+) {
+    let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: buffers.uniform.binding().unwrap(),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: buffers.values.binding().unwrap(),
+            },
+        ],
+    });
+
+    commands.insert_resource(OutputBindGroup(bind_group));
+}
+
+
+#[derive(Resource)]
+struct OutputPipeline {
+    bind_group_layout: BindGroupLayout,
+    gather_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for OutputPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let automata = world.resource::<AutomataPipeline>();
+
+        let bind_group_layout =
+            world
+                .resource::<RenderDevice>()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("output bind group layout"),
+                    entries: &[
+                        BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                        BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Storage { read_only: false },
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let gather_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: None,
+            layout: vec![automata.bind_group_layout.clone(), bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader: OUTPUT_SHADER_HANDLE.typed(),
+            shader_defs: vec![],
+            entry_point: Cow::from("gather"),
+        });
+
+        OutputPipeline {
+            bind_group_layout,
+            gather_pipeline,
+        }
+    }
+}
+
+
+// staging buffers go back here once read, and are reused while the copied regions keep their size
+#[derive(Clone)]
+pub(crate) struct StagingPool {
+    buffers: Arc<Mutex<Vec<Buffer>>>,
+    label: &'static str,
+    capacity: usize,
+}
+
+impl StagingPool {
+    pub(crate) fn new(label: &'static str, capacity: usize) -> Self {
+        Self {
+            buffers: default(),
+            label,
+            capacity,
+        }
+    }
+
+    pub(crate) fn take(&self, render_device: &RenderDevice, size: u64) -> Buffer {
+        let mut pool = self.buffers.lock().unwrap();
+
+        match pool.iter().position(|buffer| buffer.size() == size) {
+            Some(index) => pool.swap_remove(index),
+            None => render_device.create_buffer(&BufferDescriptor {
+                label: Some(self.label),
+                size,
+                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        }
+    }
+
+    // buffers of an old size are dropped first to make room
+    pub(crate) fn give(&self, buffer: Buffer) {
+        let mut pool = self.buffers.lock().unwrap();
+
+        if pool.len() >= self.capacity {
+            let stale = pool.iter().position(|pooled| pooled.size() != buffer.size()).unwrap_or(0);
+            pool.swap_remove(stale);
+        }
+        pool.push(buffer);
+    }
+}
+
+struct PendingOutput {
+    step: u64,
+    sequence: Option<u64>,
+    lengths: Vec<usize>,
+    buffer: Buffer,
+    mapped: Option<MapState>,
+}
+
+impl PendingOutput {
+    fn is_mapped(&self) -> bool {
+        self.mapped.as_ref().is_some_and(MapState::is_mapped)
+    }
+
+    fn is_failed(&self) -> bool {
+        self.mapped.as_ref().is_some_and(MapState::is_failed)
+    }
+
+    // must only be called once mapped, the unmapped buffer goes back to `staging`
+    fn into_sample(self, staging: &StagingPool) -> OutputSample {
+        let data = self.buffer.slice(..).get_mapped_range();
+        let values: Vec<f32> = data
+            .chunks_exact(4)
+            .map(|value| f32::from_ne_bytes(value.try_into().unwrap()))
+            .collect();
+        drop(data);
+        self.buffer.unmap();
+        staging.give(self.buffer);
+
+        let mut offset = 0;
+        let regions = self.lengths
+            .iter()
+            .map(|&len| {
+                let region = values[offset..offset + len].to_vec();
+                offset += len;
+                region
+            })
+            .collect();
+
+        OutputSample {
+            step: self.step,
+            sequence: self.sequence,
+            regions,
+        }
+    }
+}
+
+#[derive(Resource)]
+struct PendingOutputs {
+    receiver: Mutex<Receiver<PendingOutput>>,
+    mapping: Vec<PendingOutput>,
+    samples: Mutex<Sender<OutputSample>>,
+    staging: StagingPool,
+}
+
+// runs after the render graph has been submitted, so copies are queued before mapping
+fn map_pending_outputs(
+    mut pending: ResMut<PendingOutputs>,
+    render_device: Res<RenderDevice>,
+) {
+    let submitted: Vec<PendingOutput> = pending.receiver.lock().unwrap().try_iter().collect();
+
+    for mut output in submitted {
+        output.mapped = Some(MapState::map(&render_device, &output.buffer, "output buffer"));
+        pending.mapping.push(output);
+    }
+
+    // samples are sent in step order, a later sample waits for earlier ones to map or fail
+    let pending = &mut *pending;
+    let settled = pending.mapping
+        .iter()
+        .take_while(|output| output.is_mapped() || output.is_failed())
+        .count();
+
+    let sender = pending.samples.lock().unwrap();
+    for output in pending.mapping.drain(..settled) {
+        if output.is_mapped() {
+            let _ = sender.send(output.into_sample(&pending.staging));
+        }
+    }
+}
+
+
+// gathers once per simulated step, after the neat node has dispatched it
+struct OutputNode {
+    pending: Mutex<Sender<PendingOutput>>,
+    staging: StagingPool,
+    last_step: Option<u64>,
+    record: bool,
+}
+
+impl render_graph::Node for OutputNode {
+    fn update(&mut self, world: &mut World) {
+        let step = world.resource::<AutomataPipeline>().step;
+
+        self.record = step.is_some() && step != self.last_step;
+        self.last_step = step;
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        let Some(step) = world.resource::<AutomataPipeline>().step else {
+            return Ok(());
+        };
+        let buffers = world.resource::<OutputBuffers>();
+        let value_count = buffers.lengths.iter().sum::<usize>();
+        if !self.record || value_count == 0 {
+            return Ok(());
+        }
+
+        let (Some(automata_bind_group), Some(output_bind_group), Some(values)) = (
+            world.get_resource::<AutomataBindGroup>(),
+            world.get_resource::<OutputBindGroup>(),
+            buffers.values.buffer(),
+        ) else {
+            return Ok(());
+        };
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<OutputPipeline>();
+        let Some(gather_pipeline) = pipeline_cache.get_compute_pipeline(pipeline.gather_pipeline) else {
+            return Ok(());
+        };
+
+        {
+            let mut pass = render_context
+                .command_encoder()
+                .begin_compute_pass(&ComputePassDescriptor::default());
+
+            pass.set_bind_group(0, &automata_bind_group.0, &[]);
+            pass.set_bind_group(1, &output_bind_group.0, &[]);
+            pass.set_pipeline(gather_pipeline);
+            pass.dispatch_workgroups((value_count as u32).div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+
+        let size = (value_count * std::mem::size_of::<f32>()) as u64;
+        let buffer = self.staging.take(render_context.render_device(), size);
+        render_context.command_encoder().copy_buffer_to_buffer(values, 0, &buffer, 0, size);
+
+        let _ = self.pending.lock().unwrap().send(PendingOutput {
+            step,
+            sequence: world.get_resource::<FieldInput>().and_then(|input| input.sequence),
+            lengths: buffers.lengths.clone(),
+            buffer,
+            mapped: None,
+        });
+
+        Ok(())
+    }
+}
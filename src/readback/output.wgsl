@@ -0,0 +1,45 @@
+#import rusty_automata::automata                get_state
+
+
+struct OutputRegion {
+    bounds: vec4<i32>,
+    offset: u32,
+};
+
+struct OutputUniforms {
+    regions: array<OutputRegion, 8>,
+    region_count: u32,
+    value_count: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> output_uniforms: OutputUniforms;
+
+@group(1) @binding(1)
+var<storage, read_write> output_values: array<f32>;
+
+
+// one invocation per gathered value, regions are laid out back to back
+@compute @workgroup_size(64, 1, 1)
+fn gather(
+    @builtin(global_invocation_id) invocation_id: vec3<u32>,
+) {
+    let index = invocation_id.x;
+    if (index >= output_uniforms.value_count) {
+        return;
+    }
+
+    for (var i = 0u; i < output_uniforms.region_count; i = i + 1u) {
+        let region = output_uniforms.regions[i];
+        let width = u32(region.bounds.z - region.bounds.x);
+        let len = width * u32(region.bounds.w - region.bounds.y);
+
+        if (index >= region.offset && index < region.offset + len) {
+            let local = index - region.offset;
+            let location = region.bounds.xy + vec2<i32>(i32(local % width), i32(local / width));
+
+            output_values[index] = get_state(location).value;
+            return;
+        }
+    }
+}
@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+
+use crate::automata::AutomataField;
+
+use super::{
+    FieldSnapshot,
+    Readback,
+};
+
+
+// requests waiting on snapshots of the field they were made on, e.g. checkpoint saves or npz recordings
+//   snapshots of other fields are ignored, requests of a replaced field are dropped
+pub struct SnapshotRequests<T> {
+    complete: bool,
+    pending: Vec<SnapshotRequest<T>>,
+}
+
+struct SnapshotRequest<T> {
+    field: Handle<Image>,
+    frames: usize,
+    snapshots: Vec<FieldSnapshot>,
+    request: T,
+}
+
+impl<T> SnapshotRequests<T> {
+    // each request is answered by the next complete snapshot
+    pub fn complete() -> Self {
+        Self {
+            complete: true,
+            pending: Vec::new(),
+        }
+    }
+
+    // each request collects its next `frames` snapshots, complete or not
+    pub fn frames() -> Self {
+        Self {
+            complete: false,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn push(&mut self, field: Handle<Image>, request: T, frames: usize) {
+        self.pending.push(SnapshotRequest {
+            field,
+            frames: frames.max(1),
+            snapshots: Vec::new(),
+            request,
+        });
+    }
+
+    // queues `requests` on the current field and asks `readback` for the snapshots still missing
+    //   `feature` names what is dropped when there is no field or no `ReadbackPlugin`
+    pub fn update(
+        &mut self,
+        requests: impl IntoIterator<Item = (T, usize)>,
+        automata: Option<&AutomataField>,
+        readback: Option<&mut Readback>,
+        feature: &str,
+    ) {
+        let requests: Vec<_> = requests.into_iter().collect();
+
+        let Some(automata) = automata else {
+            if !requests.is_empty() || !self.is_empty() {
+                error!("{feature} requires an AutomataField");
+            }
+            self.pending.clear();
+            return;
+        };
+
+        for (request, frames) in requests {
+            self.push(automata.nodes.clone(), request, frames);
+        }
+
+        let before = self.len();
+        self.pending.retain(|pending| pending.field == automata.nodes);
+        if self.len() < before {
+            warn!("dropped {} pending {feature} requests of a replaced field", before - self.len());
+        }
+
+        if self.is_empty() {
+            return;
+        }
+
+        let Some(readback) = readback else {
+            error!("{feature} requires the ReadbackPlugin");
+            self.pending.clear();
+            return;
+        };
+
+        // interval snapshots are enough unless a complete one is needed
+        if self.complete || readback.interval.is_none() {
+            readback.request();
+        }
+    }
+
+    // adds `snapshots` to the requests of the same field, returns the requests that have all their frames
+    pub fn receive<'a>(
+        &mut self,
+        snapshots: impl IntoIterator<Item = &'a FieldSnapshot>,
+    ) -> Vec<(T, Vec<FieldSnapshot>)> {
+        for snapshot in snapshots {
+            if self.complete && !snapshot.is_complete() {
+                continue;
+            }
+
+            for pending in &mut self.pending {
+                if pending.field == snapshot.field && pending.snapshots.len() < pending.frames {
+                    pending.snapshots.push(snapshot.clone());
+                }
+            }
+        }
+
+        let (ready, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|pending| pending.snapshots.len() >= pending.frames);
+        self.pending = pending;
+
+        ready
+            .into_iter()
+            .map(|ready: SnapshotRequest<T>| (ready.request, ready.snapshots))
+            .collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::HandleId;
+
+    use super::*;
+
+    fn field() -> Handle<Image> {
+        Handle::weak(HandleId::random::<Image>())
+    }
+
+    // a 1x1 field with one edge, `complete` fills every texture
+    fn snapshot(field: &Handle<Image>, step: u64, complete: bool) -> FieldSnapshot {
+        FieldSnapshot {
+            field: field.clone(),
+            step,
+            width: 1,
+            height: 1,
+            edge_count: 1,
+            nodes: vec![default()],
+            edges: if complete { vec![default()] } else { Vec::new() },
+            uaf: if complete { vec![Vec4::ZERO] } else { Vec::new() },
+        }
+    }
+
+    #[test]
+    fn complete_requests_skip_partial_snapshots() {
+        let field = field();
+        let mut requests = SnapshotRequests::complete();
+        requests.push(field.clone(), "a", 1);
+        requests.push(field.clone(), "b", 1);
+
+        assert!(requests.receive([&snapshot(&field, 1, false)]).is_empty());
+
+        let ready = requests.receive([&snapshot(&field, 2, true)]);
+        assert_eq!(ready.len(), 2);
+        assert_eq!(ready[0].1[0].step, 2);
+        assert!(requests.is_empty());
+    }
+
+    #[test]
+    fn frame_requests_collect_in_order() {
+        let field = field();
+        let mut requests = SnapshotRequests::frames();
+        requests.push(field.clone(), (), 3);
+
+        assert!(requests.receive([&snapshot(&field, 1, false), &snapshot(&field, 2, false)]).is_empty());
+
+        let ready = requests.receive([&snapshot(&field, 3, false), &snapshot(&field, 4, false)]);
+        let steps: Vec<u64> = ready[0].1.iter().map(|snapshot| snapshot.step).collect();
+        assert_eq!(steps, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn snapshots_of_other_fields_are_ignored() {
+        let (field, other) = (field(), field());
+        let mut requests = SnapshotRequests::complete();
+        requests.push(field.clone(), (), 1);
+
+        assert!(requests.receive([&snapshot(&other, 1, true)]).is_empty());
+        assert_eq!(requests.receive([&snapshot(&field, 2, true)]).len(), 1);
+    }
+}
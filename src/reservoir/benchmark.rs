@@ -0,0 +1,194 @@
+use rand::{
+    rngs::StdRng,
+    Rng,
+    SeedableRng,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+
+// an input sequence and the target the readout should produce after each input
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReservoirTask {
+    pub inputs: Vec<f32>,
+    pub targets: Vec<f32>,
+}
+
+impl ReservoirTask {
+    pub fn len(&self) -> usize {
+        self.inputs.len().min(self.targets.len())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // rescales the inputs to -1..1, targets are untouched since nrmse doesn't depend on their scale
+    pub fn normalized(mut self) -> Self {
+        let (min, max) = self.inputs
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), &x| (min.min(x), max.max(x)));
+
+        if max > min {
+            for input in &mut self.inputs {
+                *input = (*input - min) / (max - min) * 2.0 - 1.0;
+            }
+        }
+
+        self
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReservoirBenchmark {
+    // nonlinear autoregressive moving average of order 10 driven by uniform noise
+    #[default]
+    Narma10,
+    // one step ahead prediction of the chaotic mackey-glass series (tau = 17)
+    MackeyGlass,
+}
+
+impl ReservoirBenchmark {
+    pub const ALL: [ReservoirBenchmark; 2] = [
+        ReservoirBenchmark::Narma10,
+        ReservoirBenchmark::MackeyGlass,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReservoirBenchmark::Narma10 => "narma-10",
+            ReservoirBenchmark::MackeyGlass => "mackey-glass",
+        }
+    }
+
+    pub fn task(&self, len: usize, seed: u64) -> ReservoirTask {
+        match self {
+            ReservoirBenchmark::Narma10 => narma10(len, seed),
+            ReservoirBenchmark::MackeyGlass => mackey_glass(len, 17.0, 1, seed),
+        }
+    }
+}
+
+
+// y(t + 1) = 0.3 y(t) + 0.05 y(t) Σ y(t - i) + 1.5 u(t - 9) u(t) + 0.1, with u ~ U(0, 0.5)
+//   the target after input u(t) is y(t + 1)
+pub fn narma10(len: usize, seed: u64) -> ReservoirTask {
+    const ORDER: usize = 10;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let inputs: Vec<f32> = (0..len).map(|_| rng.gen_range(0.0..0.5)).collect();
+
+    let mut y = vec![0.0_f64; len + 1];
+    for t in 0..len {
+        let history: f64 = y[t.saturating_sub(ORDER - 1)..=t].iter().sum();
+        let delayed = match t >= ORDER - 1 {
+            true => inputs[t - (ORDER - 1)] as f64,
+            false => 0.0,
+        };
+
+        y[t + 1] = 0.3 * y[t] + 0.05 * y[t] * history + 1.5 * delayed * inputs[t] as f64 + 0.1;
+    }
+
+    ReservoirTask {
+        inputs,
+        targets: y[1..].iter().map(|&y| y as f32).collect(),
+    }
+}
+
+// dx/dt = 0.2 x(t - tau) / (1 + x(t - tau)^10) - 0.1 x(t), sampled once per time unit after a transient
+//   the target after input x(t) is x(t + horizon), the seed perturbs the initial history
+pub fn mackey_glass(len: usize, tau: f64, horizon: usize, seed: u64) -> ReservoirTask {
+    const DT: f64 = 0.1;
+    const SUBSTEPS: usize = 10;
+    const TRANSIENT: usize = 500;
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let delay = (tau / DT).round().max(1.0) as usize;
+    let mut history: Vec<f64> = (0..=delay).map(|_| 1.2 + rng.gen_range(-0.05..0.05)).collect();
+
+    let derivative = |x: f64, delayed: f64| 0.2 * delayed / (1.0 + delayed.powi(10)) - 0.1 * x;
+
+    let samples = TRANSIENT + len + horizon;
+    let mut series = Vec::with_capacity(samples);
+
+    for _ in 0..samples * SUBSTEPS {
+        let x = history[history.len() - 1];
+        let delayed = history[history.len() - 1 - delay];
+
+        // heun's method, the corrector sees the delayed value one substep later
+        let predicted = x + DT * derivative(x, delayed);
+        let next = x + DT * 0.5 * (derivative(x, delayed) + derivative(predicted, history[history.len() - delay]));
+
+        history.push(next);
+        if history.len().is_multiple_of(SUBSTEPS) {
+            series.push(next as f32);
+        }
+    }
+
+    let series = &series[series.len() - len - horizon..];
+
+    ReservoirTask {
+        inputs: series[..len].to_vec(),
+        targets: series[horizon..].to_vec(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mean(values: &[f32]) -> f32 {
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+
+    #[test]
+    fn narma10_follows_its_recurrence() {
+        let task = narma10(500, 7);
+
+        // y(1) = 0.1, y(2) = 0.3 * 0.1 + 0.05 * 0.1 * 0.1 + 0.1 before the delayed input exists
+        assert!((task.targets[0] - 0.1).abs() < 1e-6);
+        assert!((task.targets[1] - 0.1305).abs() < 1e-6);
+
+        for t in 10..task.len() {
+            let y = |i: usize| task.targets[i - 1] as f64;
+            let history: f64 = (t - 9..=t).map(y).sum();
+            let expected = 0.3 * y(t) + 0.05 * y(t) * history + 1.5 * task.inputs[t - 9] as f64 * task.inputs[t] as f64 + 0.1;
+
+            assert!((task.targets[t] as f64 - expected).abs() < 1e-5, "step {t}");
+        }
+
+        assert!(task.inputs.iter().all(|&u| (0.0..0.5).contains(&u)));
+        assert!((0.15..0.4).contains(&mean(&task.targets)), "mean {}", mean(&task.targets));
+        assert_eq!(narma10(500, 7), task);
+    }
+
+    #[test]
+    fn mackey_glass_stays_on_its_attractor() {
+        let task = mackey_glass(1000, 17.0, 1, 3);
+
+        assert_eq!(task.len(), 1000);
+        assert_eq!(task.targets[..999], task.inputs[1..]);
+
+        // tau = 17 oscillates chaotically between roughly 0.2 and 1.4 around 0.9
+        let (min, max) = task.inputs
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), &x| (min.min(x), max.max(x)));
+        assert!(min > 0.1 && min < 0.6, "min {min}");
+        assert!(max > 1.1 && max < 1.5, "max {max}");
+        assert!((0.8..1.0).contains(&mean(&task.inputs)), "mean {}", mean(&task.inputs));
+    }
+
+    #[test]
+    fn normalized_inputs_span_minus_one_to_one() {
+        let task = mackey_glass(200, 17.0, 5, 1).normalized();
+        let (min, max) = task.inputs
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), &x| (min.min(x), max.max(x)));
+
+        assert!((min + 1.0).abs() < 1e-6 && (max - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,320 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::Extent3d,
+};
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    neat::{
+        FieldInput,
+        InputMode,
+        InputRegion,
+        RegionId,
+    },
+    readback::{
+        FieldOutput,
+        OutputPlugin,
+        OutputRegion,
+        OutputSample,
+    },
+};
+
+mod benchmark;
+pub use benchmark::*;
+
+mod readout;
+pub use readout::*;
+
+
+// frames to wait for missing output samples before training on what was collected
+const COLLECT_TIMEOUT: u32 = 120;
+
+
+// treats the field as an echo state network: drives an input region with a task's inputs,
+// collects readout node values each step and fits a ridge readout, add after the `NeatPlugin`
+#[derive(Default)]
+pub struct ReservoirPlugin;
+
+impl Plugin for ReservoirPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<OutputPlugin>() {
+            app.add_plugins(OutputPlugin);
+        }
+
+        app.add_event::<ReservoirReport>();
+        app.init_resource::<Reservoir>();
+
+        app.add_systems(Update, (drive_reservoir, collect_reservoir_states).chain());
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ReservoirConfig {
+    pub input_origin: UVec2,
+    pub input_size: UVec2,
+    pub input_mode: InputMode,
+    pub input_scale: f32,
+    // every node of the readout region is a feature
+    pub readout_origin: UVec2,
+    pub readout_size: UVec2,
+    // initial steps left out of training while the field forgets its initial state
+    pub washout: usize,
+    // the rest of the steps after washout are the held-out test split
+    pub train_fraction: f32,
+    pub ridge: f32,
+}
+
+impl Default for ReservoirConfig {
+    fn default() -> Self {
+        Self {
+            input_origin: UVec2::new(64, 64),
+            input_size: UVec2::splat(8),
+            input_mode: InputMode::Add,
+            input_scale: 1.0,
+            readout_origin: UVec2::new(60, 60),
+            readout_size: UVec2::splat(16),
+            washout: 100,
+            train_fraction: 0.7,
+            ridge: 1e-4,
+        }
+    }
+}
+
+impl ReservoirConfig {
+    // input left of the field's center, readout around it
+    pub fn for_field(field_size: Extent3d) -> Self {
+        let default = Self::default();
+        let center = UVec2::new(field_size.width, field_size.height) / 2;
+
+        Self {
+            input_origin: center - default.input_size / 2,
+            readout_origin: center - default.readout_size / 2,
+            ..default
+        }
+    }
+}
+
+
+#[derive(Event, Clone, Debug, Default, PartialEq)]
+pub struct ReservoirReport {
+    pub train_nrmse: f32,
+    pub test_nrmse: f32,
+    pub train_len: usize,
+    pub test_len: usize,
+    pub readout: RidgeReadout,
+    // readout predictions and targets over the test split
+    pub predictions: Vec<f32>,
+    pub targets: Vec<f32>,
+}
+
+// fits a readout on the states after washout, states that were never collected are skipped
+pub fn train_readout(
+    states: &[Option<Vec<f32>>],
+    targets: &[f32],
+    config: &ReservoirConfig,
+) -> Option<ReservoirReport> {
+    let (states, targets): (Vec<Vec<f32>>, Vec<f32>) = states
+        .iter()
+        .zip(targets)
+        .skip(config.washout)
+        .filter_map(|(state, &target)| state.clone().map(|state| (state, target)))
+        .unzip();
+
+    let train_len = (states.len() as f32 * config.train_fraction.clamp(0.0, 1.0)) as usize;
+    let (train_states, test_states) = states.split_at(train_len);
+    let (train_targets, test_targets) = targets.split_at(train_len);
+
+    let readout = RidgeReadout::fit(train_states, train_targets, config.ridge)?;
+    let predictions = readout.predict_all(test_states);
+
+    Some(ReservoirReport {
+        train_nrmse: nrmse(&readout.predict_all(train_states), train_targets),
+        test_nrmse: nrmse(&predictions, test_targets),
+        train_len,
+        test_len: test_states.len(),
+        readout,
+        predictions,
+        targets: test_targets.to_vec(),
+    })
+}
+
+
+struct ReservoirRun {
+    task: ReservoirTask,
+    // ids of the field input and output regions, None until the run is set up
+    regions: Option<(RegionId, RegionId)>,
+    sequence_base: u64,
+    next: usize,
+    states: Vec<Option<Vec<f32>>>,
+    collected: usize,
+    waited: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct Reservoir {
+    pub config: ReservoirConfig,
+    pub report: Option<ReservoirReport>,
+    run: Option<ReservoirRun>,
+    runs: u64,
+}
+
+impl Reservoir {
+    pub fn new(config: ReservoirConfig) -> Self {
+        Self {
+            config,
+            ..default()
+        }
+    }
+
+    // the input sequence starts on the next update, a run in progress is restarted on its regions
+    pub fn start(&mut self, task: ReservoirTask) {
+        let regions = self.run.take().and_then(|run| run.regions);
+
+        self.runs += 1;
+        self.run = Some(ReservoirRun {
+            states: vec![None; task.len()],
+            task,
+            regions,
+            sequence_base: self.runs << 32,
+            next: 0,
+            collected: 0,
+            waited: 0,
+        });
+    }
+
+    pub fn start_benchmark(&mut self, benchmark: ReservoirBenchmark, len: usize, seed: u64) {
+        self.start(benchmark.task(len, seed).normalized());
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    // fraction of the states collected
+    pub fn progress(&self) -> Option<f32> {
+        self.run
+            .as_ref()
+            .map(|run| run.collected as f32 / run.task.len().max(1) as f32)
+    }
+}
+
+
+fn drive_reservoir(
+    mut reservoir: ResMut<Reservoir>,
+    mut field_input: ResMut<FieldInput>,
+    mut field_output: ResMut<FieldOutput>,
+) {
+    let reservoir = &mut *reservoir;
+    let Some(run) = reservoir.run.as_mut() else {
+        return;
+    };
+    let config = &reservoir.config;
+
+    if run.regions.is_none() {
+        let input = field_input.add_region(InputRegion::new(config.input_origin, config.input_size, config.input_mode));
+        let output = field_output.add_region(OutputRegion::new(config.readout_origin, config.readout_size));
+
+        let Some((input, output)) = input.zip(output) else {
+            error!("no free field input or output region for the reservoir");
+
+            if let Some(input) = input {
+                field_input.remove_region(input);
+            }
+            if let Some(output) = output {
+                field_output.remove_region(output);
+            }
+            reservoir.run = None;
+            return;
+        };
+        run.regions = Some((field_input.regions[input].id, field_output.regions[output].id));
+    }
+    let Some((input_id, _)) = run.regions else {
+        return;
+    };
+
+    let Some(region) = field_input.index_of(input_id).and_then(|index| field_input.region_mut(index)) else {
+        return;
+    };
+
+    match run.task.inputs.get(run.next) {
+        Some(&input) => {
+            region.fill(input * config.input_scale);
+            field_input.sequence = Some(run.sequence_base + run.next as u64);
+            run.next += 1;
+        }
+        None => {
+            region.fill(0.0);
+            field_input.sequence = None;
+        }
+    }
+}
+
+fn collect_reservoir_states(
+    mut reservoir: ResMut<Reservoir>,
+    mut field_input: ResMut<FieldInput>,
+    mut field_output: ResMut<FieldOutput>,
+    mut samples: EventReader<OutputSample>,
+    mut reports: EventWriter<ReservoirReport>,
+) {
+    let reservoir = &mut *reservoir;
+    let Some(run) = reservoir.run.as_mut() else {
+        samples.clear();
+        return;
+    };
+    let Some((input_id, output_id)) = run.regions else {
+        samples.clear();
+        return;
+    };
+    let output_index = field_output.index_of(output_id);
+
+    for sample in samples.iter() {
+        let Some(index) = sample.sequence
+            .and_then(|sequence| sequence.checked_sub(run.sequence_base))
+            .filter(|&index| index < run.states.len() as u64) else {
+            continue;
+        };
+        let Some(values) = output_index.and_then(|index| sample.region(index)) else {
+            continue;
+        };
+
+        let state = &mut run.states[index as usize];
+        if state.is_none() {
+            run.collected += 1;
+        }
+        *state = Some(values.to_vec());
+    }
+
+    if run.next < run.task.len() {
+        return;
+    }
+
+    run.waited += 1;
+    if run.collected < run.states.len() && run.waited < COLLECT_TIMEOUT {
+        return;
+    }
+
+    if run.collected < run.states.len() {
+        warn!("reservoir collected {} of {} states", run.collected, run.states.len());
+    }
+
+    let Some(run) = reservoir.run.take() else {
+        return;
+    };
+    field_input.remove_id(input_id);
+    field_output.remove_id(output_id);
+    field_input.sequence = None;
+
+    match train_readout(&run.states, &run.task.targets, &reservoir.config) {
+        Some(report) => {
+            info!("reservoir nrmse train: {:.4} test: {:.4}", report.train_nrmse, report.test_nrmse);
+
+            reports.send(report.clone());
+            reservoir.report = Some(report);
+        }
+        None => error!("failed to fit the reservoir readout"),
+    }
+}
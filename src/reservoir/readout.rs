@@ -0,0 +1,173 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+
+// linear readout y = w · x + bias, fit by ridge regression
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RidgeReadout {
+    pub weights: Vec<f32>,
+    pub bias: f32,
+}
+
+impl RidgeReadout {
+    // solves (XᵀX + λI) w = Xᵀy with an unregularized bias column, None when states is empty or singular
+    pub fn fit(states: &[Vec<f32>], targets: &[f32], ridge: f32) -> Option<Self> {
+        let features = states.first()?.len();
+        let n = features + 1;
+
+        let mut normal = vec![0.0_f64; n * n];
+        let mut rhs = vec![0.0_f64; n];
+
+        for (state, &target) in states.iter().zip(targets) {
+            let x = |i: usize| if i < features { state[i] as f64 } else { 1.0 };
+
+            for i in 0..n {
+                let xi = x(i);
+                rhs[i] += xi * target as f64;
+
+                for j in i..n {
+                    normal[i * n + j] += xi * x(j);
+                }
+            }
+        }
+
+        for i in 0..n {
+            for j in 0..i {
+                normal[i * n + j] = normal[j * n + i];
+            }
+        }
+        for i in 0..features {
+            normal[i * n + i] += ridge as f64;
+        }
+
+        let solution = cholesky_solve(&mut normal, &rhs, n)?;
+
+        Some(Self {
+            weights: solution[..features].iter().map(|&w| w as f32).collect(),
+            bias: solution[features] as f32,
+        })
+    }
+
+    pub fn predict(&self, state: &[f32]) -> f32 {
+        self.weights
+            .iter()
+            .zip(state)
+            .map(|(w, x)| w * x)
+            .sum::<f32>()
+            + self.bias
+    }
+
+    pub fn predict_all(&self, states: &[Vec<f32>]) -> Vec<f32> {
+        states.iter().map(|state| self.predict(state)).collect()
+    }
+}
+
+
+// root mean squared error over the standard deviation of the targets, 1 matches predicting the mean
+//   NaN for empty or constant targets, which have no spread to normalize by
+pub fn nrmse(predictions: &[f32], targets: &[f32]) -> f32 {
+    let count = predictions.len().min(targets.len());
+    if count == 0 {
+        return f32::NAN;
+    }
+
+    let targets = &targets[..count];
+    let mean = targets.iter().map(|&t| t as f64).sum::<f64>() / count as f64;
+    let variance = targets.iter().map(|&t| (t as f64 - mean).powi(2)).sum::<f64>() / count as f64;
+    if variance == 0.0 {
+        return f32::NAN;
+    }
+
+    let mse = predictions
+        .iter()
+        .zip(targets)
+        .map(|(&p, &t)| (p as f64 - t as f64).powi(2))
+        .sum::<f64>() / count as f64;
+
+    (mse / variance).sqrt() as f32
+}
+
+
+// in place cholesky factorization of a symmetric positive definite `n` × `n` matrix
+fn cholesky_solve(a: &mut [f64], b: &[f64], n: usize) -> Option<Vec<f64>> {
+    for j in 0..n {
+        let diagonal = a[j * n + j] - (0..j).map(|k| a[j * n + k].powi(2)).sum::<f64>();
+        if diagonal <= 0.0 || !diagonal.is_finite() {
+            return None;
+        }
+        let diagonal = diagonal.sqrt();
+        a[j * n + j] = diagonal;
+
+        for i in j + 1..n {
+            let dot = (0..j).map(|k| a[i * n + k] * a[j * n + k]).sum::<f64>();
+            a[i * n + j] = (a[i * n + j] - dot) / diagonal;
+        }
+    }
+
+    // L z = b, then Lᵀ x = z
+    let mut z = vec![0.0; n];
+    for i in 0..n {
+        let dot = (0..i).map(|k| a[i * n + k] * z[k]).sum::<f64>();
+        z[i] = (b[i] - dot) / a[i * n + i];
+    }
+
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        let dot = (i + 1..n).map(|k| a[k * n + i] * x[k]).sum::<f64>();
+        x[i] = (z[i] - dot) / a[i * n + i];
+    }
+
+    Some(x)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ridge_recovers_a_linear_target() {
+        let states: Vec<Vec<f32>> = (0..50)
+            .map(|i| {
+                let t = i as f32 * 0.1;
+                vec![t.sin(), t.cos(), (0.3 * t).sin()]
+            })
+            .collect();
+        let targets: Vec<f32> = states
+            .iter()
+            .map(|x| 2.0 * x[0] - 0.5 * x[1] + 0.25 * x[2] + 1.0)
+            .collect();
+
+        let readout = RidgeReadout::fit(&states, &targets, 1e-6).unwrap();
+
+        for (weight, expected) in readout.weights.iter().zip([2.0, -0.5, 0.25]) {
+            assert!((weight - expected).abs() < 1e-3, "{:?}", readout.weights);
+        }
+        assert!((readout.bias - 1.0).abs() < 1e-3);
+        assert!(nrmse(&readout.predict_all(&states), &targets) < 1e-3);
+    }
+
+    #[test]
+    fn ridge_shrinks_weights() {
+        let states: Vec<Vec<f32>> = (0..20).map(|i| vec![i as f32 / 20.0]).collect();
+        let targets: Vec<f32> = states.iter().map(|x| 3.0 * x[0]).collect();
+
+        let loose = RidgeReadout::fit(&states, &targets, 0.0).unwrap();
+        let tight = RidgeReadout::fit(&states, &targets, 10.0).unwrap();
+
+        assert!(tight.weights[0].abs() < loose.weights[0].abs());
+        assert!(RidgeReadout::fit(&[], &[], 1.0).is_none());
+    }
+
+    #[test]
+    fn nrmse_is_relative_to_the_mean_predictor() {
+        let targets = [1.0, 2.0, 3.0, 4.0];
+
+        assert_eq!(nrmse(&targets, &targets), 0.0);
+        assert!((nrmse(&[2.5; 4], &targets) - 1.0).abs() < 1e-6);
+        assert!(nrmse(&[1.0; 3], &[1.0; 3]).is_nan());
+        assert!(nrmse(&[], &[]).is_nan());
+    }
+}
@@ -0,0 +1,649 @@
+use std::{
+    fs::File,
+    io::{
+        self,
+        BufWriter,
+        Write,
+    },
+    path::Path,
+};
+
+use bevy::prelude::*;
+use rand::{
+    seq::SliceRandom,
+    Rng,
+    SeedableRng,
+};
+use rand_chacha::ChaCha12Rng;
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use crate::{
+    automata::AutomataParameters,
+    batch::{
+        BatchFinished,
+        BatchPlugin,
+        BatchQueue,
+        FieldRun,
+        FieldRunFinished,
+    },
+    evolution::{
+        Behaviour,
+        Descriptor,
+    },
+    heatmap::{
+        Colormap,
+        Heatmap,
+        HeatmapError,
+    },
+};
+
+
+// axes with more distinct values are binned for heatmaps, e.g. latin hypercube samples
+const MAX_HEATMAP_CELLS: usize = 32;
+const HEATMAP_BINS: usize = 16;
+const HEATMAP_CELL_PIXELS: u32 = 16;
+
+
+// runs every point of a `Sweep` through the `BatchQueue`, add after the `AutomataPlugin` and `NeatPlugin`
+#[derive(Default)]
+pub struct SweepPlugin;
+
+impl Plugin for SweepPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<BatchPlugin>() {
+            app.add_plugins(BatchPlugin);
+        }
+
+        app.add_event::<SweepFinished>();
+
+        app.add_systems(Update, record_sweep);
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SweepParameter {
+    EdgeCount,
+    MaxRadius,
+    MaxEdgeWeight,
+}
+
+impl SweepParameter {
+    pub const ALL: [SweepParameter; 3] = [
+        SweepParameter::EdgeCount,
+        SweepParameter::MaxRadius,
+        SweepParameter::MaxEdgeWeight,
+    ];
+
+    // csv column
+    pub fn name(&self) -> &'static str {
+        match self {
+            SweepParameter::EdgeCount => "edge_count",
+            SweepParameter::MaxRadius => "max_radius",
+            SweepParameter::MaxEdgeWeight => "max_edge_weight",
+        }
+    }
+
+    pub fn get(&self, parameters: &AutomataParameters) -> f32 {
+        match self {
+            SweepParameter::EdgeCount => parameters.edge_count as f32,
+            SweepParameter::MaxRadius => parameters.max_radius,
+            SweepParameter::MaxEdgeWeight => parameters.max_edge_weight,
+        }
+    }
+
+    // edge counts are rounded, at least one edge
+    pub fn set(&self, parameters: &mut AutomataParameters, value: f32) {
+        match self {
+            SweepParameter::EdgeCount => parameters.edge_count = value.round().max(1.0) as u32,
+            SweepParameter::MaxRadius => parameters.max_radius = value,
+            SweepParameter::MaxEdgeWeight => parameters.max_edge_weight = value,
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SweepAxis {
+    pub parameter: SweepParameter,
+    // the grid values, a latin hypercube samples between their min and max
+    pub values: Vec<f32>,
+}
+
+impl SweepAxis {
+    pub fn new(parameter: SweepParameter, values: Vec<f32>) -> Self {
+        Self {
+            parameter,
+            values,
+        }
+    }
+
+    // `count` evenly spaced values from min to max
+    pub fn range(parameter: SweepParameter, min: f32, max: f32, count: usize) -> Self {
+        let values = match count {
+            0 => Vec::new(),
+            1 => vec![min],
+            _ => (0..count).map(|i| min + (max - min) * i as f32 / (count - 1) as f32).collect(),
+        };
+
+        Self::new(parameter, values)
+    }
+
+    pub fn bounds(&self) -> (f32, f32) {
+        self.values
+            .iter()
+            .fold((f32::MAX, f32::MIN), |(min, max), &value| (min.min(value), max.max(value)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SweepSampling {
+    // every combination of the axis values
+    #[default]
+    Grid,
+    // `samples` points, each axis range split into `samples` strata with one point in each
+    LatinHypercube {
+        samples: usize,
+    },
+}
+
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SweepConfig {
+    // size, uaf and steps of every run, the swept parameters replace its own
+    pub base: FieldRun,
+    pub axes: Vec<SweepAxis>,
+    pub sampling: SweepSampling,
+    // runs per point with the shader seeds 1..=seeds
+    pub seeds: u32,
+    // activity and spectrum samples, the avalanche columns always see every step
+    pub sample_interval: u64,
+    // latin hypercube sampling
+    pub rng_seed: u64,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        Self {
+            base: FieldRun::default(),
+            axes: vec![
+                SweepAxis::range(SweepParameter::MaxRadius, 5.0, 25.0, 5),
+                SweepAxis::range(SweepParameter::MaxEdgeWeight, 4.0, 32.0, 5),
+            ],
+            sampling: SweepSampling::default(),
+            seeds: 1,
+            sample_interval: 10,
+            rng_seed: 0,
+        }
+    }
+}
+
+impl SweepConfig {
+    // parameters of every point, without seeds
+    pub fn points(&self) -> Vec<AutomataParameters> {
+        let base = self.base.parameters;
+
+        match self.sampling {
+            SweepSampling::Grid => {
+                let mut points = vec![base];
+                for axis in &self.axes {
+                    points = points
+                        .iter()
+                        .flat_map(|point| axis.values.iter().map(move |&value| {
+                            let mut point = *point;
+                            axis.parameter.set(&mut point, value);
+                            point
+                        }))
+                        .collect();
+                }
+                points
+            }
+            SweepSampling::LatinHypercube { samples } => {
+                let mut rng = ChaCha12Rng::seed_from_u64(self.rng_seed);
+                let mut points = vec![base; samples];
+
+                for axis in &self.axes {
+                    let (min, max) = axis.bounds();
+
+                    let mut strata: Vec<usize> = (0..samples).collect();
+                    strata.shuffle(&mut rng);
+
+                    for (point, stratum) in points.iter_mut().zip(strata) {
+                        let t = (stratum as f32 + rng.gen::<f32>()) / samples as f32;
+                        axis.parameter.set(point, min + (max - min) * t);
+                    }
+                }
+                points
+            }
+        }
+    }
+
+    pub fn runs(&self) -> Vec<FieldRun> {
+        let seeds = self.seeds.max(1);
+
+        self.points()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(point, parameters)| (1..=seeds).map(move |seed| (point, parameters, seed)))
+            .map(|(point, parameters, seed)| FieldRun {
+                label: format!("p{point}_s{seed}"),
+                parameters: AutomataParameters {
+                    seed: seed as f32,
+                    ..parameters
+                },
+                ..self.base.clone()
+            })
+            .collect()
+    }
+
+    pub fn queue(&self) -> BatchQueue {
+        BatchQueue::new(self.runs()).with_sample_interval(self.sample_interval)
+    }
+}
+
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepRun {
+    pub point: usize,
+    pub parameters: AutomataParameters,
+    pub behaviour: Behaviour,
+}
+
+// mean and standard deviation over the seeds of a point, None when no run had the descriptor
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SweepPoint {
+    pub point: usize,
+    pub parameters: AutomataParameters,
+    pub runs: usize,
+    pub metrics: Vec<(Descriptor, Option<(f32, f32)>)>,
+}
+
+impl SweepPoint {
+    pub fn mean(&self, descriptor: Descriptor) -> Option<f32> {
+        self.metrics
+            .iter()
+            .find(|(metric, _)| *metric == descriptor)
+            .and_then(|(_, stats)| stats.map(|(mean, _)| mean))
+    }
+}
+
+
+// the sweep being run, insert with `Sweep::new` and its `queue`
+#[derive(Resource, Clone, Debug)]
+pub struct Sweep {
+    pub config: SweepConfig,
+    pub runs: Vec<SweepRun>,
+    seeds: usize,
+}
+
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SweepFinished {
+    pub runs: usize,
+}
+
+impl Sweep {
+    pub fn new(config: SweepConfig) -> Self {
+        Self {
+            seeds: config.seeds.max(1) as usize,
+            config,
+            runs: Vec::new(),
+        }
+    }
+
+    // aggregated over seeds, in point order
+    pub fn points(&self) -> Vec<SweepPoint> {
+        let mut points: Vec<SweepPoint> = Vec::new();
+
+        for run in &self.runs {
+            if !points.iter().any(|point| point.point == run.point) {
+                let behaviours: Vec<&Behaviour> = self.runs
+                    .iter()
+                    .filter(|other| other.point == run.point)
+                    .map(|other| &other.behaviour)
+                    .collect();
+
+                let metrics = Descriptor::ALL
+                    .iter()
+                    .map(|&descriptor| {
+                        let values: Vec<f32> = behaviours
+                            .iter()
+                            .filter_map(|behaviour| behaviour.descriptor(descriptor))
+                            .filter(|value| value.is_finite())
+                            .collect();
+                        (descriptor, mean_std(&values))
+                    })
+                    .collect();
+
+                points.push(SweepPoint {
+                    point: run.point,
+                    parameters: AutomataParameters {
+                        seed: 0.0,
+                        ..run.parameters
+                    },
+                    runs: behaviours.len(),
+                    metrics,
+                });
+            }
+        }
+
+        points.sort_by_key(|point| point.point);
+        points
+    }
+
+    // one row per run
+    pub fn write_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "point,seed,edge_count,max_radius,max_edge_weight")?;
+        for descriptor in Descriptor::ALL {
+            write!(writer, ",{}", column(descriptor))?;
+        }
+        writeln!(writer)?;
+
+        for run in &self.runs {
+            let parameters = run.parameters;
+            write!(
+                writer,
+                "{},{},{},{},{}",
+                run.point,
+                parameters.seed,
+                parameters.edge_count,
+                parameters.max_radius,
+                parameters.max_edge_weight,
+            )?;
+            for descriptor in Descriptor::ALL {
+                write!(writer, ",{}", optional(run.behaviour.descriptor(descriptor)))?;
+            }
+            writeln!(writer)?;
+        }
+
+        writer.flush()
+    }
+
+    // one row per point with the mean and standard deviation of each descriptor
+    pub fn write_summary_csv(&self, mut writer: impl Write) -> io::Result<()> {
+        write!(writer, "point,runs,edge_count,max_radius,max_edge_weight")?;
+        for descriptor in Descriptor::ALL {
+            write!(writer, ",{0}_mean,{0}_std", column(descriptor))?;
+        }
+        writeln!(writer)?;
+
+        for point in self.points() {
+            let parameters = point.parameters;
+            write!(
+                writer,
+                "{},{},{},{},{}",
+                point.point,
+                point.runs,
+                parameters.edge_count,
+                parameters.max_radius,
+                parameters.max_edge_weight,
+            )?;
+            for (_, stats) in &point.metrics {
+                write!(writer, ",{},{}", optional(stats.map(|(mean, _)| mean)), optional(stats.map(|(_, std)| std)))?;
+            }
+            writeln!(writer)?;
+        }
+
+        writer.flush()
+    }
+
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_csv(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn save_summary_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_summary_csv(BufWriter::new(File::create(path)?))
+    }
+
+    // mean descriptor over y by x, averaged over seeds and the other axes, y grows upwards
+    //   cells without runs are gray, None until a run has finished
+    pub fn heatmap(&self, x: SweepParameter, y: SweepParameter, descriptor: Descriptor) -> Option<Heatmap> {
+        let points = self.points();
+        if points.is_empty() {
+            return None;
+        }
+
+        let x_cells = HeatmapAxis::new(x, &points);
+        let y_cells = HeatmapAxis::new(y, &points);
+
+        let mut sums = vec![(0.0, 0); x_cells.len() * y_cells.len()];
+        for point in &points {
+            let Some(value) = point.mean(descriptor) else {
+                continue;
+            };
+
+            let column = x_cells.cell(x.get(&point.parameters));
+            let row = y_cells.len() - 1 - y_cells.cell(y.get(&point.parameters));
+            let (sum, count) = &mut sums[row * x_cells.len() + column];
+            *sum += value;
+            *count += 1;
+        }
+
+        let values = sums
+            .into_iter()
+            .map(|(sum, count)| if count > 0 { sum / count as f32 } else { f32::NAN })
+            .collect();
+
+        Some(
+            Heatmap::new(x_cells.len() as u32, y_cells.len() as u32, values)
+                .with_colormap(Colormap::Sequential)
+                .with_scale(HEATMAP_CELL_PIXELS)
+        )
+    }
+
+    pub fn save_heatmap(
+        &self,
+        path: impl AsRef<Path>,
+        x: SweepParameter,
+        y: SweepParameter,
+        descriptor: Descriptor,
+    ) -> Result<(), HeatmapError> {
+        match self.heatmap(x, y, descriptor) {
+            Some(heatmap) => heatmap.save_png(path),
+            None => Err(HeatmapError::Shape),
+        }
+    }
+
+    // the two axes with the most distinct values, the second is None for a single varying axis
+    pub fn heatmap_axes(&self) -> Option<(SweepParameter, Option<SweepParameter>)> {
+        let mut axes: Vec<&SweepAxis> = self.config.axes
+            .iter()
+            .filter(|axis| axis.bounds().0 < axis.bounds().1)
+            .collect();
+        axes.sort_by_key(|axis| std::cmp::Reverse(axis.values.len()));
+
+        let x = axes.first()?.parameter;
+        Some((x, axes.get(1).map(|axis| axis.parameter)))
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.runs.len() >= self.config.points().len() * self.seeds
+    }
+}
+
+// distinct values of a parameter, or equal bins over their range when there are too many
+struct HeatmapAxis {
+    values: Vec<f32>,
+    binned: bool,
+}
+
+impl HeatmapAxis {
+    fn new(parameter: SweepParameter, points: &[SweepPoint]) -> Self {
+        let mut values: Vec<f32> = points.iter().map(|point| parameter.get(&point.parameters)).collect();
+        values.sort_by(f32::total_cmp);
+        values.dedup();
+
+        match values.len() > MAX_HEATMAP_CELLS {
+            true => {
+                let (min, max) = (values[0], values[values.len() - 1]);
+                Self {
+                    values: (0..HEATMAP_BINS).map(|i| min + (max - min) * i as f32 / HEATMAP_BINS as f32).collect(),
+                    binned: true,
+                }
+            }
+            false => Self {
+                values,
+                binned: false,
+            },
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn cell(&self, value: f32) -> usize {
+        match self.binned {
+            // lower bin edges
+            true => self.values.iter().rposition(|&edge| edge <= value).unwrap_or(0),
+            false => self.values
+                .iter()
+                .position(|&cell| cell == value)
+                .unwrap_or(0),
+        }
+    }
+}
+
+fn mean_std(values: &[f32]) -> Option<(f32, f32)> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let variance = values.iter().map(|value| (value - mean).powi(2)).sum::<f32>() / values.len() as f32;
+    Some((mean, variance.sqrt()))
+}
+
+fn column(descriptor: Descriptor) -> String {
+    descriptor.name().replace(' ', "_")
+}
+
+fn optional(value: Option<f32>) -> String {
+    value.map_or(String::new(), |value| value.to_string())
+}
+
+
+fn record_sweep(
+    sweep: Option<ResMut<Sweep>>,
+    mut finished_runs: EventReader<FieldRunFinished>,
+    mut batch_finished: EventReader<BatchFinished>,
+    mut sweep_finished: EventWriter<SweepFinished>,
+) {
+    let Some(mut sweep) = sweep else {
+        finished_runs.clear();
+        batch_finished.clear();
+        return;
+    };
+
+    let seeds = sweep.seeds;
+    for run in finished_runs.iter() {
+        // runs are queued point by point
+        sweep.runs.push(SweepRun {
+            point: run.index / seeds,
+            parameters: run.run.parameters,
+            behaviour: run.behaviour.clone(),
+        });
+    }
+
+    if batch_finished.iter().last().is_some() {
+        sweep_finished.send(SweepFinished {
+            runs: sweep.runs.len(),
+        });
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(axes: Vec<SweepAxis>, sampling: SweepSampling) -> SweepConfig {
+        SweepConfig {
+            axes,
+            sampling,
+            ..SweepConfig::default()
+        }
+    }
+
+    #[test]
+    fn grid_has_every_combination() {
+        let grid = config(
+            vec![
+                SweepAxis::new(SweepParameter::EdgeCount, vec![4.0, 8.0, 16.0]),
+                SweepAxis::new(SweepParameter::MaxRadius, vec![5.0, 10.0]),
+            ],
+            SweepSampling::Grid,
+        );
+
+        let points = grid.points();
+        assert_eq!(points.len(), 6);
+
+        let mut combinations: Vec<(u32, u32)> = points
+            .iter()
+            .map(|point| (point.edge_count, point.max_radius as u32))
+            .collect();
+        combinations.sort();
+        combinations.dedup();
+        assert_eq!(combinations.len(), 6);
+
+        let runs = SweepConfig { seeds: 2, ..grid }.runs();
+        assert_eq!(runs.len(), 12);
+        assert_eq!(runs[1].label, "p0_s2");
+        assert_eq!(runs[1].parameters.seed, 2.0);
+    }
+
+    #[test]
+    fn grid_with_an_empty_axis_has_no_points() {
+        let grid = config(
+            vec![
+                SweepAxis::new(SweepParameter::EdgeCount, vec![4.0, 8.0]),
+                SweepAxis::new(SweepParameter::MaxRadius, Vec::new()),
+            ],
+            SweepSampling::Grid,
+        );
+
+        assert!(grid.points().is_empty());
+        assert!(grid.runs().is_empty());
+    }
+
+    #[test]
+    fn latin_hypercube_fills_each_stratum_once() {
+        let samples = 10;
+        let axes = vec![
+            SweepAxis::range(SweepParameter::MaxRadius, 5.0, 25.0, 3),
+            SweepAxis::range(SweepParameter::MaxEdgeWeight, 4.0, 32.0, 3),
+        ];
+        let lhs = config(axes.clone(), SweepSampling::LatinHypercube { samples });
+
+        let points = lhs.points();
+        assert_eq!(points.len(), samples);
+
+        for axis in &axes {
+            let (min, max) = axis.bounds();
+
+            let mut strata: Vec<usize> = points
+                .iter()
+                .map(|point| {
+                    let t = (axis.parameter.get(point) - min) / (max - min);
+                    assert!((0.0..=1.0).contains(&t));
+                    ((t * samples as f32) as usize).min(samples - 1)
+                })
+                .collect();
+            strata.sort();
+            assert_eq!(strata, (0..samples).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn latin_hypercube_follows_the_rng_seed() {
+        let axes = vec![SweepAxis::range(SweepParameter::MaxRadius, 5.0, 25.0, 2)];
+        let lhs = config(axes, SweepSampling::LatinHypercube { samples: 6 });
+
+        assert_eq!(lhs.points(), lhs.points());
+        assert_ne!(lhs.points(), SweepConfig { rng_seed: 1, ..lhs.clone() }.points());
+
+        assert!(SweepConfig { sampling: SweepSampling::LatinHypercube { samples: 0 }, ..lhs }.points().is_empty());
+    }
+}
@@ -0,0 +1,543 @@
+use bevy::{
+    asset::{
+        load_internal_asset,
+        HandleUntyped,
+    },
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        extract_resource::{
+            ExtractResource,
+            ExtractResourcePlugin,
+        },
+        render_asset::RenderAssets,
+        renderer::{
+            RenderContext,
+            RenderDevice,
+            RenderQueue,
+        },
+        render_graph::{
+            self,
+            RenderGraph,
+        },
+        render_resource::{
+            AsBindGroup,
+            AsBindGroupShaderType,
+            BindGroup,
+            BindGroupDescriptor,
+            BindGroupEntry,
+            BindGroupLayout,
+            BindGroupLayoutDescriptor,
+            BindGroupLayoutEntry,
+            BindingResource,
+            BindingType,
+            BufferBindingType,
+            CachedComputePipelineId,
+            ComputePassDescriptor,
+            ComputePipelineDescriptor,
+            Extent3d,
+            PipelineCache,
+            ShaderRef,
+            ShaderStages,
+            ShaderType,
+            StorageTextureAccess,
+            TextureFormat,
+            TextureViewDimension,
+            UniformBuffer,
+        },
+        texture::Volume,
+        Render,
+        RenderApp,
+        RenderSet,
+    },
+    sprite::{
+        Material2d,
+        Material2dPlugin,
+    },
+};
+
+use crate::{
+    automata::{
+        storage_image,
+        AutomataBindGroup,
+        AutomataField,
+        AutomataPipeline,
+        TEXEL_SIZE,
+    },
+    plot::{
+        PlotPlugin,
+        PLOT_PALETTE,
+    },
+};
+
+use std::borrow::Cow;
+
+
+const TRACE_SHADER_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 44710293817501);
+const TRACE_MATERIAL_SHADER_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 44710293817502);
+
+// one workgroup records every probe, see `record` in trace.wgsl
+pub const MAX_TRACE_PROBES: usize = 32;
+pub const MAX_TRACE_WINDOW: u32 = 4096;
+
+
+// records pinned node locations of the field every step, add after the `NeatPlugin`
+#[derive(Default)]
+pub struct TracePlugin;
+
+impl Plugin for TracePlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(
+            app,
+            TRACE_SHADER_HANDLE,
+            "trace.wgsl",
+            Shader::from_wgsl
+        );
+        load_internal_asset!(
+            app,
+            TRACE_MATERIAL_SHADER_HANDLE,
+            "trace_material.wgsl",
+            Shader::from_wgsl
+        );
+
+        // trace materials draw with the plot shader functions
+        if !app.is_plugin_added::<PlotPlugin>() {
+            app.add_plugins(PlotPlugin);
+        }
+
+        app.register_type::<Traces>();
+        app.init_resource::<Traces>();
+
+        app.add_plugins((
+            ExtractResourcePlugin::<Traces>::default(),
+            Material2dPlugin::<TraceMaterial>::default(),
+        ));
+        app.register_asset_reflect::<TraceMaterial>();
+
+        app.add_systems(Update, (reset_traces, update_trace_materials).chain());
+
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.add_systems(
+            Render,
+            (
+                prepare_trace_uniforms.in_set(RenderSet::Prepare),
+                queue_trace_bind_group.in_set(RenderSet::Queue),
+            )
+        );
+
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        render_graph.add_node("trace", TraceNode::default());
+        render_graph.add_node_edge("neat", "trace");
+        render_graph.add_node_edge(
+            "trace",
+            bevy::render::main_graph::node::CAMERA_DRIVER,
+        );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let render_app = app.sub_app_mut(RenderApp);
+        render_app.init_resource::<TracePipeline>();
+        render_app.init_resource::<TraceUniformBuffer>();
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum TraceChannel {
+    #[default]
+    Value,
+    // change since the previous step
+    Derivative,
+    // sum of the values in the window
+    Integral,
+}
+
+impl TraceChannel {
+    pub const ALL: [TraceChannel; 3] = [
+        TraceChannel::Value,
+        TraceChannel::Derivative,
+        TraceChannel::Integral,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TraceChannel::Value => "value",
+            TraceChannel::Derivative => "derivative",
+            TraceChannel::Integral => "integral",
+        }
+    }
+}
+
+
+// the last `window` steps of each probe live on the gpu, row `i` of `texture` belongs to `probes[i]`
+//   texels are (value, derivative, integral, 1), the texel past the last row holds the header (head, recorded)
+//   pinning, unpinning, resizing the window or a new field starts a new recording
+#[derive(Resource, Clone, Debug, ExtractResource, Reflect)]
+#[reflect(Resource)]
+pub struct Traces {
+    pub probes: Vec<UVec2>,
+    pub window: u32,
+    // display settings of trace materials
+    pub channel: TraceChannel,
+    pub gain: f32,
+    #[reflect(ignore)]
+    pub texture: Handle<Image>,
+    #[reflect(ignore)]
+    cleared: bool,
+}
+
+impl Default for Traces {
+    fn default() -> Self {
+        Self {
+            probes: Vec::new(),
+            window: 512,
+            channel: TraceChannel::Value,
+            gain: 1.0,
+            texture: Handle::default(),
+            cleared: false,
+        }
+    }
+}
+
+impl Traces {
+    // ignored once `MAX_TRACE_PROBES` are pinned
+    pub fn pin(&mut self, location: UVec2) {
+        if self.probes.len() < MAX_TRACE_PROBES && !self.probes.contains(&location) {
+            self.probes.push(location);
+        }
+    }
+
+    pub fn unpin(&mut self, location: UVec2) {
+        self.probes.retain(|&probe| probe != location);
+    }
+
+    pub fn with_probes(mut self, probes: impl IntoIterator<Item = UVec2>) -> Self {
+        for probe in probes {
+            self.pin(probe);
+        }
+        self
+    }
+
+    pub fn with_window(mut self, window: u32) -> Self {
+        self.window = window;
+        self
+    }
+
+    // drops the recording, keeping the probes
+    pub fn clear(&mut self) {
+        self.cleared = true;
+    }
+
+    pub fn probe_count(&self) -> u32 {
+        self.probes.len().min(MAX_TRACE_PROBES) as u32
+    }
+
+    pub fn window(&self) -> u32 {
+        self.window.clamp(2, MAX_TRACE_WINDOW)
+    }
+
+    fn texture_size(&self) -> Extent3d {
+        Extent3d {
+            width: self.window(),
+            height: MAX_TRACE_PROBES as u32 + 1,
+            depth_or_array_layers: 1,
+        }
+    }
+}
+
+
+#[derive(PartialEq)]
+struct TraceRecording {
+    probes: Vec<UVec2>,
+    window: u32,
+    field: Option<Handle<Image>>,
+}
+
+// a zeroed texture starts over at head 0 with nothing recorded
+fn reset_traces(
+    mut traces: ResMut<Traces>,
+    mut images: ResMut<Assets<Image>>,
+    automata: Option<Res<AutomataField>>,
+    mut recording: Local<Option<TraceRecording>>,
+) {
+    let current = TraceRecording {
+        probes: traces.probes.clone(),
+        window: traces.window(),
+        field: automata.map(|automata| automata.nodes.clone()),
+    };
+
+    if !traces.cleared && recording.as_ref() == Some(&current) {
+        return;
+    }
+
+    let size = traces.texture_size();
+    traces.texture = images.add(storage_image(size, vec![0; size.volume() * TEXEL_SIZE]));
+    traces.cleared = false;
+    *recording = Some(current);
+}
+
+fn update_trace_materials(
+    traces: Res<Traces>,
+    mut trace_materials: ResMut<Assets<TraceMaterial>>,
+) {
+    if !traces.is_changed() {
+        return;
+    }
+
+    for (_, material) in trace_materials.iter_mut() {
+        material.traces = traces.texture.clone();
+        material.probe_count = traces.probe_count();
+        material.window = traces.window();
+        material.channel = traces.channel;
+        material.gain = traces.gain;
+    }
+}
+
+
+// stacked scrolling traces, one row per probe with the newest step on the right
+//   each row spans -1 to 1 after `gain` is applied
+#[derive(AsBindGroup, Clone, Debug, Reflect, TypeUuid)]
+#[uuid = "b6f1c0d4-7a2e-4f83-9c5b-2e8d4a61f097"]
+#[uniform(0, TraceMaterialUniform)]
+pub struct TraceMaterial {
+    #[texture(1, sample_type = "float", filterable = false)]
+    pub traces: Handle<Image>,
+    pub probe_count: u32,
+    pub window: u32,
+    pub channel: TraceChannel,
+    pub gain: f32,
+    pub background: Color,
+    pub separator_color: Color,
+}
+
+impl TraceMaterial {
+    pub fn new(traces: &Traces) -> Self {
+        Self {
+            traces: traces.texture.clone(),
+            probe_count: traces.probe_count(),
+            window: traces.window(),
+            channel: traces.channel,
+            gain: traces.gain,
+            background: Color::rgb(0.1, 0.1, 0.1),
+            separator_color: Color::rgba(0.6, 0.6, 0.6, 0.3),
+        }
+    }
+}
+
+impl Material2d for TraceMaterial {
+    fn fragment_shader() -> ShaderRef {
+        TRACE_MATERIAL_SHADER_HANDLE.typed().into()
+    }
+}
+
+#[derive(Clone, ShaderType)]
+pub struct TraceMaterialUniform {
+    background: Vec4,
+    separator_color: Vec4,
+    // rows cycle through the plot palette
+    colors: [Vec4; 8],
+    probe_count: u32,
+    window: u32,
+    channel: u32,
+    gain: f32,
+}
+
+impl AsBindGroupShaderType<TraceMaterialUniform> for TraceMaterial {
+    fn as_bind_group_shader_type(&self, _images: &RenderAssets<Image>) -> TraceMaterialUniform {
+        TraceMaterialUniform {
+            background: self.background.as_rgba_f32().into(),
+            separator_color: self.separator_color.as_rgba_f32().into(),
+            colors: PLOT_PALETTE.map(|color| color.as_rgba_f32().into()),
+            probe_count: self.probe_count,
+            window: self.window,
+            channel: self.channel as u32,
+            gain: self.gain,
+        }
+    }
+}
+
+
+#[derive(Clone, Default, ShaderType)]
+struct TraceUniform {
+    // two probe locations per element
+    probes: [IVec4; MAX_TRACE_PROBES / 2],
+    probe_count: u32,
+    window: u32,
+}
+
+#[derive(Resource, Default)]
+struct TraceUniformBuffer {
+    buffer: UniformBuffer<TraceUniform>,
+}
+
+fn prepare_trace_uniforms(
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut uniform_buffer: ResMut<TraceUniformBuffer>,
+    traces: Res<Traces>,
+) {
+    let buffer = uniform_buffer.buffer.get_mut();
+
+    buffer.probes = [IVec4::ZERO; MAX_TRACE_PROBES / 2];
+    for (i, probe) in traces.probes.iter().take(MAX_TRACE_PROBES).enumerate() {
+        let probe = probe.as_ivec2();
+        let pair = &mut buffer.probes[i / 2];
+
+        if i % 2 == 0 {
+            pair.x = probe.x;
+            pair.y = probe.y;
+        } else {
+            pair.z = probe.x;
+            pair.w = probe.y;
+        }
+    }
+    buffer.probe_count = traces.probe_count();
+    buffer.window = traces.window();
+
+    uniform_buffer.buffer.write_buffer(&render_device, &render_queue);
+}
+
+
+#[derive(Resource)]
+pub struct TraceBindGroup(pub BindGroup);
+
+fn queue_trace_bind_group(
+    mut commands: Commands,
+    pipeline: Res<TracePipeline>,
+    gpu_images: Res<RenderAssets<Image>>,
+    traces: Res<Traces>,
+    render_device: Res<RenderDevice>,
+    uniform_buffer: Res<TraceUniformBuffer>,
+) {
+    // the texture is created on the first update
+    let Some(texture) = gpu_images.get(&traces.texture) else {
+        commands.remove_resource::<TraceBindGroup>();
+        return;
+    };
+
+    let bind_group = render_device.create_bind_group(&BindGroupDescriptor {
+        label: None,
+        layout: &pipeline.bind_group_layout,
+        entries: &[
+            BindGroupEntry {
+                binding: 0,
+                resource: BindingResource::TextureView(&texture.texture_view),
+            },
+            BindGroupEntry {
+                binding: 1,
+                resource: uniform_buffer.buffer.binding().unwrap(),
+            },
+        ],
+    });
+
+    commands.insert_resource(TraceBindGroup(bind_group));
+}
+
+
+#[derive(Resource)]
+pub struct TracePipeline {
+    pub bind_group_layout: BindGroupLayout,
+    record_pipeline: CachedComputePipelineId,
+}
+
+impl FromWorld for TracePipeline {
+    fn from_world(world: &mut World) -> Self {
+        let automata = world.resource::<AutomataPipeline>();
+
+        let bind_group_layout =
+            world
+                .resource::<RenderDevice>()
+                .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                    label: Some("trace bind group layout"),
+                    entries: &[
+                        BindGroupLayoutEntry {
+                            binding: 0,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::StorageTexture {
+                                access: StorageTextureAccess::ReadWrite,
+                                format: TextureFormat::Rgba32Float,
+                                view_dimension: TextureViewDimension::D2,
+                            },
+                            count: None,
+                        },
+                        BindGroupLayoutEntry {
+                            binding: 1,
+                            visibility: ShaderStages::COMPUTE,
+                            ty: BindingType::Buffer {
+                                ty: BufferBindingType::Uniform,
+                                has_dynamic_offset: false,
+                                min_binding_size: None,
+                            },
+                            count: None,
+                        },
+                    ],
+                });
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let record_pipeline = pipeline_cache.queue_compute_pipeline(ComputePipelineDescriptor {
+            label: None,
+            layout: vec![automata.bind_group_layout.clone(), bind_group_layout.clone()],
+            push_constant_ranges: Vec::new(),
+            shader: TRACE_SHADER_HANDLE.typed(),
+            shader_defs: vec![],
+            entry_point: Cow::from("record"),
+        });
+
+        TracePipeline {
+            bind_group_layout,
+            record_pipeline,
+        }
+    }
+}
+
+
+// records once per simulated step, after the neat node has dispatched it
+#[derive(Default)]
+struct TraceNode {
+    last_step: Option<u64>,
+    record: bool,
+}
+
+impl render_graph::Node for TraceNode {
+    fn update(&mut self, world: &mut World) {
+        let step = world.resource::<AutomataPipeline>().step;
+
+        self.record = step.is_some() && step != self.last_step;
+        self.last_step = step;
+    }
+
+    fn run(
+        &self,
+        _graph: &mut render_graph::RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), render_graph::NodeRunError> {
+        if !self.record || world.resource::<Traces>().probes.is_empty() {
+            return Ok(());
+        }
+
+        let (Some(automata_bind_group), Some(trace_bind_group)) = (
+            world.get_resource::<AutomataBindGroup>(),
+            world.get_resource::<TraceBindGroup>(),
+        ) else {
+            return Ok(());
+        };
+
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let pipeline = world.resource::<TracePipeline>();
+        let Some(record_pipeline) = pipeline_cache.get_compute_pipeline(pipeline.record_pipeline) else {
+            return Ok(());
+        };
+
+        let mut pass = render_context
+            .command_encoder()
+            .begin_compute_pass(&ComputePassDescriptor::default());
+
+        pass.set_bind_group(0, &automata_bind_group.0, &[]);
+        pass.set_bind_group(1, &trace_bind_group.0, &[]);
+        pass.set_pipeline(record_pipeline);
+        pass.dispatch_workgroups(1, 1, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+#import rusty_automata::automata                automata_uniforms, get_state
+
+
+struct TraceUniforms {
+    probes: array<vec4<i32>, 16>,
+    probe_count: u32,
+    window: u32,
+};
+
+@group(1) @binding(0)
+var traces: texture_storage_2d<rgba32float, read_write>;
+
+@group(1) @binding(1)
+var<uniform> trace_uniforms: TraceUniforms;
+
+// must match `MAX_TRACE_PROBES` in mod.rs, the header texel sits in the row after the last probe
+const MAX_TRACE_PROBES: u32 = 32u;
+
+
+fn get_probe(index: u32) -> vec2<i32> {
+    let pair = trace_uniforms.probes[index / 2u];
+    return select(pair.xy, pair.zw, index % 2u == 1u);
+}
+
+@compute @workgroup_size(32, 1, 1)
+fn record(
+    @builtin(local_invocation_id) invocation_id: vec3<u32>,
+) {
+    let probe = invocation_id.x;
+    let header_location = vec2<i32>(0, i32(MAX_TRACE_PROBES));
+
+    // (head, recorded), head is the column written this step
+    let header = textureLoad(traces, header_location);
+    let head = u32(header.x);
+    let recorded = u32(header.y);
+    let window = trace_uniforms.window;
+
+    if (probe < trace_uniforms.probe_count) {
+        let field_size = vec2<i32>(i32(automata_uniforms.width), i32(automata_uniforms.height));
+        let location = (get_probe(probe) % field_size + field_size) % field_size;
+        let value = get_state(location).value;
+
+        let previous = textureLoad(traces, vec2<i32>(i32((head + window - 1u) % window), i32(probe)));
+        let dropped = textureLoad(traces, vec2<i32>(i32(head), i32(probe)));
+
+        // the integral is over the window, so the sample being overwritten leaves it
+        var derivative = 0.0;
+        var integral = value;
+        if (recorded > 0u) {
+            derivative = value - previous.x;
+            integral = previous.z + value;
+        }
+        if (recorded >= window) {
+            integral -= dropped.x;
+        }
+
+        textureStore(traces, vec2<i32>(i32(head), i32(probe)), vec4<f32>(value, derivative, integral, 1.0));
+    }
+
+    // every invocation has read the header texel before it moves on
+    storageBarrier();
+
+    if (probe == 0u) {
+        textureStore(traces, header_location, vec4<f32>(f32((head + 1u) % window), f32(min(recorded + 1u, window)), 0.0, 0.0));
+    }
+}
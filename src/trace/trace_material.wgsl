@@ -0,0 +1,73 @@
+#import rusty_automata::plot                    draw_curve, mix_color
+
+#import bevy_sprite::mesh2d_vertex_output       MeshVertexOutput
+
+
+struct TraceMaterial {
+    background: vec4<f32>,
+    separator_color: vec4<f32>,
+    colors: array<vec4<f32>, 8>,
+    probe_count: u32,
+    window: u32,
+    channel: u32,
+    gain: f32,
+};
+
+@group(1) @binding(0)
+var<uniform> material: TraceMaterial;
+
+@group(1) @binding(1)
+var traces: texture_2d<f32>;
+
+// must match `MAX_TRACE_PROBES` in mod.rs
+const MAX_TRACE_PROBES: i32 = 32;
+
+
+// `age` steps before the newest sample, `head` is the column the next step is written to
+fn load_sample(probe: u32, head: u32, age: u32) -> f32 {
+    let window = material.window;
+    let column = (head + window - 1u - age % window) % window;
+    return textureLoad(traces, vec2<i32>(i32(column), i32(probe)), 0)[material.channel];
+}
+
+
+@fragment
+fn fragment(
+    in: MeshVertexOutput,
+) -> @location(0) vec4<f32> {
+    var col: vec4<f32> = material.background;
+
+    if (material.probe_count == 0u) {
+        return col;
+    }
+
+    // one row per probe from the top, each spanning -1 to 1
+    let rows = in.uv.y * f32(material.probe_count);
+    let probe = min(u32(rows), material.probe_count - 1u);
+    let y = 1.0 - 2.0 * (rows - f32(probe));
+
+    let header = textureLoad(traces, vec2<i32>(0, MAX_TRACE_PROBES), 0);
+    let head = u32(header.x);
+    let recorded = f32(header.y);
+
+    // newest step on the right
+    let age = (1.0 - in.uv.x) * f32(material.window - 1u);
+    let newer = u32(age);
+    let value = mix(
+        load_sample(probe, head, newer),
+        load_sample(probe, head, newer + 1u),
+        fract(age),
+    ) * material.gain;
+    let coverage = select(0.0, 1.0, age <= recorded - 1.0);
+
+    // row separators and zero lines
+    let dy = abs(dpdy(y));
+    let edge = 1.0 - smoothstep(0.0, dy * 1.5, 1.0 - abs(y));
+    let zero = 1.0 - smoothstep(0.0, dy * 1.5, abs(y));
+    col = mix_color(col, material.separator_color, max(edge, zero * 0.5));
+
+    let color = material.colors[probe % 8u];
+    col = draw_curve(col, y, clamp(value, -1.0, 1.0), color * vec4<f32>(1.0, 1.0, 1.0, coverage));
+
+    return col;
+}
@@ -0,0 +1,331 @@
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+use super::Uaf;
+
+
+const PARAMETERS: usize = 5;
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FitMethod {
+    #[default]
+    LevenbergMarquardt,
+    Adam,
+}
+
+impl FitMethod {
+    pub const ALL: [FitMethod; 2] = [
+        FitMethod::LevenbergMarquardt,
+        FitMethod::Adam,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FitMethod::LevenbergMarquardt => "levenberg-marquardt",
+            FitMethod::Adam => "adam",
+        }
+    }
+}
+
+
+// least squares fit of the five uaf parameters to a target function sampled uniformly over `range`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UafFit {
+    pub method: FitMethod,
+    pub range: (f32, f32),
+    pub samples: usize,
+    pub max_iterations: usize,
+    // stop once the mean squared error improves by less than this
+    pub tolerance: f32,
+    // adam step size, ignored by levenberg-marquardt
+    pub learning_rate: f32,
+}
+
+impl Default for UafFit {
+    fn default() -> Self {
+        Self {
+            method: FitMethod::default(),
+            range: (-5.0, 5.0),
+            samples: 256,
+            max_iterations: 2000,
+            tolerance: 1e-10,
+            learning_rate: 0.02,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FitResult {
+    pub uaf: Uaf,
+    // root mean squared error over the fitted samples
+    pub error: f32,
+    pub iterations: usize,
+}
+
+impl UafFit {
+    pub fn with_method(mut self, method: FitMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    pub fn fit(&self, initial: Uaf, target: impl Fn(f32) -> f32) -> FitResult {
+        let (start, end) = self.range;
+        let samples = self.samples.max(2);
+
+        let points = (0..samples)
+            .map(|i| {
+                let x = start + (end - start) * i as f32 / (samples - 1) as f32;
+                (x, target(x))
+            })
+            .collect::<Vec<_>>();
+
+        self.fit_points(initial, &points)
+    }
+
+    pub fn fit_points(&self, initial: Uaf, points: &[(f32, f32)]) -> FitResult {
+        match self.method {
+            FitMethod::LevenbergMarquardt => self.levenberg_marquardt(initial, points),
+            FitMethod::Adam => self.adam(initial, points),
+        }
+    }
+
+    fn levenberg_marquardt(&self, initial: Uaf, points: &[(f32, f32)]) -> FitResult {
+        let mut parameters = to_f64(initial);
+        let mut error = mean_squared_error(parameters, points);
+        let mut damping = 1e-3;
+        let mut iterations = 0;
+
+        while iterations < self.max_iterations && error > 0.0 {
+            iterations += 1;
+
+            // normal equations (JᵀJ + λ diag(JᵀJ)) δ = Jᵀr
+            let mut jtj = [[0.0; PARAMETERS]; PARAMETERS];
+            let mut jtr = [0.0; PARAMETERS];
+            for &(x, y) in points {
+                let uaf = from_f64(parameters);
+                let residual = y as f64 - uaf.eval(x) as f64;
+                let jacobian = uaf.gradient(x).map(f64::from);
+
+                for i in 0..PARAMETERS {
+                    jtr[i] += jacobian[i] * residual;
+                    for j in 0..PARAMETERS {
+                        jtj[i][j] += jacobian[i] * jacobian[j];
+                    }
+                }
+            }
+
+            let mut improved = false;
+            while damping < 1e12 {
+                let mut system = jtj;
+                for (i, row) in system.iter_mut().enumerate() {
+                    row[i] += damping * row[i].max(1e-9);
+                }
+
+                if let Some(step) = solve(system, jtr) {
+                    let mut candidate = parameters;
+                    for (parameter, delta) in candidate.iter_mut().zip(step) {
+                        *parameter += delta;
+                    }
+
+                    let candidate_error = mean_squared_error(candidate, points);
+                    if candidate_error.is_finite() && candidate_error < error {
+                        let improvement = error - candidate_error;
+
+                        parameters = candidate;
+                        error = candidate_error;
+                        damping = (damping / 3.0).max(1e-12);
+                        improved = improvement >= self.tolerance as f64;
+                        break;
+                    }
+                }
+
+                damping *= 4.0;
+            }
+
+            if !improved {
+                break;
+            }
+        }
+
+        FitResult {
+            uaf: from_f64(parameters),
+            error: error.sqrt() as f32,
+            iterations,
+        }
+    }
+
+    fn adam(&self, initial: Uaf, points: &[(f32, f32)]) -> FitResult {
+        const BETA1: f64 = 0.9;
+        const BETA2: f64 = 0.999;
+        const EPSILON: f64 = 1e-8;
+
+        let mut parameters = to_f64(initial);
+        let mut best = (parameters, mean_squared_error(parameters, points));
+        let mut previous = best.1;
+
+        let mut m = [0.0; PARAMETERS];
+        let mut v = [0.0; PARAMETERS];
+        let mut iterations = 0;
+
+        while iterations < self.max_iterations {
+            iterations += 1;
+
+            let uaf = from_f64(parameters);
+            let mut gradient = [0.0; PARAMETERS];
+            for &(x, y) in points {
+                let residual = uaf.eval(x) as f64 - y as f64;
+                for (g, partial) in gradient.iter_mut().zip(uaf.gradient(x)) {
+                    *g += 2.0 * residual * partial as f64 / points.len() as f64;
+                }
+            }
+
+            let t = iterations as i32;
+            for i in 0..PARAMETERS {
+                m[i] = BETA1 * m[i] + (1.0 - BETA1) * gradient[i];
+                v[i] = BETA2 * v[i] + (1.0 - BETA2) * gradient[i] * gradient[i];
+
+                let m_hat = m[i] / (1.0 - BETA1.powi(t));
+                let v_hat = v[i] / (1.0 - BETA2.powi(t));
+                parameters[i] -= self.learning_rate as f64 * m_hat / (v_hat.sqrt() + EPSILON);
+            }
+
+            let error = mean_squared_error(parameters, points);
+            if error.is_finite() && error < best.1 {
+                best = (parameters, error);
+            }
+
+            if (previous - error).abs() < self.tolerance as f64 {
+                break;
+            }
+            previous = error;
+        }
+
+        FitResult {
+            uaf: from_f64(best.0),
+            error: best.1.sqrt() as f32,
+            iterations,
+        }
+    }
+}
+
+impl Uaf {
+    pub fn fit(initial: Uaf, target: impl Fn(f32) -> f32) -> FitResult {
+        UafFit::default().fit(initial, target)
+    }
+}
+
+
+fn mean_squared_error(parameters: [f64; PARAMETERS], points: &[(f32, f32)]) -> f64 {
+    let uaf = from_f64(parameters);
+
+    points
+        .iter()
+        .map(|&(x, y)| (uaf.eval(x) as f64 - y as f64).powi(2))
+        .sum::<f64>() / points.len().max(1) as f64
+}
+
+fn to_f64(uaf: Uaf) -> [f64; PARAMETERS] {
+    uaf.to_array().map(f64::from)
+}
+
+fn from_f64(parameters: [f64; PARAMETERS]) -> Uaf {
+    Uaf::from_array(parameters.map(|parameter| parameter as f32))
+}
+
+// gaussian elimination with partial pivoting
+fn solve(
+    mut a: [[f64; PARAMETERS]; PARAMETERS],
+    mut b: [f64; PARAMETERS],
+) -> Option<[f64; PARAMETERS]> {
+    for column in 0..PARAMETERS {
+        let pivot = (column..PARAMETERS)
+            .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))?;
+        if a[pivot][column].abs() < 1e-15 {
+            return None;
+        }
+
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+
+        for row in column + 1..PARAMETERS {
+            let factor = a[row][column] / a[column][column];
+
+            let (upper, lower) = a.split_at_mut(row);
+            for (value, pivot) in lower[0][column..].iter_mut().zip(&upper[column][column..]) {
+                *value -= factor * pivot;
+            }
+            b[row] -= factor * b[column];
+        }
+    }
+
+    let mut x = [0.0; PARAMETERS];
+    for row in (0..PARAMETERS).rev() {
+        let sum = (row + 1..PARAMETERS).map(|k| a[row][k] * x[k]).sum::<f64>();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+
+    Some(x)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uaf::UafPreset;
+
+    fn rmse(uaf: Uaf, target: impl Fn(f32) -> f32) -> f32 {
+        let points: Vec<(f32, f32)> = (0..=100)
+            .map(|i| {
+                let x = -5.0 + i as f32 * 0.1;
+                (x, target(x))
+            })
+            .collect();
+
+        mean_squared_error(to_f64(uaf), &points).sqrt() as f32
+    }
+
+    #[test]
+    fn levenberg_marquardt_recovers_a_generating_uaf() {
+        let truth = Uaf::new(1.5, 0.3, 0.05, 0.8, -0.2);
+        let fit = Uaf::fit(Uaf::SIGMOID, |x| truth.eval(x));
+
+        assert!(fit.error < 1e-3, "error {}", fit.error);
+        for (fitted, expected) in fit.uaf.to_array().iter().zip(truth.to_array()) {
+            assert!((fitted - expected).abs() < 1e-2, "{:?} != {truth:?}", fit.uaf);
+        }
+    }
+
+    #[test]
+    fn levenberg_marquardt_matches_the_fitted_presets() {
+        // the presets are least squares fits over the default range, a perturbed start finds them again
+        for preset in [UafPreset::Tanh, UafPreset::Gelu] {
+            let start = Uaf::from_array(preset.uaf().to_array().map(|parameter| parameter * 1.1 + 0.05));
+            let fit = Uaf::fit(start, |x| preset.reference(x));
+            let preset_error = rmse(preset.uaf(), |x| preset.reference(x));
+
+            assert!(fit.error <= preset_error + 1e-3, "{}: {} > {preset_error}", preset.name(), fit.error);
+            assert!((fit.uaf.eval(1.0) - preset.uaf().eval(1.0)).abs() < 1e-2, "{}: {:?}", preset.name(), fit.uaf);
+        }
+    }
+
+    #[test]
+    fn adam_approaches_the_sigmoid() {
+        let fit = UafFit::default()
+            .with_method(FitMethod::Adam)
+            .fit(Uaf::IDENTITY, |x| UafPreset::Sigmoid.reference(x));
+
+        assert!(fit.error < 0.05, "error {}", fit.error);
+        assert!(fit.iterations <= UafFit::default().max_iterations);
+    }
+
+    #[test]
+    fn a_perfect_start_stops_immediately() {
+        let fit = Uaf::fit(Uaf::SOFTPLUS, |x| Uaf::SOFTPLUS.eval(x));
+
+        assert_eq!(fit.uaf, Uaf::SOFTPLUS);
+        assert_eq!(fit.iterations, 0);
+    }
+}
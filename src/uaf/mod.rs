@@ -4,8 +4,16 @@ use bevy::{
         HandleUntyped,
     },
     prelude::*,
-    reflect::TypeUuid
+    reflect::TypeUuid,
+    render::render_resource::ShaderType,
 };
+use serde::{
+    Deserialize,
+    Serialize,
+};
+
+mod fit;
+pub use fit::*;
 
 
 const UAF_SHADER_HANDLE: HandleUntyped = HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 61270573934);
@@ -23,3 +31,176 @@ impl Plugin for UafPlugin {
         );
     }
 }
+
+
+// universal activation function - https://arxiv.org/pdf/2011.03842.pdf
+//   f(x) = softplus(a(x + b) + cx²) - softplus(d(x - b)) + e
+// mirrors `fUAF` in uaf.wgsl, and lays out the same as its `UafParameters`
+#[derive(Clone, Copy, Debug, PartialEq, Reflect, Serialize, Deserialize, ShaderType)]
+pub struct Uaf {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+}
+
+impl Default for Uaf {
+    fn default() -> Self {
+        Self::SIGMOID
+    }
+}
+
+impl Uaf {
+    pub const IDENTITY: Self = Self::new(1.0, 0.0, 0.0, -1.0, 0.0);
+    pub const SIGMOID: Self = Self::new(1.016_052_9, 0.492100, 0.0, 1.016_052_9, 0.0);
+    // d = 0 leaves a constant softplus(0) = ln 2, cancelled by e
+    pub const SOFTPLUS: Self = Self::new(1.0, 0.0, 0.0, 0.0, std::f32::consts::LN_2);
+    // sharper with larger a, the slope is a - d = 1 for x >> 0
+    pub const RELU: Self = Self::new(16.0, 0.0, 0.0, 15.0, 0.0);
+    pub const STEP: Self = Self::new(32.0, 0.015625, 0.0, 32.0, 0.0);
+    // least squares fits over [-5, 5]
+    pub const TANH: Self = Self::new(2.136272, 0.467216, 0.0, 2.137913, -0.998367);
+    pub const GELU: Self = Self::new(2.315071, -0.011627, -0.005346, 1.283308, 0.010348);
+
+    pub const fn new(a: f32, b: f32, c: f32, d: f32, e: f32) -> Self {
+        Self { a, b, c, d, e }
+    }
+
+    // uaf activation texels only store a, b, c and d
+    pub fn from_texel(texel: Vec4, e: f32) -> Self {
+        Self::new(texel.x, texel.y, texel.z, texel.w, e)
+    }
+
+    pub fn texel(&self) -> Vec4 {
+        Vec4::new(self.a, self.b, self.c, self.d)
+    }
+
+    pub fn to_array(&self) -> [f32; 5] {
+        [self.a, self.b, self.c, self.d, self.e]
+    }
+
+    pub fn from_array([a, b, c, d, e]: [f32; 5]) -> Self {
+        Self::new(a, b, c, d, e)
+    }
+
+    pub fn eval(&self, x: f32) -> f32 {
+        let (p1, p2) = self.inner(x);
+        softplus(p1) - softplus(p2) + self.e
+    }
+
+    // df/dx = σ(p1)(a + 2cx) - σ(p2)d
+    pub fn derivative(&self, x: f32) -> f32 {
+        let (p1, p2) = self.inner(x);
+        sigmoid(p1) * (self.a + 2.0 * self.c * x) - sigmoid(p2) * self.d
+    }
+
+    // [df/da, df/db, df/dc, df/dd, df/de]
+    pub fn gradient(&self, x: f32) -> [f32; 5] {
+        let (p1, p2) = self.inner(x);
+        let (s1, s2) = (sigmoid(p1), sigmoid(p2));
+
+        [
+            s1 * (x + self.b),
+            s1 * self.a + s2 * self.d,
+            s1 * x * x,
+            -s2 * (x - self.b),
+            1.0,
+        ]
+    }
+
+    fn inner(&self, x: f32) -> (f32, f32) {
+        (
+            self.a * (x + self.b) + self.c * x * x,
+            self.d * (x - self.b),
+        )
+    }
+}
+
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect, Serialize, Deserialize)]
+pub enum UafPreset {
+    Identity,
+    #[default]
+    Sigmoid,
+    Tanh,
+    Relu,
+    Softplus,
+    Gelu,
+    Step,
+}
+
+impl UafPreset {
+    pub const ALL: [UafPreset; 7] = [
+        UafPreset::Identity,
+        UafPreset::Sigmoid,
+        UafPreset::Tanh,
+        UafPreset::Relu,
+        UafPreset::Softplus,
+        UafPreset::Gelu,
+        UafPreset::Step,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            UafPreset::Identity => "identity",
+            UafPreset::Sigmoid => "sigmoid",
+            UafPreset::Tanh => "tanh",
+            UafPreset::Relu => "relu",
+            UafPreset::Softplus => "softplus",
+            UafPreset::Gelu => "gelu",
+            UafPreset::Step => "step",
+        }
+    }
+
+    // position in `ALL`, as used by `uaf_reference` in uaf.wgsl
+    pub fn index(&self) -> u32 {
+        Self::ALL.iter().position(|preset| preset == self).unwrap() as u32
+    }
+
+    pub fn uaf(&self) -> Uaf {
+        match self {
+            UafPreset::Identity => Uaf::IDENTITY,
+            UafPreset::Sigmoid => Uaf::SIGMOID,
+            UafPreset::Tanh => Uaf::TANH,
+            UafPreset::Relu => Uaf::RELU,
+            UafPreset::Softplus => Uaf::SOFTPLUS,
+            UafPreset::Gelu => Uaf::GELU,
+            UafPreset::Step => Uaf::STEP,
+        }
+    }
+
+    // the exact function the preset approximates
+    pub fn reference(&self, x: f32) -> f32 {
+        match self {
+            UafPreset::Identity => x,
+            UafPreset::Sigmoid => sigmoid(x),
+            UafPreset::Tanh => x.tanh(),
+            UafPreset::Relu => x.max(0.0),
+            UafPreset::Softplus => softplus(x),
+            // tanh approximation, std has no erf
+            UafPreset::Gelu => 0.5 * x * (1.0 + (0.797_884_6 * (x + 0.044715 * x * x * x)).tanh()),
+            UafPreset::Step => if x > 0.0 { 1.0 } else { 0.0 },
+        }
+    }
+}
+
+impl From<UafPreset> for Uaf {
+    fn from(preset: UafPreset) -> Self {
+        preset.uaf()
+    }
+}
+
+
+pub fn softplus(x: f32) -> f32 {
+    x.max(0.0) + (-x.abs()).exp().ln_1p()
+}
+
+pub fn sigmoid(x: f32) -> f32 {
+    if x >= 0.0 {
+        1.0 / (1.0 + (-x).exp())
+    } else {
+        let z = x.exp();
+        z / (1.0 + z)
+    }
+}
@@ -15,6 +15,18 @@ fn fUAF(x: f32, a: f32, b: f32, c: f32, d: f32, e: f32) -> f32 {
     return p3 - p4 + e;
 }
 
+fn sigmoid(x: f32) -> f32 {
+    return 1.0 / (1.0 + exp(-x));
+}
+
+// analytic df/dx, see `Uaf::derivative`
+fn fUAFdx(x: f32, a: f32, b: f32, c: f32, d: f32) -> f32 {
+    let p1: f32 = (a * (x + b)) + (c * (x * x));
+    let p2: f32 = (d * (x - b));
+
+    return sigmoid(p1) * (a + 2.0 * c * x) - sigmoid(p2) * d;
+}
+
 
 struct UafParameters {
     a: f32,
@@ -27,3 +39,35 @@ struct UafParameters {
 fn fUAFp(x: f32, params: UafParameters) -> f32 {
     return fUAF(x, params.a, params.b, params.c, params.d, params.e);
 }
+
+fn fUAFpdx(x: f32, params: UafParameters) -> f32 {
+    return fUAFdx(x, params.a, params.b, params.c, params.d);
+}
+
+
+// the exact function a preset approximates, `preset` indexes `UafPreset::ALL`
+fn uaf_reference(preset: u32, x: f32) -> f32 {
+    switch preset {
+        case 1u: {
+            return sigmoid(x);
+        }
+        case 2u: {
+            return tanh(x);
+        }
+        case 3u: {
+            return max(x, 0.0);
+        }
+        case 4u: {
+            return max(x, 0.0) + log1p(exp(-abs(x)));
+        }
+        case 5u: {
+            return 0.5 * x * (1.0 + tanh(0.7978846 * (x + 0.044715 * x * x * x)));
+        }
+        case 6u: {
+            return select(0.0, 1.0, x > 0.0);
+        }
+        default: {
+            return x;
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use std::sync::{
+    atomic::{
+        AtomicU64,
+        Ordering,
+    },
+    Arc,
+};
+
+use bevy::{
+    prelude::*,
+    render::render_resource::Extent3d,
+};
+
+use rusty_automata::{
+    RustyAutomataApp,
+    automata::{
+        AutomataField,
+        AutomataPlugin,
+        StepLimit,
+    },
+    neat::{
+        NeatField,
+        NeatPlugin,
+    },
+    readback::{
+        FieldSnapshot,
+        Readback,
+        ReadbackPlugin,
+    },
+};
+
+
+const STEPS: u64 = 20;
+
+
+// same backends as `RustyAutomataApp::headless`
+//   bevy requests the adapter in a detached task, without one the app never becomes ready instead of panicking
+fn adapter_available() -> bool {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::PRIMARY),
+        ..default()
+    });
+
+    futures_lite::future::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+        power_preference: wgpu::PowerPreference::HighPerformance,
+        ..default()
+    })).is_some()
+}
+
+
+// runs a small field for a few steps without a window, skipped when wgpu finds no adapter
+//   unless `RUSTY_AUTOMATA_REQUIRE_ADAPTER` is set, e.g. in ci with lavapipe installed
+#[test]
+fn headless_app_steps_and_exits() {
+    if !adapter_available() {
+        assert!(
+            std::env::var_os("RUSTY_AUTOMATA_REQUIRE_ADAPTER").is_none(),
+            "no wgpu adapter found, but RUSTY_AUTOMATA_REQUIRE_ADAPTER is set",
+        );
+        eprintln!("skipping headless smoke test, no wgpu adapter found");
+        return;
+    }
+
+    let last_step = Arc::new(AtomicU64::new(0));
+    let observed = last_step.clone();
+
+    App::new()
+        .add_plugins((
+            RustyAutomataApp::headless(),
+            AutomataPlugin,
+            NeatPlugin,
+            ReadbackPlugin,
+        ))
+        .insert_resource(StepLimit::new(STEPS))
+        .add_systems(Startup, setup)
+        .add_systems(Update, move |mut snapshots: EventReader<FieldSnapshot>| {
+            for snapshot in snapshots.iter() {
+                observed.fetch_max(snapshot.step, Ordering::Relaxed);
+            }
+        })
+        .run();
+
+    assert!(last_step.load(Ordering::Relaxed) > 0, "no snapshot was read back before the step limit");
+}
+
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let field_size = Extent3d {
+        width: 32,
+        height: 32,
+        depth_or_array_layers: 1,
+    };
+
+    let mut readback = Readback::every(5);
+    readback.edges = false;
+    readback.uaf = false;
+    commands.insert_resource(readback);
+
+    commands.insert_resource(AutomataField::new(field_size, 4, &mut images));
+    commands.insert_resource(NeatField::new(field_size, &mut images));
+}